@@ -0,0 +1,94 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::structs::consulta::StatusConsulta;
+
+pub const ARQUIVO_CONFIG: &str = "config.json";
+pub const VARIAVEL_DIRETORIO_DADOS: &str = "ARQUIVO_DADOS_DIR";
+const ARGUMENTO_DIRETORIO_DADOS: &str = "--dados";
+const ARGUMENTO_MEMORIA: &str = "--memoria";
+const ARGUMENTO_PESQUISA: &str = "--pesquisa";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub diretorio_dados: Option<PathBuf>,
+    /// Cria uma chave na primeira execução se o diretório ainda não tiver uma.
+    pub criptografia: bool,
+    /// Nomes dos arquivos (ex.: `"pacientes.dat"`) cujos registros novos são comprimidos.
+    pub arquivos_comprimidos: Vec<String>,
+    /// Status (ex.: `["realizada", "faltou"]`) das consultas que entram no
+    /// faturamento e nos totais dos relatórios. Padrão: só as realizadas.
+    pub status_faturados: Option<Vec<StatusConsulta>>,
+    /// Nomes das regras de validação aplicadas ao agendar (ex.:
+    /// `["exame_da_especialidade"]`). Sem o campo, todas ficam ativas.
+    pub regras_agendamento: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Lê `config.json` do diretório atual; a ausência do arquivo não é erro.
+    pub fn carregar(caminho: &Path) -> Result<Config, io::Error> {
+        match fs::read_to_string(caminho) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn status_faturados(&self) -> Vec<StatusConsulta> {
+        self.status_faturados
+            .clone()
+            .unwrap_or_else(|| vec![StatusConsulta::Realizada])
+    }
+}
+
+/// Decide onde ficam os arquivos de dados, nesta ordem de prioridade:
+/// `--dados <dir>` na linha de comando, variável `ARQUIVO_DADOS_DIR`,
+/// `diretorio_dados` em `config.json` e, por fim, o diretório atual.
+pub fn resolver_diretorio_dados(args: &[String], config: &Config) -> PathBuf {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == ARGUMENTO_DIRETORIO_DADOS {
+            if let Some(dir) = iter.next() {
+                return PathBuf::from(dir);
+            }
+        } else if let Some(dir) = arg.strip_prefix("--dados=") {
+            return PathBuf::from(dir);
+        }
+    }
+
+    if let Ok(dir) = env::var(VARIAVEL_DIRETORIO_DADOS)
+        && !dir.is_empty()
+    {
+        return PathBuf::from(dir);
+    }
+
+    config
+        .diretorio_dados
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// `--memoria` abre os dados em RAM: útil para treinar ou testar sem alterar os `.dat`.
+pub fn usar_memoria(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == ARGUMENTO_MEMORIA)
+}
+
+/// `--pesquisa "<consulta>"` executa a consulta (ver `db::query`), imprime os
+/// registros encontrados e encerra sem abrir o menu.
+pub fn pesquisa(args: &[String]) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == ARGUMENTO_PESQUISA {
+            return iter.next().cloned();
+        } else if let Some(texto) = arg.strip_prefix("--pesquisa=") {
+            return Some(texto.to_string());
+        }
+    }
+    None
+}
@@ -0,0 +1,257 @@
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const DIRETORIO_BACKUPS: &str = "backups";
+const ARQUIVO_MANIFESTO: &str = "manifesto.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArquivoBackup {
+    pub nome: String,
+    pub tamanho: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifesto {
+    pub criado_em: String,
+    pub arquivos: Vec<ArquivoBackup>,
+}
+
+/// Copia os arquivos de dados para `backups/backup_AAAAMMDD_HHMMSS` dentro de
+/// `diretorio_dados`, junto com um manifesto contendo tamanho e SHA-256 de cada um.
+///
+/// Os índices ficam apenas em memória e são reconstruídos ao abrir cada arquivo,
+/// então basta copiar os `.dat`. Quem chama deve sincronizar os `FileManager`s antes.
+pub fn criar_backup(diretorio_dados: &Path, arquivos: &[&str]) -> io::Result<PathBuf> {
+    let agora = Local::now();
+    let destino = diretorio_dados
+        .join(DIRETORIO_BACKUPS)
+        .join(format!("backup_{}", agora.format("%Y%m%d_%H%M%S")));
+    if destino.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("o backup {} já existe", destino.display()),
+        ));
+    }
+    fs::create_dir_all(&destino)?;
+
+    let mut manifesto = Manifesto {
+        criado_em: agora.to_rfc3339(),
+        arquivos: Vec::new(),
+    };
+
+    for nome in arquivos {
+        let origem = diretorio_dados.join(nome);
+        if !origem.exists() {
+            continue;
+        }
+        let conteudo = fs::read(&origem)?;
+        fs::write(destino.join(nome), &conteudo)?;
+        manifesto.arquivos.push(ArquivoBackup {
+            nome: nome.to_string(),
+            tamanho: conteudo.len() as u64,
+            sha256: calcular_sha256(&conteudo),
+        });
+    }
+
+    let json = serde_json::to_string_pretty(&manifesto)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(destino.join(ARQUIVO_MANIFESTO), json)?;
+
+    Ok(destino)
+}
+
+/// Lista os diretórios de backup existentes, do mais antigo para o mais recente.
+pub fn listar_backups(diretorio_dados: &Path) -> io::Result<Vec<String>> {
+    let raiz = diretorio_dados.join(DIRETORIO_BACKUPS);
+    if !raiz.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entrada in fs::read_dir(raiz)? {
+        let entrada = entrada?;
+        if entrada.path().join(ARQUIVO_MANIFESTO).is_file() {
+            backups.push(entrada.file_name().to_string_lossy().into_owned());
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Se o backup guardou `arquivo` (ex.: `chave.json`), pela lista do manifesto.
+pub fn contem_arquivo(diretorio_dados: &Path, nome_backup: &str, arquivo: &str) -> io::Result<bool> {
+    validar_nome(nome_backup)?;
+    let json = fs::read_to_string(
+        diretorio_dados
+            .join(DIRETORIO_BACKUPS)
+            .join(nome_backup)
+            .join(ARQUIVO_MANIFESTO),
+    )?;
+    let manifesto: Manifesto = serde_json::from_str(&json)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(manifesto.arquivos.iter().any(|a| a.nome == arquivo))
+}
+
+/// Apaga o diretório do backup com todos os seus arquivos.
+pub fn remover_backup(diretorio_dados: &Path, nome_backup: &str) -> io::Result<()> {
+    validar_nome(nome_backup)?;
+    fs::remove_dir_all(diretorio_dados.join(DIRETORIO_BACKUPS).join(nome_backup))
+}
+
+/// Confere todos os arquivos do backup contra o manifesto sem alterar nada.
+pub fn validar_backup(diretorio_backup: &Path) -> io::Result<Manifesto> {
+    let json = fs::read_to_string(diretorio_backup.join(ARQUIVO_MANIFESTO))?;
+    let manifesto: Manifesto = serde_json::from_str(&json)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    for arquivo in &manifesto.arquivos {
+        validar_nome(&arquivo.nome)?;
+        let conteudo = fs::read(diretorio_backup.join(&arquivo.nome))?;
+        if conteudo.len() as u64 != arquivo.tamanho || calcular_sha256(&conteudo) != arquivo.sha256 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("o arquivo {} não confere com o manifesto", arquivo.nome),
+            ));
+        }
+    }
+
+    Ok(manifesto)
+}
+
+/// Valida o backup e faz o diretório voltar a ser exatamente o do backup:
+/// os arquivos do manifesto substituem os atuais e os de `arquivos` que o
+/// backup não tem (ex.: um `chave.json` criado depois) são retirados.
+///
+/// Cada arquivo é copiado primeiro para um temporário e depois renomeado por cima
+/// do original, para que uma falha no meio da cópia não deixe um `.dat` truncado.
+/// Os originais ficam guardados até todos os arquivos serem trocados; se uma troca
+/// falhar, as anteriores são desfeitas e os dados continuam como estavam.
+/// Os arquivos não podem estar abertos durante a restauração (ver `Database::fechar`).
+pub fn restaurar_backup(
+    diretorio_dados: &Path,
+    nome_backup: &str,
+    arquivos: &[&str],
+) -> io::Result<Manifesto> {
+    validar_nome(nome_backup)?;
+    let diretorio_backup = diretorio_dados.join(DIRETORIO_BACKUPS).join(nome_backup);
+    let manifesto = validar_backup(&diretorio_backup)?;
+
+    let mut temporarios = Vec::new();
+    for arquivo in &manifesto.arquivos {
+        let temporario = diretorio_dados.join(format!("{}.restaurando", arquivo.nome));
+        if let Err(e) = fs::copy(diretorio_backup.join(&arquivo.nome), &temporario) {
+            for (temporario, _) in &temporarios {
+                let _ = fs::remove_file(temporario);
+            }
+            return Err(e);
+        }
+        temporarios.push((temporario, diretorio_dados.join(&arquivo.nome)));
+    }
+    let fora_do_backup: Vec<PathBuf> = arquivos
+        .iter()
+        .filter(|nome| !manifesto.arquivos.iter().any(|a| a.nome == **nome))
+        .map(|nome| diretorio_dados.join(nome))
+        .collect();
+
+    let mut trocados: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+    for (i, (temporario, destino)) in temporarios.iter().enumerate() {
+        match substituir(temporario, destino) {
+            Ok(anterior) => trocados.push((destino.clone(), anterior)),
+            Err(e) => {
+                desfazer(&trocados);
+                for (temporario, _) in &temporarios[i..] {
+                    let _ = fs::remove_file(temporario);
+                }
+                return Err(e);
+            }
+        }
+    }
+    for destino in fora_do_backup {
+        match afastar(&destino) {
+            Ok(Some(anterior)) => trocados.push((destino, Some(anterior))),
+            Ok(None) => {}
+            Err(e) => {
+                desfazer(&trocados);
+                return Err(e);
+            }
+        }
+    }
+
+    for (_, anterior) in trocados {
+        if let Some(anterior) = anterior {
+            let _ = fs::remove_file(anterior);
+        }
+    }
+
+    Ok(manifesto)
+}
+
+/// Nomes de backup e de arquivo do manifesto são sempre um único componente:
+/// nada de separadores, `..` ou caminhos absolutos que saiam dos diretórios de dados.
+fn validar_nome(nome: &str) -> io::Result<()> {
+    let mut componentes = Path::new(nome).components();
+    let valido = !nome.contains(['/', '\\'])
+        && matches!(componentes.next(), Some(Component::Normal(_)))
+        && componentes.next().is_none();
+    if valido {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("nome inválido: {}", nome),
+        ))
+    }
+}
+
+/// Renomeia `destino` para `<nome>.anterior`. Devolve onde ele ficou, ou
+/// `None` se não existia.
+fn afastar(destino: &Path) -> io::Result<Option<PathBuf>> {
+    if !destino.exists() {
+        return Ok(None);
+    }
+    let mut nome = destino.as_os_str().to_owned();
+    nome.push(".anterior");
+    let anterior = PathBuf::from(nome);
+    fs::rename(destino, &anterior)?;
+    Ok(Some(anterior))
+}
+
+/// Troca `destino` pelo temporário, guardando o original em `<nome>.anterior`.
+/// Devolve onde o original ficou, ou `None` se ainda não existia.
+fn substituir(temporario: &Path, destino: &Path) -> io::Result<Option<PathBuf>> {
+    let anterior = afastar(destino)?;
+    if let Err(e) = fs::rename(temporario, destino) {
+        if let Some(anterior) = &anterior {
+            let _ = fs::rename(anterior, destino);
+        }
+        return Err(e);
+    }
+    Ok(anterior)
+}
+
+/// Devolve os originais das trocas já feitas, da última para a primeira.
+fn desfazer(trocados: &[(PathBuf, Option<PathBuf>)]) {
+    for (destino, anterior) in trocados.iter().rev() {
+        match anterior {
+            Some(anterior) => {
+                let _ = fs::rename(anterior, destino);
+            }
+            None => {
+                let _ = fs::remove_file(destino);
+            }
+        }
+    }
+}
+
+fn calcular_sha256(conteudo: &[u8]) -> String {
+    Sha256::digest(conteudo)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -0,0 +1,131 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload, rand_core::RngCore};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+
+pub const ARQUIVO_CHAVE: &str = "chave.json";
+pub const VARIAVEL_SENHA: &str = "ARQUIVO_SENHA";
+
+const TAMANHO_SALT: usize = 16;
+const TAMANHO_NONCE: usize = 12;
+const TEXTO_VERIFICADOR: &[u8] = b"arquivo-indexado-rust";
+
+/// Chave simétrica usada pelo `FileManager` para cifrar cada registro com
+/// ChaCha20-Poly1305. O payload cifrado é `nonce (12 bytes) || texto cifrado || tag`;
+/// os dados associados (`aad`) são autenticados mas não gravados.
+#[derive(Clone)]
+pub struct Cipher {
+    aead: ChaCha20Poly1305,
+}
+
+impl Cipher {
+    /// Deriva a chave da senha com Argon2id.
+    pub fn from_passphrase(senha: &str, salt: &[u8]) -> Result<Cipher, io::Error> {
+        let mut chave = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(senha.as_bytes(), salt, &mut chave)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(Cipher {
+            aead: ChaCha20Poly1305::new(Key::from_slice(&chave)),
+        })
+    }
+
+    pub fn encrypt(&self, dados: &[u8], aad: &[u8]) -> Result<Vec<u8>, io::Error> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let cifrado = self
+            .aead
+            .encrypt(&nonce, Payload { msg: dados, aad })
+            .map_err(|_| io::Error::other("falha ao cifrar registro"))?;
+
+        let mut bytes = Vec::with_capacity(TAMANHO_NONCE + cifrado.len());
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&cifrado);
+        Ok(bytes)
+    }
+
+    pub fn decrypt(&self, bytes: &[u8], aad: &[u8]) -> Result<Vec<u8>, io::Error> {
+        if bytes.len() < TAMANHO_NONCE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "registro cifrado truncado"));
+        }
+        let (nonce, cifrado) = bytes.split_at(TAMANHO_NONCE);
+        self.aead
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: cifrado, aad })
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "falha ao decifrar registro (senha incorreta ou dado adulterado)",
+                )
+            })
+    }
+}
+
+/// Conteúdo de `chave.json`: o salt da derivação e um texto conhecido cifrado,
+/// usado para detectar senha errada antes de abrir os arquivos.
+#[derive(Serialize, Deserialize)]
+pub struct ArquivoChave {
+    salt: String,
+    verificador: String,
+}
+
+/// Gera um novo salt e deriva a chave da senha, sem gravar nada em disco.
+pub fn gerar_chave(senha: &str) -> Result<(Cipher, ArquivoChave), io::Error> {
+    let mut salt = [0u8; TAMANHO_SALT];
+    OsRng.fill_bytes(&mut salt);
+
+    let cipher = Cipher::from_passphrase(senha, &salt)?;
+    let arquivo = ArquivoChave {
+        salt: para_hex(&salt),
+        verificador: para_hex(&cipher.encrypt(TEXTO_VERIFICADOR, &[])?),
+    };
+    Ok((cipher, arquivo))
+}
+
+pub fn gravar_chave(diretorio: &Path, arquivo: &ArquivoChave) -> Result<(), io::Error> {
+    let json = serde_json::to_string_pretty(arquivo)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(diretorio.join(ARQUIVO_CHAVE), json)
+}
+
+/// Lê `chave.json` e deriva a chave; retorna `Ok(None)` se o diretório não usa criptografia.
+pub fn carregar_chave(diretorio: &Path, senha: &str) -> Result<Option<Cipher>, io::Error> {
+    let json = match fs::read_to_string(diretorio.join(ARQUIVO_CHAVE)) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let arquivo: ArquivoChave = serde_json::from_str(&json)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let cipher = Cipher::from_passphrase(senha, &de_hex(&arquivo.salt)?)?;
+    match cipher.decrypt(&de_hex(&arquivo.verificador)?, &[]) {
+        Ok(texto) if texto == TEXTO_VERIFICADOR => Ok(Some(cipher)),
+        _ => Err(io::Error::new(io::ErrorKind::PermissionDenied, "senha incorreta")),
+    }
+}
+
+pub fn existe_chave(diretorio: &Path) -> bool {
+    diretorio.join(ARQUIVO_CHAVE).is_file()
+}
+
+pub fn remover_chave(diretorio: &Path) -> Result<(), io::Error> {
+    fs::remove_file(diretorio.join(ARQUIVO_CHAVE))
+}
+
+fn para_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn de_hex(texto: &str) -> Result<Vec<u8>, io::Error> {
+    let invalido = || io::Error::new(io::ErrorKind::InvalidData, "hexadecimal inválido em chave.json");
+    if !texto.len().is_multiple_of(2) {
+        return Err(invalido());
+    }
+    (0..texto.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&texto[i..i + 2], 16).map_err(|_| invalido()))
+        .collect()
+}
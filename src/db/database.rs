@@ -0,0 +1,241 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::db::crypto::{self, Cipher};
+use crate::db::file_manager::{Entity, FileManager, FileOptions};
+use crate::db::migracoes;
+use crate::db::storage::MemoryStorage;
+use crate::structs::{
+    agenda_medico::AgendaMedico, cidade::Cidade, consulta::Consulta,
+    consulta_exame::ConsultaExame, diaria::Diaria, especialidade::Especialidade, exame::Exame,
+    medicao::Medicao, medico::Medico, paciente::Paciente, prontuario::Prontuario, cid::Cid,
+    consulta_diagnostico::ConsultaDiagnostico,
+};
+
+pub const ARQUIVO_PACIENTES: &str = "pacientes.dat";
+pub const ARQUIVO_MEDICOS: &str = "medicos.dat";
+pub const ARQUIVO_CIDADES: &str = "cidades.dat";
+pub const ARQUIVO_ESPECIALIDADES: &str = "especialidades.dat";
+pub const ARQUIVO_EXAMES: &str = "exames.dat";
+pub const ARQUIVO_CONSULTAS: &str = "consultas.dat";
+pub const ARQUIVO_DIARIAS: &str = "diarias.dat";
+pub const ARQUIVO_AGENDAS: &str = "agendas.dat";
+pub const ARQUIVO_CONSULTA_EXAMES: &str = "consulta_exames.dat";
+pub const ARQUIVO_MEDICOES: &str = "medicoes.dat";
+pub const ARQUIVO_PRONTUARIOS: &str = "prontuarios.dat";
+pub const ARQUIVO_CIDS: &str = "cid10.dat";
+pub const ARQUIVO_CONSULTA_DIAGNOSTICOS: &str = "consulta_diagnosticos.dat";
+
+pub const ARQUIVOS_DADOS: [&str; 13] = [
+    ARQUIVO_PACIENTES,
+    ARQUIVO_MEDICOS,
+    ARQUIVO_CIDADES,
+    ARQUIVO_ESPECIALIDADES,
+    ARQUIVO_EXAMES,
+    ARQUIVO_CONSULTAS,
+    ARQUIVO_DIARIAS,
+    ARQUIVO_AGENDAS,
+    ARQUIVO_CONSULTA_EXAMES,
+    ARQUIVO_MEDICOES,
+    ARQUIVO_PRONTUARIOS,
+    ARQUIVO_CIDS,
+    ARQUIVO_CONSULTA_DIAGNOSTICOS,
+];
+
+#[derive(Clone, Default)]
+pub struct DatabaseOptions {
+    pub cipher: Option<Cipher>,
+    pub arquivos_comprimidos: Vec<String>,
+    /// Carrega os arquivos para a memória e nunca grava de volta no disco.
+    pub memoria: bool,
+}
+
+impl DatabaseOptions {
+    fn arquivo(&self, nome: &str) -> FileOptions {
+        FileOptions {
+            cipher: self.cipher.clone(),
+            compression: self.arquivos_comprimidos.iter().any(|a| a == nome),
+            name: nome.to_string(),
+        }
+    }
+
+    /// Com `ler_disco` falso o arquivo começa vazio, sem abrir nada em disco.
+    fn abrir<T: Entity>(
+        &self,
+        diretorio: &Path,
+        nome: &str,
+        ler_disco: bool,
+    ) -> Result<FileManager<T>, io::Error> {
+        let caminho = diretorio.join(nome);
+        if !ler_disco {
+            return FileManager::with_storage(Box::new(MemoryStorage::default()), self.arquivo(nome));
+        }
+        if !self.memoria {
+            return FileManager::new(caminho, self.arquivo(nome));
+        }
+
+        let bytes = match fs::read(&caminho) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        FileManager::with_storage(Box::new(MemoryStorage::from_bytes(bytes)), self.arquivo(nome))
+    }
+}
+
+/// Reúne todos os arquivos do sistema, abertos a partir de um mesmo diretório.
+pub struct Database {
+    diretorio: PathBuf,
+    options: DatabaseOptions,
+    pub pacientes: FileManager<Paciente>,
+    pub medicos: FileManager<Medico>,
+    pub cidades: FileManager<Cidade>,
+    pub especialidades: FileManager<Especialidade>,
+    pub exames: FileManager<Exame>,
+    pub consultas: FileManager<Consulta>,
+    pub diarias: FileManager<Diaria>,
+    pub agendas: FileManager<AgendaMedico>,
+    pub consulta_exames: FileManager<ConsultaExame>,
+    pub medicoes: FileManager<Medicao>,
+    pub prontuarios: FileManager<Prontuario>,
+    pub cids: FileManager<Cid>,
+    pub consulta_diagnosticos: FileManager<ConsultaDiagnostico>,
+}
+
+impl Database {
+    pub fn open(diretorio: &Path, options: DatabaseOptions) -> Result<Database, io::Error> {
+        if !options.memoria {
+            fs::create_dir_all(diretorio)?;
+        }
+
+        let mut db = Database::montar(diretorio, options, true)?;
+        migracoes::aplicar(&mut db)?;
+        Ok(db)
+    }
+
+    fn montar(
+        diretorio: &Path,
+        options: DatabaseOptions,
+        ler_disco: bool,
+    ) -> Result<Database, io::Error> {
+        Ok(Database {
+            diretorio: diretorio.to_path_buf(),
+            pacientes: options.abrir(diretorio, ARQUIVO_PACIENTES, ler_disco)?,
+            medicos: options.abrir(diretorio, ARQUIVO_MEDICOS, ler_disco)?,
+            cidades: options.abrir(diretorio, ARQUIVO_CIDADES, ler_disco)?,
+            especialidades: options.abrir(diretorio, ARQUIVO_ESPECIALIDADES, ler_disco)?,
+            exames: options.abrir(diretorio, ARQUIVO_EXAMES, ler_disco)?,
+            consultas: options.abrir(diretorio, ARQUIVO_CONSULTAS, ler_disco)?,
+            diarias: options.abrir(diretorio, ARQUIVO_DIARIAS, ler_disco)?,
+            agendas: options.abrir(diretorio, ARQUIVO_AGENDAS, ler_disco)?,
+            consulta_exames: options.abrir(diretorio, ARQUIVO_CONSULTA_EXAMES, ler_disco)?,
+            medicoes: options.abrir(diretorio, ARQUIVO_MEDICOES, ler_disco)?,
+            prontuarios: options.abrir(diretorio, ARQUIVO_PRONTUARIOS, ler_disco)?,
+            cids: options.abrir(diretorio, ARQUIVO_CIDS, ler_disco)?,
+            consulta_diagnosticos: options.abrir(diretorio, ARQUIVO_CONSULTA_DIAGNOSTICOS, ler_disco)?,
+            options,
+        })
+    }
+
+    /// Banco vazio só em memória, para os testes e para pesquisar um diretório
+    /// ainda sem dados. Nada é lido nem gravado em disco e nenhuma migração
+    /// roda: os arquivos já nascem no formato atual.
+    pub fn em_memoria() -> Database {
+        let options = DatabaseOptions {
+            memoria: true,
+            ..Default::default()
+        };
+        Database::montar(Path::new(""), options, false).unwrap()
+    }
+
+    /// `true` para um diretório novo, sem `versao.json` e sem arquivos de dados.
+    pub fn sem_dados(diretorio: &Path) -> bool {
+        !diretorio.join(migracoes::ARQUIVO_VERSAO).exists()
+            && ARQUIVOS_DADOS.iter().all(|nome| !diretorio.join(nome).exists())
+    }
+
+    pub fn diretorio(&self) -> &Path {
+        &self.diretorio
+    }
+
+    /// `false` quando o banco foi aberto com `--memoria` e as alterações se perdem ao sair.
+    pub fn persistente(&self) -> bool {
+        !self.options.memoria
+    }
+
+    /// Arquivos que compõem um backup: os dados, a versão do formato e, se houver,
+    /// a chave de criptografia.
+    pub fn arquivos(&self) -> Vec<&'static str> {
+        let mut arquivos = ARQUIVOS_DADOS.to_vec();
+        arquivos.push(migracoes::ARQUIVO_VERSAO);
+        arquivos.push(crypto::ARQUIVO_CHAVE);
+        arquivos
+    }
+
+    /// Grava o que estiver pendente e solta todos os arquivos abertos, para que
+    /// possam ser substituídos (ex.: restauração de backup). Até `reabrir`, o
+    /// banco fica vazio em memória.
+    pub fn fechar(&mut self) -> Result<(), io::Error> {
+        self.sync()?;
+        *self = Database::montar(&self.diretorio, self.options.clone(), false)?;
+        Ok(())
+    }
+
+    /// Descarta os índices em memória e reabre todos os arquivos do disco,
+    /// necessário depois que os `.dat` são substituídos (ex.: restauração de backup).
+    /// Com `senha`, a chave é lida de novo do `chave.json` restaurado, que pode
+    /// ser de outra senha (e só é usada se o diretório tiver chave); sem ela,
+    /// continua valendo a chave atual.
+    pub fn reabrir(&mut self, senha: Option<&str>) -> Result<(), io::Error> {
+        let cipher = match senha {
+            Some(senha) => crypto::carregar_chave(&self.diretorio, senha)?,
+            None => self.options.cipher.clone(),
+        };
+        let options = DatabaseOptions {
+            cipher,
+            ..self.options.clone()
+        };
+        *self = Database::open(&self.diretorio, options)?;
+        Ok(())
+    }
+
+    /// Regrava todos os arquivos com a nova chave (ou em texto puro, com `None`).
+    pub fn rekey(&mut self, cipher: Option<Cipher>) -> Result<(), io::Error> {
+        let options = DatabaseOptions {
+            cipher,
+            ..self.options.clone()
+        };
+        self.pacientes.rewrite(options.arquivo(ARQUIVO_PACIENTES))?;
+        self.medicos.rewrite(options.arquivo(ARQUIVO_MEDICOS))?;
+        self.cidades.rewrite(options.arquivo(ARQUIVO_CIDADES))?;
+        self.especialidades.rewrite(options.arquivo(ARQUIVO_ESPECIALIDADES))?;
+        self.exames.rewrite(options.arquivo(ARQUIVO_EXAMES))?;
+        self.consultas.rewrite(options.arquivo(ARQUIVO_CONSULTAS))?;
+        self.diarias.rewrite(options.arquivo(ARQUIVO_DIARIAS))?;
+        self.agendas.rewrite(options.arquivo(ARQUIVO_AGENDAS))?;
+        self.consulta_exames.rewrite(options.arquivo(ARQUIVO_CONSULTA_EXAMES))?;
+        self.medicoes.rewrite(options.arquivo(ARQUIVO_MEDICOES))?;
+        self.prontuarios.rewrite(options.arquivo(ARQUIVO_PRONTUARIOS))?;
+        self.cids.rewrite(options.arquivo(ARQUIVO_CIDS))?;
+        self.consulta_diagnosticos.rewrite(options.arquivo(ARQUIVO_CONSULTA_DIAGNOSTICOS))?;
+        self.options = options;
+        Ok(())
+    }
+
+    pub fn sync(&self) -> Result<(), io::Error> {
+        self.pacientes.sync()?;
+        self.medicos.sync()?;
+        self.cidades.sync()?;
+        self.especialidades.sync()?;
+        self.exames.sync()?;
+        self.consultas.sync()?;
+        self.diarias.sync()?;
+        self.agendas.sync()?;
+        self.consulta_exames.sync()?;
+        self.medicoes.sync()?;
+        self.prontuarios.sync()?;
+        self.cids.sync()?;
+        self.consulta_diagnosticos.sync()
+    }
+}
@@ -1,132 +1,364 @@
-use std::fs::{File, OpenOptions};
-use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
 use std::marker::PhantomData;
+use std::path::Path;
 
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec;
+
+use crate::db::crypto::Cipher;
+use crate::db::search;
+use crate::db::storage::{FileStorage, Storage};
 use crate::db::tree::BinaryTree;
 
+/// Bits do primeiro byte do cabeçalho de cada registro. Arquivos antigos só
+/// gravavam 0 ou 1 nesse byte, então continuam legíveis.
+const FLAG_ACTIVE: u8 = 0b0000_0001;
+const FLAG_ENCRYPTED: u8 = 0b0000_0010;
+const FLAG_COMPRESSED: u8 = 0b0000_0100;
+/// O payload cifrado começa com a chave do registro em claro, e ela e o nome do
+/// arquivo entram como dados associados: o texto cifrado de um registro não
+/// decifra no lugar de outro. Registros cifrados antes deste bit não têm a chave.
+const FLAG_KEY_BOUND: u8 = 0b0000_1000;
+
+const HEADER_SIZE: u64 = 5;
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// Chave de um registro, com a forma em bytes usada como dado associado na criptografia.
+pub trait RecordKey: Ord + Copy {
+    fn key_bytes(&self) -> Vec<u8>;
+}
+
+impl RecordKey for u32 {
+    fn key_bytes(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+}
+
+impl RecordKey for (u32, u32) {
+    fn key_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.0.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.1.to_le_bytes());
+        bytes
+    }
+}
+
 pub trait Entity {
-    fn get_key(&self) -> u32;
+    type Key: RecordKey;
+
+    fn get_key(&self) -> Self::Key;
+    /// Valor que não pode se repetir entre registros vigentes (ex.: CPF).
+    /// `None` quando a entidade não tem essa restrição ou o registro não tem o valor.
+    fn unique_key(&self) -> Option<String> {
+        None
+    }
+    /// Texto indexado para a busca por nome/descrição (ver `FileManager::search`).
+    /// `None` quando a entidade não participa da busca.
+    fn search_text(&self) -> Option<String> {
+        None
+    }
     fn to_bytes(&self) -> Result<Vec<u8>, io::Error>;
     fn from_bytes(bytes: &[u8]) -> Result<Self, io::Error>
     where
         Self: Sized;
 }
 
+#[derive(Clone, Default)]
+pub struct FileOptions {
+    /// Quando presente, todo registro gravado é cifrado com esta chave.
+    /// Registros em texto puro continuam legíveis no mesmo arquivo.
+    pub cipher: Option<Cipher>,
+    /// Comprime o payload com deflate antes de cifrar. Só é aplicado quando o
+    /// resultado fica menor, então registros curtos continuam sem compressão.
+    pub compression: bool,
+    /// Nome do arquivo, usado como dado associado na criptografia para que um
+    /// registro copiado de outro arquivo não seja aceito.
+    pub name: String,
+}
+
 pub struct FileManager<T: Entity> {
-    file: File,
-    index: BinaryTree,
+    storage: Box<dyn Storage>,
+    index: BinaryTree<T::Key>,
+    unique: BTreeMap<String, T::Key>,
+    /// Índice de busca: token normalizado -> chaves cujo texto contém o token.
+    tokens: BTreeMap<String, BTreeSet<T::Key>>,
+    /// Tokens de cada chave, para tirar a versão anterior do índice.
+    tokens_by_key: BTreeMap<T::Key, Vec<String>>,
+    options: FileOptions,
     _phantom: PhantomData<T>,
 }
 
 impl<T: Entity> FileManager<T> {
-    pub fn new(file_path: &str) -> Result<FileManager<T>, io::Error> {
-        let mut file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(file_path)?;
-
-        let mut index = BinaryTree::new();
-        let mut offset = 0;
-        let mut buffer = Vec::new();
-
-        loop {
-            let mut header_buf = [0u8; 5];
-            match file.read_exact(&mut header_buf) {
-                Ok(_) => {
-                    let is_active = header_buf[0];
-                    let size = u32::from_le_bytes([
-                        header_buf[1],
-                        header_buf[2],
-                        header_buf[3],
-                        header_buf[4],
-                    ]);
+    pub fn new<P: AsRef<Path>>(file_path: P, options: FileOptions) -> Result<FileManager<T>, io::Error> {
+        Self::with_storage(Box::new(FileStorage::open(file_path)?), options)
+    }
 
-                    buffer.resize(size as usize, 0);
-                    file.read_exact(&mut buffer)?;
+    pub fn with_storage(
+        storage: Box<dyn Storage>,
+        options: FileOptions,
+    ) -> Result<FileManager<T>, io::Error> {
+        let mut manager = Self {
+            storage,
+            index: BinaryTree::new(),
+            unique: BTreeMap::new(),
+            tokens: BTreeMap::new(),
+            tokens_by_key: BTreeMap::new(),
+            options,
+            _phantom: PhantomData,
+        };
 
-                    if is_active == 1 {
-                        let record = T::from_bytes(&buffer)?;
-                        index.insert(record.get_key(), offset);
-                    }
-                    offset = file.seek(SeekFrom::Current(0))?;
-                }
-                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
-                Err(e) => return Err(e),
-            }
+        // A mesma chave pode aparecer mais de uma vez no arquivo; vale a última
+        // versão, tanto para o índice quanto para o valor único e a busca.
+        let mut index = BinaryTree::new();
+        let mut unique_by_key = BTreeMap::new();
+        let mut text_by_key = BTreeMap::new();
+        manager.scan(|offset, flags, buffer| {
+            let record = manager.decode(flags, buffer)?;
+            index.insert(record.get_key(), offset);
+            unique_by_key.insert(record.get_key(), record.unique_key());
+            text_by_key.insert(record.get_key(), record.search_text());
+            Ok(())
+        })?;
+        manager.index = index;
+        manager.unique = unique_by_key
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|value| (value, key)))
+            .collect();
+        for (key, text) in text_by_key {
+            manager.index_text(key, text.as_deref());
         }
 
-        Ok(FileManager {
-            file,
-            index,
-            _phantom: PhantomData,
-        })
+        Ok(manager)
     }
 
-    pub fn create_record(&mut self, record: &T, key: u32) -> Result<(), io::Error> {
-        let serialized_data = record.to_bytes()?;
+    pub fn create_record(&mut self, record: &T, key: T::Key) -> Result<(), io::Error> {
+        self.check_unique(record, key)?;
+        let (flags, serialized_data) = self.encode(record)?;
         let size = serialized_data.len() as u32;
 
-        let offset = self.file.seek(SeekFrom::End(0))?;
-
-        self.file.write_all(&[1])?;
-        self.file.write_all(&size.to_le_bytes())?;
+        let offset = self.storage.len()?;
 
-        self.file.write_all(&serialized_data)?;
+        let mut bytes = Vec::with_capacity(HEADER_SIZE as usize + serialized_data.len());
+        bytes.push(flags);
+        bytes.extend_from_slice(&size.to_le_bytes());
+        bytes.extend_from_slice(&serialized_data);
+        self.storage.write_at(offset, &bytes)?;
 
         self.index.insert(key, offset);
+        self.unique.retain(|_, k| *k != key);
+        if let Some(value) = record.unique_key() {
+            self.unique.insert(value, key);
+        }
+        self.index_text(key, record.search_text().as_deref());
         Ok(())
     }
 
-    pub fn read_record(&self, key: u32) -> Result<Option<T>, io::Error> {
-        if let Some(offset) = self.index.search(key) {
-            let mut file = self.file.try_clone()?;
-            file.seek(SeekFrom::Start(offset))?;
+    /// Busca sem diferenciar maiúsculas nem acentos no texto de `Entity::search_text`.
+    /// Todo termo da consulta precisa casar com algum token do registro (exato,
+    /// prefixo, trecho ou com erro de digitação); os resultados vêm do mais
+    /// relevante para o menos relevante.
+    pub fn search(&self, query: &str) -> Result<Vec<T>, io::Error> {
+        let terms = search::tokenize(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
 
-            let mut header_buf = [0u8; 5];
-            file.read_exact(&mut header_buf)?;
+        let mut scores: BTreeMap<T::Key, (usize, u32)> = BTreeMap::new();
+        for term in &terms {
+            let mut best: BTreeMap<T::Key, u32> = BTreeMap::new();
+            for (token, keys) in &self.tokens {
+                if let Some(score) = search::score(term, token) {
+                    for key in keys {
+                        let entry = best.entry(*key).or_insert(0);
+                        *entry = (*entry).max(score);
+                    }
+                }
+            }
+            for (key, score) in best {
+                let entry = scores.entry(key).or_insert((0, 0));
+                entry.0 += 1;
+                entry.1 += score;
+            }
+        }
+
+        let mut ranked: Vec<(T::Key, u32)> = scores
+            .into_iter()
+            .filter(|(_, (matched, _))| *matched == terms.len())
+            .map(|(key, (_, score))| (key, score))
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut records = Vec::with_capacity(ranked.len());
+        for (key, _) in ranked {
+            if let Some(record) = self.read_record(key)? {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
 
-            let is_active = header_buf[0];
+    /// Busca pelo valor único (ver `Entity::unique_key`).
+    pub fn find_by_unique(&self, value: &str) -> Result<Option<T>, io::Error> {
+        match self.unique.get(value) {
+            Some(key) => self.read_record(*key),
+            None => Ok(None),
+        }
+    }
+
+    pub fn read_record(&self, key: T::Key) -> Result<Option<T>, io::Error> {
+        if let Some(offset) = self.index.search(key) {
+            let mut header_buf = [0u8; HEADER_SIZE as usize];
+            self.storage.read_at(offset, &mut header_buf)?;
+
+            let flags = header_buf[0];
             let size =
                 u32::from_le_bytes([header_buf[1], header_buf[2], header_buf[3], header_buf[4]]);
 
-            if is_active != 1 {
+            if flags & FLAG_ACTIVE == 0 {
                 return Ok(None);
             }
 
-            let mut buffer = Vec::new();
-            buffer.resize(size as usize, 0);
-            file.read_exact(&mut buffer)?;
+            let mut buffer = vec![0; size as usize];
+            self.storage.read_at(offset + HEADER_SIZE, &mut buffer)?;
 
-            Ok(Some(T::from_bytes(&buffer)?))
+            Ok(Some(self.decode(flags, &buffer)?))
         } else {
             Ok(None)
         }
     }
 
-    pub fn delete_record(&mut self, key: u32) -> Result<bool, io::Error> {
+    /// Grava a nova versão no fim do arquivo e só então desativa a anterior; se o
+    /// processo cair no meio, a versão mais recente é a que vale ao reabrir.
+    pub fn update_record(&mut self, record: &T, key: T::Key) -> Result<bool, io::Error> {
+        let Some(old_offset) = self.index.search(key) else {
+            return Ok(false);
+        };
+
+        self.create_record(record, key)?;
+        self.deactivate(old_offset)?;
+        Ok(true)
+    }
+
+    pub fn delete_record(&mut self, key: T::Key) -> Result<bool, io::Error> {
         if let Some(offset) = self.index.search(key) {
-            self.file.seek(SeekFrom::Start(offset))?;
-            self.file.write_all(&[0])?;
+            self.deactivate(offset)?;
             self.index.delete(key);
+            self.unique.retain(|_, k| *k != key);
+            self.index_text(key, None);
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
+    pub fn sync(&self) -> Result<(), io::Error> {
+        self.storage.sync()
+    }
+
+    /// Lista os registros vigentes. Versões antigas que ainda estejam marcadas
+    /// como ativas no arquivo (gravadas antes de existir `update_record`) são ignoradas.
     pub fn read_all_records(&self) -> Result<Vec<T>, io::Error> {
         let mut records = Vec::new();
-        let mut file = self.file.try_clone()?;
-        file.seek(SeekFrom::Start(0))?;
+        self.scan(|offset, flags, buffer| {
+            let record = self.decode(flags, buffer)?;
+            if self.index.search(record.get_key()) == Some(offset) {
+                records.push(record);
+            }
+            Ok(())
+        })?;
+        Ok(records)
+    }
+
+    /// Como `read_all_records`, junto com o payload de cada registro já decifrado
+    /// e descomprimido. As migrações usam o payload para recuperar campos de
+    /// formatos antigos que a entidade atual não guarda mais.
+    pub fn read_all_with_payload(&self) -> Result<Vec<(T, Vec<u8>)>, io::Error> {
+        let mut records = Vec::new();
+        self.scan(|offset, flags, buffer| {
+            let payload = self.payload(flags, buffer)?;
+            let record = T::from_bytes(&payload)?;
+            if self.index.search(record.get_key()) == Some(offset) {
+                records.push((record, payload));
+            }
+            Ok(())
+        })?;
+        Ok(records)
+    }
+
+    /// Regrava o arquivo só com os registros vigentes, aplicando as novas opções
+    /// (ex.: troca de chave de criptografia). Registros excluídos e versões
+    /// antigas deixadas para trás são descartados.
+    pub fn rewrite(&mut self, options: FileOptions) -> Result<(), io::Error> {
+        let records = self.read_all_records()?;
+        self.options = options;
+        self.replace_all(&records)
+    }
+
+    /// Descarta todo o conteúdo do arquivo e grava apenas `records`.
+    pub fn replace_all(&mut self, records: &[T]) -> Result<(), io::Error> {
+        self.storage.truncate(0)?;
+        self.index = BinaryTree::new();
+        self.unique.clear();
+        self.tokens.clear();
+        self.tokens_by_key.clear();
+        for record in records {
+            self.create_record(record, record.get_key())?;
+        }
+        self.storage.sync()
+    }
+
+    /// Recusa o registro se o seu valor único já pertence a outra chave.
+    fn check_unique(&self, record: &T, key: T::Key) -> Result<(), io::Error> {
+        if let Some(value) = record.unique_key()
+            && let Some(owner) = self.unique.get(&value)
+            && *owner != key
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("valor único já cadastrado em outro registro: {}", value),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Troca os tokens de `key` no índice de busca; `None` só remove.
+    fn index_text(&mut self, key: T::Key, text: Option<&str>) {
+        for token in self.tokens_by_key.remove(&key).unwrap_or_default() {
+            if let Some(keys) = self.tokens.get_mut(&token) {
+                keys.remove(&key);
+                if keys.is_empty() {
+                    self.tokens.remove(&token);
+                }
+            }
+        }
 
+        let Some(text) = text else { return };
+        let tokens = search::tokenize(text);
+        for token in &tokens {
+            self.tokens.entry(token.clone()).or_default().insert(key);
+        }
+        self.tokens_by_key.insert(key, tokens);
+    }
+
+    fn deactivate(&mut self, offset: u64) -> Result<(), io::Error> {
+        let mut flags = [0u8; 1];
+        self.storage.read_at(offset, &mut flags)?;
+        self.storage.write_at(offset, &[flags[0] & !FLAG_ACTIVE])
+    }
+
+    /// Percorre o arquivo chamando `f(offset, flags, payload)` para cada registro ativo.
+    fn scan<F>(&self, mut f: F) -> Result<(), io::Error>
+    where
+        F: FnMut(u64, u8, &[u8]) -> Result<(), io::Error>,
+    {
+        let mut offset = 0;
         let mut buffer = Vec::new();
 
         loop {
-            let mut header_buf = [0u8; 5];
-            match file.read_exact(&mut header_buf) {
+            let mut header_buf = [0u8; HEADER_SIZE as usize];
+            match self.storage.read_at(offset, &mut header_buf) {
                 Ok(_) => {
-                    let is_active = header_buf[0];
+                    let flags = header_buf[0];
                     let size = u32::from_le_bytes([
                         header_buf[1],
                         header_buf[2],
@@ -135,17 +367,291 @@ impl<T: Entity> FileManager<T> {
                     ]);
 
                     buffer.resize(size as usize, 0);
-                    file.read_exact(&mut buffer)?;
+                    self.storage.read_at(offset + HEADER_SIZE, &mut buffer)?;
 
-                    if is_active == 1 {
-                        records.push(T::from_bytes(&buffer)?);
+                    if flags & FLAG_ACTIVE != 0 {
+                        f(offset, flags, &buffer)?;
                     }
+                    offset += HEADER_SIZE + size as u64;
                 }
                 Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
                 Err(e) => return Err(e),
             }
         }
 
-        Ok(records)
+        Ok(())
+    }
+
+    fn encode(&self, record: &T) -> Result<(u8, Vec<u8>), io::Error> {
+        let mut flags = FLAG_ACTIVE;
+        let mut bytes = record.to_bytes()?;
+
+        if self.options.compression {
+            let compressed = compress_to_vec(&bytes, COMPRESSION_LEVEL);
+            if compressed.len() < bytes.len() {
+                flags |= FLAG_COMPRESSED;
+                bytes = compressed;
+            }
+        }
+
+        if let Some(cipher) = &self.options.cipher {
+            flags |= FLAG_ENCRYPTED | FLAG_KEY_BOUND;
+            let key = record.get_key().key_bytes();
+            let cifrado = cipher.encrypt(&bytes, &self.associated_data(&key))?;
+            bytes = Vec::with_capacity(1 + key.len() + cifrado.len());
+            bytes.push(key.len() as u8);
+            bytes.extend_from_slice(&key);
+            bytes.extend_from_slice(&cifrado);
+        }
+
+        Ok((flags, bytes))
+    }
+
+    fn decode(&self, flags: u8, buffer: &[u8]) -> Result<T, io::Error> {
+        let record = T::from_bytes(&self.payload(flags, buffer)?)?;
+        if flags & FLAG_ENCRYPTED != 0
+            && flags & FLAG_KEY_BOUND != 0
+            && split_key(buffer)?.0 != record.get_key().key_bytes()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "registro cifrado não corresponde à sua chave (dado adulterado)",
+            ));
+        }
+        Ok(record)
+    }
+
+    /// Nome do arquivo e chave do registro, separados por um byte zero.
+    fn associated_data(&self, key: &[u8]) -> Vec<u8> {
+        let mut data = self.options.name.as_bytes().to_vec();
+        data.push(0);
+        data.extend_from_slice(key);
+        data
+    }
+
+    /// Desfaz a criptografia e a compressão indicadas em `flags`.
+    fn payload(&self, flags: u8, buffer: &[u8]) -> Result<Vec<u8>, io::Error> {
+        let mut bytes = buffer.to_vec();
+
+        if flags & FLAG_ENCRYPTED != 0 {
+            bytes = match &self.options.cipher {
+                Some(cipher) if flags & FLAG_KEY_BOUND != 0 => {
+                    let (key, cifrado) = split_key(&bytes)?;
+                    cipher.decrypt(cifrado, &self.associated_data(key))?
+                }
+                Some(cipher) => cipher.decrypt(&bytes, &[])?,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "registro criptografado: informe a senha do banco de dados",
+                    ));
+                }
+            };
+        }
+
+        if flags & FLAG_COMPRESSED != 0 {
+            bytes = decompress_to_vec(&bytes).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("falha ao descomprimir registro: {:?}", e.status),
+                )
+            })?;
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Separa a chave em claro do resto de um payload com `FLAG_KEY_BOUND`.
+fn split_key(bytes: &[u8]) -> Result<(&[u8], &[u8]), io::Error> {
+    let truncated = || io::Error::new(io::ErrorKind::InvalidData, "registro cifrado truncado");
+    let (&len, rest) = bytes.split_first().ok_or_else(truncated)?;
+    if rest.len() < len as usize {
+        return Err(truncated());
+    }
+    Ok(rest.split_at(len as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::db::crypto;
+    use crate::db::storage::MemoryStorage;
+
+    /// `MemoryStorage` compartilhado entre gerenciadores, para simular fechar e reabrir o arquivo.
+    #[derive(Clone, Default)]
+    struct Compartilhado(Rc<RefCell<MemoryStorage>>);
+
+    impl Storage for Compartilhado {
+        fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<(), io::Error> {
+            self.0.borrow().read_at(offset, buf)
+        }
+        fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<(), io::Error> {
+            self.0.borrow_mut().write_at(offset, data)
+        }
+        fn len(&self) -> Result<u64, io::Error> {
+            self.0.borrow().len()
+        }
+        fn sync(&self) -> Result<(), io::Error> {
+            Ok(())
+        }
+        fn truncate(&mut self, len: u64) -> Result<(), io::Error> {
+            self.0.borrow_mut().truncate(len)
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Registro {
+        codigo: u32,
+        nome: String,
+        documento: String,
+    }
+
+    impl Entity for Registro {
+        type Key = u32;
+
+        fn get_key(&self) -> u32 {
+            self.codigo
+        }
+
+        fn unique_key(&self) -> Option<String> {
+            (!self.documento.is_empty()).then(|| self.documento.clone())
+        }
+
+        fn search_text(&self) -> Option<String> {
+            Some(self.nome.clone())
+        }
+
+        fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
+            let mut bytes = self.codigo.to_le_bytes().to_vec();
+            for texto in [&self.nome, &self.documento] {
+                bytes.extend_from_slice(&(texto.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(texto.as_bytes());
+            }
+            Ok(bytes)
+        }
+
+        fn from_bytes(bytes: &[u8]) -> Result<Self, io::Error> {
+            let ler_u32 = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+            let ler_texto = |i: usize| {
+                let fim = i + 4 + ler_u32(i) as usize;
+                (String::from_utf8(bytes[i + 4..fim].to_vec()).unwrap(), fim)
+            };
+            let (nome, fim) = ler_texto(4);
+            let (documento, _) = ler_texto(fim);
+            Ok(Registro {
+                codigo: ler_u32(0),
+                nome,
+                documento,
+            })
+        }
+    }
+
+    fn registro(codigo: u32, nome: &str, documento: &str) -> Registro {
+        Registro {
+            codigo,
+            nome: nome.to_string(),
+            documento: documento.to_string(),
+        }
+    }
+
+    fn abrir(storage: &Compartilhado) -> FileManager<Registro> {
+        FileManager::with_storage(Box::new(storage.clone()), FileOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn crud_e_reabertura() {
+        let storage = Compartilhado::default();
+        let mut manager = abrir(&storage);
+
+        manager.create_record(&registro(1, "Ana", ""), 1).unwrap();
+        manager.create_record(&registro(2, "Bruno", ""), 2).unwrap();
+        assert_eq!(manager.read_record(1).unwrap(), Some(registro(1, "Ana", "")));
+
+        assert!(manager.update_record(&registro(1, "Ana Maria", ""), 1).unwrap());
+        assert!(!manager.update_record(&registro(9, "Ninguém", ""), 9).unwrap());
+        assert_eq!(manager.read_record(1).unwrap().unwrap().nome, "Ana Maria");
+
+        assert!(manager.delete_record(2).unwrap());
+        assert!(!manager.delete_record(2).unwrap());
+        assert_eq!(manager.read_record(2).unwrap(), None);
+
+        let reaberto = abrir(&storage);
+        assert_eq!(reaberto.read_record(1).unwrap(), Some(registro(1, "Ana Maria", "")));
+        assert_eq!(reaberto.read_record(2).unwrap(), None);
+        assert_eq!(reaberto.search("maria").unwrap(), vec![registro(1, "Ana Maria", "")]);
+    }
+
+    #[test]
+    fn listagem_ignora_versoes_antigas() {
+        let storage = Compartilhado::default();
+        let mut manager = abrir(&storage);
+
+        manager.create_record(&registro(1, "Ana", ""), 1).unwrap();
+        manager.create_record(&registro(2, "Bruno", ""), 2).unwrap();
+        manager.update_record(&registro(1, "Ana Maria", ""), 1).unwrap();
+        manager.update_record(&registro(1, "Ana Maria Souza", ""), 1).unwrap();
+        // Arquivos antigos regravavam a chave sem desativar a versão anterior.
+        manager.create_record(&registro(2, "Bruno Lima", ""), 2).unwrap();
+
+        let esperado = vec![registro(1, "Ana Maria Souza", ""), registro(2, "Bruno Lima", "")];
+        let mut registros = manager.read_all_records().unwrap();
+        registros.sort_by_key(|r| r.codigo);
+        assert_eq!(registros, esperado);
+
+        let mut registros = abrir(&storage).read_all_records().unwrap();
+        registros.sort_by_key(|r| r.codigo);
+        assert_eq!(registros, esperado);
+    }
+
+    #[test]
+    fn recusa_valor_unico_repetido() {
+        let storage = Compartilhado::default();
+        let mut manager = abrir(&storage);
+
+        manager.create_record(&registro(1, "Ana", "111"), 1).unwrap();
+        let erro = manager.create_record(&registro(2, "Bruno", "111"), 2).unwrap_err();
+        assert_eq!(erro.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(manager.read_record(2).unwrap(), None);
+
+        // O próprio registro pode manter o valor, e o valor volta a ficar livre ao sair.
+        manager.update_record(&registro(1, "Ana Maria", "111"), 1).unwrap();
+        manager.update_record(&registro(1, "Ana Maria", "222"), 1).unwrap();
+        manager.create_record(&registro(2, "Bruno", "111"), 2).unwrap();
+
+        let erro = abrir(&storage)
+            .create_record(&registro(3, "Carla", "222"), 3)
+            .unwrap_err();
+        assert_eq!(erro.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(manager.find_by_unique("111").unwrap(), Some(registro(2, "Bruno", "111")));
+    }
+
+    #[test]
+    fn registro_cifrado_so_vale_no_seu_arquivo_e_na_sua_chave() {
+        let (cipher, _) = crypto::gerar_chave("senha").unwrap();
+        let opcoes = |name: &str| FileOptions {
+            cipher: Some(cipher.clone()),
+            compression: false,
+            name: name.to_string(),
+        };
+        let abrir_cifrado = |storage: &Compartilhado, name: &str| {
+            FileManager::<Registro>::with_storage(Box::new(storage.clone()), opcoes(name))
+        };
+        let storage = Compartilhado::default();
+        abrir_cifrado(&storage, "a.dat")
+            .unwrap()
+            .create_record(&registro(1, "Ana", ""), 1)
+            .unwrap();
+
+        let reaberto = abrir_cifrado(&storage, "a.dat").unwrap();
+        assert_eq!(reaberto.read_record(1).unwrap(), Some(registro(1, "Ana", "")));
+        assert!(abrir_cifrado(&storage, "b.dat").is_err());
+
+        // A chave em claro vem logo depois do cabeçalho e do byte com o seu tamanho.
+        storage.0.borrow_mut().write_at(HEADER_SIZE + 1, &2u32.to_le_bytes()).unwrap();
+        assert!(abrir_cifrado(&storage, "a.dat").is_err());
     }
 }
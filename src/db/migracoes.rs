@@ -0,0 +1,328 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::mem::size_of;
+use std::path::Path;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::db::database::Database;
+use crate::db::file_manager::Entity;
+use crate::structs::consulta::Consulta;
+use crate::structs::consulta_exame::ConsultaExame;
+use crate::structs::medicao::Medicao;
+use crate::structs::paciente::MedidasAntigas;
+use crate::utils::cid10;
+use crate::utils::datas;
+use crate::utils::clinical;
+use crate::utils::agendamento;
+
+/// Guarda a versão do formato dos dados do diretório. Diretórios sem este
+/// arquivo são tratados como versão 0 (anteriores às migrações).
+pub const ARQUIVO_VERSAO: &str = "versao.json";
+
+#[derive(Serialize, Deserialize)]
+struct ArquivoVersao {
+    versao: u32,
+}
+
+type Migracao = fn(&mut Database) -> Result<(), io::Error>;
+
+/// Migrações em ordem: a de índice `i` leva os dados da versão `i` para `i + 1`.
+const MIGRACOES: &[(&str, Migracao)] = &[
+    (
+        "diárias passam a ser contadas por dia e especialidade",
+        diarias_por_especialidade,
+    ),
+    (
+        "exames das consultas passam para consulta_exames.dat",
+        exames_das_consultas,
+    ),
+    ("pacientes passam a ter CPF", pacientes_com_cpf),
+    ("médicos passam a ter CRM", medicos_com_crm),
+    ("datas passam a ser gravadas como data", datas_tipadas),
+    ("peso e altura passam para medicoes.dat", medicoes_dos_pacientes),
+    (
+        "catálogo CID-10 e diagnósticos das consultas",
+        catalogo_cid10,
+    ),
+    (
+        "pacientes deixam de reservar espaço para peso e altura",
+        pacientes_sem_medidas,
+    ),
+];
+
+pub fn versao_atual() -> u32 {
+    MIGRACOES.len() as u32
+}
+
+pub fn ler_versao(diretorio: &Path) -> Result<u32, io::Error> {
+    match fs::read_to_string(diretorio.join(ARQUIVO_VERSAO)) {
+        Ok(json) => serde_json::from_str::<ArquivoVersao>(&json)
+            .map(|arquivo| arquivo.versao)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+fn gravar_versao(diretorio: &Path, versao: u32) -> Result<(), io::Error> {
+    let json = serde_json::to_string_pretty(&ArquivoVersao { versao })
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(diretorio.join(ARQUIVO_VERSAO), json)
+}
+
+/// Aplica as migrações pendentes. No modo em memória elas rodam sobre a cópia
+/// carregada e a versão não é gravada, então o disco continua intacto.
+pub fn aplicar(db: &mut Database) -> Result<(), io::Error> {
+    let versao = ler_versao(db.diretorio())?;
+    if versao > versao_atual() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "dados na versão {}, mas este programa só conhece até a versão {}",
+                versao,
+                versao_atual()
+            ),
+        ));
+    }
+
+    for (i, (descricao, migracao)) in MIGRACOES.iter().enumerate().skip(versao as usize) {
+        println!("Migrando dados para a versão {}: {}.", i + 1, descricao);
+        migracao(db)?;
+        if db.persistente() {
+            gravar_versao(db.diretorio(), i as u32 + 1)?;
+        }
+    }
+    Ok(())
+}
+
+/// Antes, `diarias.dat` era indexado só pelo dia e as especialidades de um mesmo
+/// dia disputavam o mesmo contador. Os contadores são refeitos a partir das consultas.
+fn diarias_por_especialidade(db: &mut Database) -> Result<(), io::Error> {
+    agendamento::reconstruir_diarias(db).map(|_| ())
+}
+
+/// Cada consulta antiga tinha exatamente um exame em `Consulta::codigo_exame`.
+/// O exame vira um item em `consulta_exames.dat` e o campo é zerado.
+fn exames_das_consultas(db: &mut Database) -> Result<(), io::Error> {
+    for consulta in db.consultas.read_all_records()? {
+        if consulta.codigo_exame == 0 {
+            continue;
+        }
+        let item = ConsultaExame::novo(consulta.codigo_consulta, consulta.codigo_exame);
+        if db.consulta_exames.read_record(item.get_key())?.is_none() {
+            db.consulta_exames.create_record(&item, item.get_key())?;
+        }
+        let migrada = Consulta {
+            codigo_exame: 0,
+            ..consulta
+        };
+        db.consultas.update_record(&migrada, migrada.codigo_consulta)?;
+    }
+    Ok(())
+}
+
+/// Avisa quais pacientes estão sem CPF, destacando nomes repetidos, que podem
+/// ser a mesma pessoa cadastrada em códigos diferentes. Registros antigos são
+/// lidos com o CPF vazio; `pacientes.dat` só é regravado em
+/// `pacientes_sem_medidas`, depois que peso e altura foram para o histórico.
+fn pacientes_com_cpf(db: &mut Database) -> Result<(), io::Error> {
+    let pacientes = db.pacientes.read_all_records()?;
+
+    let mut por_nome: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+    for paciente in pacientes.iter().filter(|p| p.cpf.is_empty()) {
+        por_nome
+            .entry(paciente.nome.trim().to_lowercase())
+            .or_default()
+            .push(paciente.codigo_paciente);
+    }
+    if por_nome.is_empty() {
+        return Ok(());
+    }
+
+    let total: usize = por_nome.values().map(Vec::len).sum();
+    println!(
+        "{} paciente(s) sem CPF. Informe o CPF pela opção Alterar Paciente.",
+        total
+    );
+    for (nome, codigos) in por_nome.iter().filter(|(_, codigos)| codigos.len() > 1) {
+        let codigos: Vec<String> = codigos.iter().map(|c| c.to_string()).collect();
+        println!(
+            "  Possível duplicidade: \"{}\" nos códigos {}",
+            nome,
+            codigos.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Regrava `medicos.dat` já com os campos de CRM (vazios) e lista os médicos
+/// que precisam ter o CRM informado.
+fn medicos_com_crm(db: &mut Database) -> Result<(), io::Error> {
+    let medicos = db.medicos.read_all_records()?;
+    db.medicos.replace_all(&medicos)?;
+
+    let sem_crm: Vec<_> = medicos.iter().filter(|m| m.crm.is_empty()).collect();
+    if sem_crm.is_empty() {
+        return Ok(());
+    }
+    println!(
+        "{} médico(s) sem CRM. Informe o CRM pela opção Alterar Médico:",
+        sem_crm.len()
+    );
+    for medico in sem_crm {
+        println!("  {} - {}", medico.codigo_medico, medico.nome);
+    }
+    Ok(())
+}
+
+/// Pacientes e consultas guardavam a data como texto, em `AAAAMMDD` ou
+/// `DD/MM/AAAA`. A leitura já interpreta os dois formatos; aqui `consultas.dat`
+/// é regravado no formato novo. As datas das consultas sempre foram validadas
+/// na marcação. Os pacientes são regravados em `pacientes_sem_medidas`.
+fn datas_tipadas(db: &mut Database) -> Result<(), io::Error> {
+    let consultas = db.consultas.read_all_records()?;
+    db.consultas.replace_all(&consultas)?;
+    Ok(())
+}
+
+/// Regrava `pacientes.dat` no formato atual, sem o espaço de peso e altura
+/// (ver `MedidasAntigas`), que a essa altura já estão em `medicoes.dat`. O
+/// texto de uma data de nascimento que não pôde ser interpretada não sobrevive
+/// à regravação, então é mostrado junto com o paciente para que a data seja
+/// informada de novo.
+fn pacientes_sem_medidas(db: &mut Database) -> Result<(), io::Error> {
+    let mut pacientes = Vec::new();
+    let mut sem_data = Vec::new();
+    for (paciente, payload) in db.pacientes.read_all_with_payload()? {
+        if paciente.data_nascimento.is_none()
+            && let Some(texto) = data_nascimento_antiga(&payload)?
+        {
+            sem_data.push((paciente.codigo_paciente, paciente.nome.clone(), texto));
+        }
+        pacientes.push(paciente);
+    }
+    db.pacientes.replace_all(&pacientes)?;
+
+    if !sem_data.is_empty() {
+        println!(
+            "{} paciente(s) com data de nascimento não reconhecida. Informe a data pela opção Alterar Paciente:",
+            sem_data.len()
+        );
+        for (codigo, nome, texto) in sem_data {
+            println!("  {} - {} (gravada como \"{}\")", codigo, nome, texto);
+        }
+    }
+    Ok(())
+}
+
+/// Texto da data de nascimento em registros de paciente anteriores às datas
+/// tipadas: ela vem logo depois do código e do nome.
+fn data_nascimento_antiga(payload: &[u8]) -> Result<Option<String>, io::Error> {
+    let mut cursor = size_of::<u32>();
+    pular_texto(payload, &mut cursor)?;
+    datas::texto_antigo(payload, cursor)
+}
+
+/// O paciente guardava um único par peso/altura, sobrescrito a cada alteração.
+/// O par vira a primeira medição do histórico, datada do dia da migração,
+/// porque a data original não foi gravada. O registro do paciente fica como
+/// está até `pacientes_sem_medidas`. Também avisa quem ainda não tem sexo
+/// cadastrado.
+fn medicoes_dos_pacientes(db: &mut Database) -> Result<(), io::Error> {
+    let hoje = Local::now().date_naive();
+    let mut movidas = 0;
+    let mut sem_sexo = 0;
+    for (paciente, payload) in db.pacientes.read_all_with_payload()? {
+        if paciente.sexo.is_none() {
+            sem_sexo += 1;
+        }
+        if let MedidasAntigas::Par(peso, altura) = medidas_antigas(&payload)?
+            && (peso > 0.0 || altura > 0.0)
+        {
+            let medicao = Medicao {
+                codigo_paciente: paciente.codigo_paciente,
+                data: hoje,
+                peso,
+                altura,
+            };
+            clinical::registrar_medicao(&mut db.medicoes, &medicao)?;
+            movidas += 1;
+        }
+    }
+
+    if movidas > 0 {
+        println!(
+            "{} medição(ões) movida(s) para o histórico com a data de hoje.",
+            movidas
+        );
+    }
+    if sem_sexo > 0 {
+        println!(
+            "{} paciente(s) sem sexo cadastrado; ele é necessário para classificar o IMC de crianças e adolescentes.",
+            sem_sexo
+        );
+    }
+    Ok(())
+}
+
+/// Peso e altura de registros de paciente gravados antes de `medicoes.dat`.
+/// Vêm depois do código da cidade.
+fn medidas_antigas(payload: &[u8]) -> Result<MedidasAntigas, io::Error> {
+    let mut cursor = size_of::<u32>();
+    pular_texto(payload, &mut cursor)?; // nome
+    datas::ler(payload, &mut cursor)?;
+    pular_texto(payload, &mut cursor)?; // endereço
+    pular_texto(payload, &mut cursor)?; // telefone
+    cursor += size_of::<u32>(); // código da cidade
+    MedidasAntigas::ler(payload, cursor)
+}
+
+/// Avança `cursor` por um texto gravado como tamanho seguido dos bytes.
+fn pular_texto(payload: &[u8], cursor: &mut usize) -> Result<(), io::Error> {
+    let truncado = || io::Error::new(io::ErrorKind::InvalidData, "registro de paciente truncado");
+    let inicio = *cursor + size_of::<u32>();
+    let campo = payload.get(*cursor..inicio).ok_or_else(truncado)?;
+    let tamanho = u32::from_le_bytes(campo.try_into().unwrap()) as usize;
+    let fim = inicio.checked_add(tamanho).ok_or_else(truncado)?;
+    if fim > payload.len() {
+        return Err(truncado());
+    }
+    *cursor = fim;
+    Ok(())
+}
+
+/// Carrega o catálogo embutido em `cid10.dat` e liga às consultas os
+/// diagnósticos já escritos nos prontuários. Códigos que não estão no catálogo
+/// ficam só no prontuário e são listados para revisão.
+fn catalogo_cid10(db: &mut Database) -> Result<(), io::Error> {
+    let catalogo = cid10::interpretar_csv(cid10::CATALOGO_PADRAO)?;
+    let carregados = cid10::importar(&mut db.cids, &catalogo)?;
+    println!("{} código(s) CID-10 carregado(s) no catálogo.", carregados);
+
+    let mut fora_do_catalogo = Vec::new();
+    for prontuario in db.prontuarios.read_all_records()? {
+        let mut validos = Vec::new();
+        for codigo in &prontuario.diagnosticos {
+            match cid10::validar(&db.cids, std::slice::from_ref(codigo)) {
+                Ok(codigos) => validos.extend(codigos),
+                Err(cid10::ErroDiagnostico::Io(e)) => return Err(e),
+                Err(_) => fora_do_catalogo.push((prontuario.codigo_prontuario, codigo.clone())),
+            }
+        }
+        cid10::anexar_diagnosticos(db, prontuario.codigo_consulta, &validos)?;
+    }
+
+    if !fora_do_catalogo.is_empty() {
+        println!(
+            "{} diagnóstico(s) de prontuários fora do catálogo; importe o catálogo completo e revise-os:",
+            fora_do_catalogo.len()
+        );
+        for (codigo_prontuario, codigo) in fora_do_catalogo {
+            println!("  prontuário {} - {}", codigo_prontuario, codigo);
+        }
+    }
+    Ok(())
+}
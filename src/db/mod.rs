@@ -1,2 +1,9 @@
+pub mod backup;
+pub mod crypto;
+pub mod database;
 pub mod file_manager;
+pub mod migracoes;
+pub mod query;
+pub mod search;
+pub mod storage;
 pub mod tree;
\ No newline at end of file
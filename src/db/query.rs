@@ -0,0 +1,534 @@
+//! Linguagem de consulta sobre os registros de um `FileManager`, por exemplo:
+//!
+//! ```text
+//! consultas where data between 20250101 and 20250131 and codigo_medico = 3 order by hora
+//! pacientes where nome contains silva or cpf = 12345678909 order by nome desc limit 10
+//! ```
+//!
+//! Operadores: `=`, `!=`, `<`, `<=`, `>`, `>=`, `between .. and ..` e `contains`.
+//! `and` tem precedência sobre `or`. Textos são comparados sem diferenciar
+//! maiúsculas nem acentos; datas podem ser escritas como AAAAMMDD ou DD/MM/AAAA.
+
+use std::cmp::Ordering;
+use std::io;
+
+use chrono::NaiveDate;
+
+use crate::db::file_manager::{Entity, FileManager};
+use crate::db::search;
+use crate::utils::datas;
+
+/// Valor de um campo nomeado. Datas viram números AAAAMMDD para que `<`, `>` e
+/// `between` funcionem direto sobre elas.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+    /// Campo opcional sem valor; não satisfaz nenhuma condição.
+    Null,
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+impl From<NaiveDate> for Value {
+    fn from(value: NaiveDate) -> Self {
+        Value::from(datas::codigo(value))
+    }
+}
+
+impl<V: Into<Value>> From<Option<V>> for Value {
+    fn from(value: Option<V>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+/// Entidade cujos registros podem ser filtrados e ordenados por nome de campo.
+pub trait Queryable: Entity {
+    /// Nomes aceitos em `where` e `order by`.
+    const FIELDS: &'static [&'static str];
+
+    /// Valor do campo `name`; só é chamado com nomes de `FIELDS`.
+    fn field(&self, name: &str) -> Value;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// Valor escrito na consulta; `number` vem preenchido quando o texto é um
+/// número ou uma data.
+#[derive(Debug, Clone)]
+struct Literal {
+    text: String,
+    number: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+enum Test {
+    Compare(Operator, Literal),
+    Between(Literal, Literal),
+    Contains(Literal),
+}
+
+#[derive(Debug, Clone)]
+struct Condition {
+    field: String,
+    test: Test,
+}
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    /// Nome da coleção (ex.: `consultas`); quem executa decide o `FileManager`.
+    pub entity: String,
+    /// Grupos ligados por `or`; dentro de cada grupo, condições ligadas por `and`.
+    filter: Vec<Vec<Condition>>,
+    order_by: Option<(String, bool)>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Operator(Operator),
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, io::Error> {
+        let mut parser = Parser { tokens: tokenize(text)?, position: 0 };
+        let entity = parser.word("nome da coleção")?.to_lowercase();
+
+        let mut filter = Vec::new();
+        if parser.keyword("where") {
+            filter.push(vec![parser.condition()?]);
+            loop {
+                if parser.keyword("and") {
+                    let condition = parser.condition()?;
+                    filter.last_mut().unwrap().push(condition);
+                } else if parser.keyword("or") {
+                    filter.push(vec![parser.condition()?]);
+                } else {
+                    break;
+                }
+            }
+        }
+
+        let mut order_by = None;
+        if parser.keyword("order") {
+            if !parser.keyword("by") {
+                return Err(invalid("esperado 'by' depois de 'order'"));
+            }
+            let field = parser.word("campo de ordenação")?.to_lowercase();
+            let descending = if parser.keyword("desc") {
+                true
+            } else {
+                parser.keyword("asc");
+                false
+            };
+            order_by = Some((field, descending));
+        }
+
+        let mut limit = None;
+        if parser.keyword("limit") {
+            let value = parser.word("quantidade do limit")?;
+            limit = Some(value.parse().map_err(|_| invalid(&format!("limit inválido: {}", value)))?);
+        }
+
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(invalid(&format!("trecho inesperado: {}", describe(token))));
+        }
+
+        Ok(Query { entity, filter, order_by, limit })
+    }
+
+    /// Filtra, ordena e limita os registros vigentes de `manager`. Campos
+    /// desconhecidos são recusados antes de ler o arquivo.
+    pub fn execute<T: Queryable>(&self, manager: &FileManager<T>) -> Result<Vec<T>, io::Error> {
+        let fields = self
+            .filter
+            .iter()
+            .flatten()
+            .map(|c| &c.field)
+            .chain(self.order_by.as_ref().map(|(field, _)| field));
+        for field in fields {
+            if !T::FIELDS.contains(&field.as_str()) {
+                return Err(invalid(&format!(
+                    "campo desconhecido em {}: {} (campos: {})",
+                    self.entity,
+                    field,
+                    T::FIELDS.join(", ")
+                )));
+            }
+        }
+
+        let mut records = Vec::new();
+        for record in manager.read_all_records()? {
+            if self.matches(&record)? {
+                records.push(record);
+            }
+        }
+
+        if let Some((field, descending)) = &self.order_by {
+            records.sort_by(|a, b| {
+                let ordering = compare_values(&a.field(field), &b.field(field));
+                if *descending { ordering.reverse() } else { ordering }
+            });
+        }
+        if let Some(limit) = self.limit {
+            records.truncate(limit);
+        }
+        Ok(records)
+    }
+
+    fn matches<T: Queryable>(&self, record: &T) -> Result<bool, io::Error> {
+        if self.filter.is_empty() {
+            return Ok(true);
+        }
+        for group in &self.filter {
+            let mut all = true;
+            for condition in group {
+                if !condition.matches(&record.field(&condition.field))? {
+                    all = false;
+                    break;
+                }
+            }
+            if all {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Condition {
+    fn matches(&self, value: &Value) -> Result<bool, io::Error> {
+        let matches = match &self.test {
+            Test::Compare(operator, literal) => match self.compare(value, literal)? {
+                Some(ordering) => match operator {
+                    Operator::Equal => ordering == Ordering::Equal,
+                    Operator::NotEqual => ordering != Ordering::Equal,
+                    Operator::Less => ordering == Ordering::Less,
+                    Operator::LessOrEqual => ordering != Ordering::Greater,
+                    Operator::Greater => ordering == Ordering::Greater,
+                    Operator::GreaterOrEqual => ordering != Ordering::Less,
+                },
+                None => false,
+            },
+            Test::Between(low, high) => {
+                self.compare(value, low)?.is_some_and(|o| o != Ordering::Less)
+                    && self.compare(value, high)?.is_some_and(|o| o != Ordering::Greater)
+            }
+            Test::Contains(literal) => match value {
+                Value::Text(text) => search::normalize(text).contains(&search::normalize(&literal.text)),
+                Value::Number(number) => format_number(*number).contains(&literal.text),
+                Value::Null => false,
+            },
+        };
+        Ok(matches)
+    }
+
+    /// Ordem do valor do campo em relação ao literal; `None` quando o campo
+    /// está vazio. Campo numérico com um literal que não é número nem data é
+    /// erro, para a consulta não voltar vazia sem explicação.
+    fn compare(&self, value: &Value, literal: &Literal) -> Result<Option<Ordering>, io::Error> {
+        match value {
+            Value::Number(number) => match literal.number {
+                Some(other) => Ok(number.partial_cmp(&other)),
+                None => Err(invalid(&format!(
+                    "o campo {} é numérico e '{}' não é número nem data",
+                    self.field, literal.text
+                ))),
+            },
+            Value::Text(text) => Ok(Some(search::normalize(text).cmp(&search::normalize(&literal.text)))),
+            Value::Null => Ok(None),
+        }
+    }
+}
+
+/// Ordem usada no `order by`; campos vazios vão para o fim.
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (Value::Text(a), Value::Text(b)) => search::normalize(a).cmp(&search::normalize(b)),
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        (Value::Number(_), Value::Text(_)) => Ordering::Less,
+        (Value::Text(_), Value::Number(_)) => Ordering::Greater,
+    }
+}
+
+fn format_number(number: f64) -> String {
+    if number.fract() == 0.0 {
+        format!("{}", number as i64)
+    } else {
+        number.to_string()
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Consome `keyword` (sem diferenciar maiúsculas) se for o próximo token.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn word(&mut self, expected: &str) -> Result<String, io::Error> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            Some(token) => Err(invalid(&format!("esperado {}, encontrado {}", expected, describe(&token)))),
+            None => Err(invalid(&format!("esperado {} no fim da consulta", expected))),
+        }
+    }
+
+    fn literal(&mut self) -> Result<Literal, io::Error> {
+        match self.next() {
+            // Entre aspas o valor vale do mesmo jeito: `codigo = "3"` compara como número.
+            Some(Token::Quoted(text)) | Some(Token::Word(text)) => {
+                let number = match text.trim().parse::<f64>() {
+                    Ok(number) => Some(number),
+                    Err(_) if text.contains('/') => datas::interpretar(&text).map(|d| datas::codigo(d) as f64),
+                    Err(_) => None,
+                };
+                Ok(Literal { text, number })
+            }
+            Some(token) => Err(invalid(&format!("esperado um valor, encontrado {}", describe(&token)))),
+            None => Err(invalid("esperado um valor no fim da consulta")),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, io::Error> {
+        let field = self.word("nome de campo")?.to_lowercase();
+        let test = if self.keyword("between") {
+            let low = self.literal()?;
+            if !self.keyword("and") {
+                return Err(invalid("esperado 'and' em 'between .. and ..'"));
+            }
+            Test::Between(low, self.literal()?)
+        } else if self.keyword("contains") {
+            Test::Contains(self.literal()?)
+        } else {
+            match self.next() {
+                Some(Token::Operator(operator)) => Test::Compare(operator, self.literal()?),
+                Some(token) => {
+                    return Err(invalid(&format!(
+                        "esperado operador depois de {}, encontrado {}",
+                        field,
+                        describe(&token)
+                    )));
+                }
+                None => return Err(invalid(&format!("esperado operador depois de {}", field))),
+            }
+        };
+        Ok(Condition { field, test })
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, io::Error> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut quoted = String::new();
+            loop {
+                match chars.next() {
+                    Some(q) if q == c => break,
+                    Some(other) => quoted.push(other),
+                    None => return Err(invalid("texto entre aspas sem fechamento")),
+                }
+            }
+            tokens.push(Token::Quoted(quoted));
+        } else if matches!(c, '=' | '!' | '<' | '>') {
+            chars.next();
+            let followed_by_equal = chars.next_if_eq(&'=').is_some();
+            let operator = match (c, followed_by_equal) {
+                ('=', _) => Operator::Equal,
+                ('!', true) => Operator::NotEqual,
+                ('<', false) => Operator::Less,
+                ('<', true) => Operator::LessOrEqual,
+                ('>', false) => Operator::Greater,
+                ('>', true) => Operator::GreaterOrEqual,
+                _ => return Err(invalid("operador inválido: !")),
+            };
+            tokens.push(Token::Operator(operator));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || matches!(c, '=' | '!' | '<' | '>' | '\'' | '"') {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => format!("'{}'", word),
+        Token::Quoted(text) => format!("\"{}\"", text),
+        Token::Operator(_) => "um operador".to_string(),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("consulta inválida: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::database::Database;
+    use crate::structs::consulta::{Consulta, StatusConsulta};
+
+    /// (código, paciente, médico, dia de junho de 2025, hora, status)
+    const CONSULTAS: [(u32, u32, u32, u32, &str, StatusConsulta); 5] = [
+        (1, 1, 1, 2, "09:00", StatusConsulta::Agendada),
+        (2, 2, 1, 5, "10:30", StatusConsulta::Realizada),
+        (3, 2, 2, 10, "08:00", StatusConsulta::Agendada),
+        (4, 3, 2, 15, "11:00", StatusConsulta::Cancelada),
+        (5, 1, 3, 30, "14:00", StatusConsulta::Confirmada),
+    ];
+
+    fn banco() -> Database {
+        let mut db = Database::em_memoria();
+        for (codigo_consulta, codigo_paciente, codigo_medico, dia, hora, status) in CONSULTAS {
+            let consulta = Consulta {
+                codigo_consulta,
+                codigo_paciente,
+                codigo_medico,
+                data: NaiveDate::from_ymd_opt(2025, 6, dia).unwrap(),
+                hora: hora.to_string(),
+                status,
+                ..Default::default()
+            };
+            db.consultas.create_record(&consulta, codigo_consulta).unwrap();
+        }
+        db
+    }
+
+    fn codigos(db: &Database, texto: &str) -> Vec<u32> {
+        Query::parse(texto)
+            .and_then(|query| query.execute(&db.consultas))
+            .unwrap()
+            .iter()
+            .map(|c| c.codigo_consulta)
+            .collect()
+    }
+
+    fn erro(db: &Database, texto: &str) -> io::Error {
+        Query::parse(texto)
+            .and_then(|query| query.execute(&db.consultas))
+            .unwrap_err()
+    }
+
+    #[test]
+    fn and_tem_precedencia_sobre_or() {
+        let db = banco();
+        assert_eq!(
+            codigos(&db, "consultas where codigo_medico = 1 and codigo_paciente = 2 or codigo_consulta = 5"),
+            vec![2, 5]
+        );
+        assert_eq!(
+            codigos(&db, "consultas where codigo_consulta = 5 or codigo_medico = 2 and status != cancelada"),
+            vec![3, 5]
+        );
+        assert_eq!(codigos(&db, "consultas"), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn between_contains_order_by_e_limit() {
+        let db = banco();
+        assert_eq!(codigos(&db, "consultas where data between 05/06/2025 and 20250615"), vec![2, 3, 4]);
+        assert_eq!(codigos(&db, "consultas where status contains AGEND"), vec![1, 3]);
+        assert_eq!(codigos(&db, "consultas where data contains 202506"), vec![1, 2, 3, 4, 5]);
+        assert_eq!(codigos(&db, "consultas order by hora"), vec![3, 1, 2, 4, 5]);
+        assert_eq!(codigos(&db, "consultas order by hora desc limit 2"), vec![5, 4]);
+        assert_eq!(
+            codigos(&db, "CONSULTAS WHERE codigo_medico >= 2 ORDER BY data DESC LIMIT 10"),
+            vec![5, 4, 3]
+        );
+    }
+
+    #[test]
+    fn valor_entre_aspas_vale_como_numero_ou_data() {
+        let db = banco();
+        assert_eq!(codigos(&db, "consultas where codigo_consulta = \"3\""), vec![3]);
+        assert_eq!(codigos(&db, "consultas where data <= '05/06/2025'"), vec![1, 2]);
+        assert_eq!(codigos(&db, "consultas where hora = '08:00'"), vec![3]);
+
+        let erro = erro(&db, "consultas where codigo_consulta = \"tres\"");
+        assert_eq!(erro.kind(), io::ErrorKind::InvalidInput);
+        assert!(erro.to_string().contains("codigo_consulta"));
+    }
+
+    #[test]
+    fn recusa_campo_desconhecido_e_consulta_mal_formada() {
+        let db = banco();
+        let desconhecido = erro(&db, "consultas where valor > 10");
+        assert!(desconhecido.to_string().contains("campo desconhecido"));
+        assert!(erro(&db, "consultas order by valor").to_string().contains("campo desconhecido"));
+
+        for texto in [
+            "",
+            "consultas where",
+            "consultas where codigo_consulta",
+            "consultas where codigo_consulta 3",
+            "consultas where codigo_consulta ! 3",
+            "consultas where data between 1",
+            "consultas where data between 1 or 2",
+            "consultas where nome = 'silva",
+            "consultas order hora",
+            "consultas limit dez",
+            "consultas limit 2 sobrando",
+        ] {
+            let erro = Query::parse(texto).unwrap_err();
+            assert_eq!(erro.kind(), io::ErrorKind::InvalidInput, "{}", texto);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Normalização e pontuação usadas pelo índice de busca textual do `FileManager`.
+
+/// Pontuação de um termo da consulta contra um token indexado; maior é melhor.
+pub const SCORE_EXACT: u32 = 4;
+pub const SCORE_PREFIX: u32 = 3;
+pub const SCORE_SUBSTRING: u32 = 2;
+pub const SCORE_FUZZY: u32 = 1;
+
+/// Minúsculas e sem acentos: "José Conceição" -> "jose conceicao".
+pub fn normalize(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ã' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ó' | 'ò' | 'ô' | 'õ' | 'ö' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            'ñ' => 'n',
+            c => c,
+        })
+        .collect()
+}
+
+/// Quebra o texto normalizado em palavras; pontuação separa tokens.
+pub fn tokenize(text: &str) -> Vec<String> {
+    normalize(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Quão bem `term` (já normalizado) casa com `token`. Erros de digitação só são
+/// tolerados em termos com 4 letras ou mais: 1 erro até 7 letras, 2 a partir de 8.
+pub fn score(term: &str, token: &str) -> Option<u32> {
+    if term == token {
+        return Some(SCORE_EXACT);
+    }
+    if token.starts_with(term) {
+        return Some(SCORE_PREFIX);
+    }
+    if token.contains(term) {
+        return Some(SCORE_SUBSTRING);
+    }
+    let length = term.chars().count();
+    let tolerance = match length {
+        0..=3 => return None,
+        4..=7 => 1,
+        _ => 2,
+    };
+    // Também aceita o começo do token com erro ("guilhr" -> "guilherme").
+    let prefix: String = token.chars().take(length).collect();
+    if edit_distance(term, token) <= tolerance || edit_distance(term, &prefix) <= tolerance {
+        Some(SCORE_FUZZY)
+    } else {
+        None
+    }
+}
+
+/// Distância de Levenshtein entre duas strings, contada em caracteres.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
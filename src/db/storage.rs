@@ -0,0 +1,103 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Meio onde o `FileManager` guarda os bytes dos registros.
+///
+/// `read_at` tem a semântica de `read_exact`: se não houver bytes suficientes
+/// a partir de `offset`, retorna `ErrorKind::UnexpectedEof`.
+pub trait Storage {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<(), io::Error>;
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<(), io::Error>;
+    fn len(&self) -> Result<u64, io::Error>;
+    fn sync(&self) -> Result<(), io::Error>;
+    fn truncate(&mut self, len: u64) -> Result<(), io::Error>;
+}
+
+pub struct FileStorage {
+    file: File,
+}
+
+impl FileStorage {
+    pub fn open<P: AsRef<Path>>(file_path: P) -> Result<FileStorage, io::Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(file_path)?;
+        Ok(FileStorage { file })
+    }
+}
+
+impl Storage for FileStorage {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<(), io::Error> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(buf)
+    }
+
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<(), io::Error> {
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(data)
+    }
+
+    fn len(&self) -> Result<u64, io::Error> {
+        Ok(self.file.metadata()?.len())
+    }
+
+    fn sync(&self) -> Result<(), io::Error> {
+        self.file.sync_all()
+    }
+
+    fn truncate(&mut self, len: u64) -> Result<(), io::Error> {
+        self.file.set_len(len)
+    }
+}
+
+/// Armazenamento volátil, usado para rodar o sistema sem tocar nos `.dat`.
+#[derive(Default)]
+pub struct MemoryStorage {
+    data: Vec<u8>,
+}
+
+impl MemoryStorage {
+    pub fn from_bytes(data: Vec<u8>) -> MemoryStorage {
+        MemoryStorage { data }
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<(), io::Error> {
+        let start = offset as usize;
+        let end = start + buf.len();
+        if end > self.data.len() {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        buf.copy_from_slice(&self.data[start..end]);
+        Ok(())
+    }
+
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<(), io::Error> {
+        let start = offset as usize;
+        let end = start + data.len();
+        if end > self.data.len() {
+            self.data.resize(end, 0);
+        }
+        self.data[start..end].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn len(&self) -> Result<u64, io::Error> {
+        Ok(self.data.len() as u64)
+    }
+
+    fn sync(&self) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn truncate(&mut self, len: u64) -> Result<(), io::Error> {
+        self.data.resize(len as usize, 0);
+        Ok(())
+    }
+}
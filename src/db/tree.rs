@@ -2,15 +2,15 @@ use std::option::Option;
 use std::boxed::Box;
 
 #[derive(Debug)]
-pub struct Node {
-    pub key: u32,
+pub struct Node<K> {
+    pub key: K,
     pub offset: u64,
-    pub left: Option<Box<Node>>,
-    pub right: Option<Box<Node>>,
+    pub left: Option<Box<Node<K>>>,
+    pub right: Option<Box<Node<K>>>,
 }
 
-impl Node {
-    pub fn new(key: u32, offset: u64) -> Self {
+impl<K> Node<K> {
+    pub fn new(key: K, offset: u64) -> Self {
         Node {
             key,
             offset,
@@ -20,26 +20,28 @@ impl Node {
     }
 }
 
-pub struct BinaryTree {
-    pub root: Option<Box<Node>>,
+pub struct BinaryTree<K> {
+    pub root: Option<Box<Node<K>>>,
 }
 
-impl BinaryTree {
+impl<K: Ord + Copy> BinaryTree<K> {
     pub fn new() -> Self {
         BinaryTree { root: None }
     }
 
-    pub fn insert(&mut self, key: u32, offset: u64) {
+    pub fn insert(&mut self, key: K, offset: u64) {
         Self::insert_recursive(&mut self.root, key, offset);
     }
     
-    fn insert_recursive(node_opt: &mut Option<Box<Node>>, key: u32, offset: u64) {
+    fn insert_recursive(node_opt: &mut Option<Box<Node<K>>>, key: K, offset: u64) {
         match node_opt {
             Some(node) => {
                 if key < node.key {
                     Self::insert_recursive(&mut node.left, key, offset);
                 } else if key > node.key {
                     Self::insert_recursive(&mut node.right, key, offset);
+                } else {
+                    node.offset = offset;
                 }
             }
             None => {
@@ -48,11 +50,11 @@ impl BinaryTree {
         }
     }
 
-    pub fn search(&self, key: u32) -> Option<u64> {
+    pub fn search(&self, key: K) -> Option<u64> {
         Self::search_recursive(&self.root, key)
     }
 
-    fn search_recursive(node_opt: &Option<Box<Node>>, key: u32) -> Option<u64> {
+    fn search_recursive(node_opt: &Option<Box<Node<K>>>, key: K) -> Option<u64> {
         match node_opt {
             Some(node) => {
                 if key == node.key {
@@ -67,12 +69,14 @@ impl BinaryTree {
         }
     }
 
-    pub fn delete(&mut self, key: u32) -> bool {
-        delete_recursive(&mut self.root, key).is_some()
+    pub fn delete(&mut self, key: K) -> bool {
+        let found = self.search(key).is_some();
+        self.root = delete_recursive(&mut self.root, key);
+        found
     }
 }
 
-fn delete_recursive(node: &mut Option<Box<Node>>, key: u32) -> Option<Box<Node>> {
+fn delete_recursive<K: Ord>(node: &mut Option<Box<Node<K>>>, key: K) -> Option<Box<Node<K>>> {
     if let Some(mut current_node) = node.take() {
         if key < current_node.key {
             current_node.left = delete_recursive(&mut current_node.left, key);
@@ -96,11 +100,13 @@ fn delete_recursive(node: &mut Option<Box<Node>>, key: u32) -> Option<Box<Node>>
     None
 }
 
-fn find_min_and_remove(node_opt: &mut Option<Box<Node>>) -> Option<Box<Node>> {
+fn find_min_and_remove<K>(node_opt: &mut Option<Box<Node<K>>>) -> Option<Box<Node<K>>> {
     let mut current = node_opt.as_mut().unwrap();
     
     if current.left.is_none() {
-        return node_opt.take();
+        let mut min = node_opt.take().unwrap();
+        *node_opt = min.right.take();
+        return Some(min);
     }
     
     while current.left.as_ref().unwrap().left.is_some() {
@@ -1,53 +1,94 @@
-use crate::{db::file_manager::FileManager, structs::{cidade::Cidade, consulta::Consulta, diaria::Diaria, especialidade::Especialidade, exame::Exame, medico::Medico, paciente::Paciente}, utils::{faturamentos, relatorios}};
+use crate::{config::Config, db::{crypto::{self, Cipher}, database::{Database, DatabaseOptions}, migracoes}, utils::{clinical, faturamentos, pesquisa, regras, relatorios}};
+use std::env;
+use std::io;
+use std::path::Path;
+use std::process;
+
+mod config;
 mod structs;
 mod menus;
 mod db;
 mod utils;
 
 fn main() {
-    let mut paciente_manager = FileManager::<Paciente>::new("pacientes.dat").unwrap();
-    let mut medico_manager = FileManager::<Medico>::new("medicos.dat").unwrap();
-    let mut cidade_manager = FileManager::<Cidade>::new("cidades.dat").unwrap();
-    let mut especialidade_manager = FileManager::<Especialidade>::new("especialidades.dat").unwrap();
-    let mut exame_manager = FileManager::<Exame>::new("exames.dat").unwrap();
-    let mut consulta_manager = FileManager::<Consulta>::new("consultas.dat").unwrap();
-    let mut diaria_manager = FileManager::<Diaria>::new("diarias.dat").unwrap();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = match Config::carregar(Path::new(config::ARQUIVO_CONFIG)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("[ERRO]: Não foi possível ler {}: {}", config::ARQUIVO_CONFIG, e);
+            process::exit(1);
+        }
+    };
+    let diretorio_dados = config::resolver_diretorio_dados(&args, &config);
+    if let Some(texto) = config::pesquisa(&args) {
+        pesquisar(&diretorio_dados, &config, &texto);
+        return;
+    }
+    let cipher = match carregar_cifra(&diretorio_dados) {
+        Ok(cipher) => cipher,
+        Err(e) => {
+            eprintln!("[ERRO]: Não foi possível abrir o banco de dados: {}", e);
+            process::exit(1);
+        }
+    };
+    let options = DatabaseOptions {
+        cipher,
+        arquivos_comprimidos: config.arquivos_comprimidos.clone(),
+        memoria: config::usar_memoria(&args),
+    };
+    let mut db = match Database::open(&diretorio_dados, options) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("[ERRO]: Não foi possível abrir o banco de dados: {}", e);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = ativar_criptografia(&mut db, &config) {
+        eprintln!("[ERRO]: Não foi possível ativar a criptografia: {}", e);
+        process::exit(1);
+    }
+    let status_faturados = config.status_faturados();
+    let regras = match regras::carregar(config.regras_agendamento.as_deref()) {
+        Ok(regras) => regras,
+        Err(e) => {
+            eprintln!("[ERRO]: Configuração inválida: {}", e);
+            process::exit(1);
+        }
+    };
+    let referencia_imc =
+        match clinical::ReferenciaImc::carregar(Path::new(clinical::DIRETORIO_REFERENCIA)) {
+            Ok(referencia) => referencia,
+            Err(e) => {
+                eprintln!("[ERRO]: Tabela de IMC por idade inválida: {}", e);
+                process::exit(1);
+            }
+        };
+    if !db.persistente() {
+        println!("Modo em memória: nenhuma alteração será gravada em disco.");
+    }
 
     loop {
-        menus::exibir_menu_principal(); 
+        menus::exibir_menu_principal();
         let choice = menus::ler_opcao_menu();
 
         match choice {
-            1 => menus::menu_pacientes(&mut paciente_manager, &cidade_manager),
-            2 => menus::menu_medicos(&mut medico_manager, &cidade_manager, &especialidade_manager),
-            3 => menus::menu_especialidades(&mut especialidade_manager),
-            4 => menus::menu_cidades(&mut cidade_manager),
-            5 => menus::menu_exames(&mut exame_manager, &especialidade_manager),
-            6 => menus::menu_consultas(
-                &mut consulta_manager,
-                &paciente_manager,
-                &medico_manager,
-                &cidade_manager,
-                &especialidade_manager,
-                &exame_manager,
-                &mut diaria_manager,
-            ),
-            7 => menus::menu_diarias(&mut diaria_manager),
-            8 => faturamentos::menu_faturamento(
-                &consulta_manager,
-                &medico_manager,
-                &especialidade_manager,
-                &exame_manager,
-            ),
-            9 => relatorios::relatorio_consultas_ordenadas(
-                &consulta_manager,
-                &paciente_manager,
-                &cidade_manager,
-                &medico_manager,
-                &especialidade_manager,
-                &exame_manager,
-            ),
-            10 => {
+            1 => menus::menu_pacientes(&mut db, &referencia_imc),
+            2 => menus::menu_medicos(&mut db),
+            3 => menus::menu_especialidades(&mut db),
+            4 => menus::menu_cidades(&mut db),
+            5 => menus::menu_exames(&mut db),
+            6 => menus::menu_consultas(&mut db, &regras),
+            7 => menus::menu_diarias(&mut db),
+            8 => faturamentos::menu_faturamento(&db, &status_faturados),
+            9 => relatorios::relatorio_consultas_ordenadas(&db, &status_faturados),
+            10 => menus::menu_backup(&mut db),
+            11 => menus::menu_criptografia(&mut db),
+            12 => menus::menu_agendas(&mut db),
+            13 => menus::menu_prontuarios(&mut db),
+            14 => menus::menu_cid10(&mut db),
+            15 => menus::menu_resultados_exames(&mut db),
+            16 => menus::menu_pesquisa(&db),
+            17 => {
                 println!("Até mais!");
                 break;
             },
@@ -55,3 +96,82 @@ fn main() {
         }
     }
 }
+
+/// Executa a consulta de `--pesquisa` só lendo os dados: o banco é aberto em
+/// memória e nenhuma migração roda, então os arquivos não são tocados. Dados em
+/// versão antiga precisam antes ser migrados abrindo o programa normalmente.
+fn pesquisar(diretorio: &Path, config: &Config, texto: &str) {
+    // Diretório ainda sem dados: a consulta é validada e não traz resultados.
+    if Database::sem_dados(diretorio) {
+        if let Err(e) = pesquisa::executar(&Database::em_memoria(), texto) {
+            eprintln!("[ERRO]: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let pendente = match migracoes::ler_versao(diretorio) {
+        Ok(versao) => versao < migracoes::versao_atual(),
+        Err(e) => {
+            eprintln!("[ERRO]: Não foi possível abrir o banco de dados: {}", e);
+            process::exit(1);
+        }
+    };
+    if pendente {
+        eprintln!(
+            "[ERRO]: Os dados precisam ser migrados para a versão {}. Abra o programa sem --pesquisa para migrar.",
+            migracoes::versao_atual()
+        );
+        process::exit(1);
+    }
+
+    let options = carregar_cifra(diretorio).map(|cipher| DatabaseOptions {
+        cipher,
+        arquivos_comprimidos: config.arquivos_comprimidos.clone(),
+        memoria: true,
+    });
+    match options
+        .and_then(|options| Database::open(diretorio, options))
+        .and_then(|db| pesquisa::executar(&db, texto))
+    {
+        Ok(linhas) => {
+            for linha in linhas {
+                println!("{}", linha);
+            }
+        }
+        Err(e) => {
+            eprintln!("[ERRO]: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Pede a senha se o diretório já tem `chave.json`; sem ele, os arquivos estão
+/// em texto puro. A senha pode vir da variável de ambiente `crypto::VARIAVEL_SENHA`.
+fn carregar_cifra(diretorio: &Path) -> Result<Option<Cipher>, io::Error> {
+    if !crypto::existe_chave(diretorio) {
+        return Ok(None);
+    }
+    let senha = env::var(crypto::VARIAVEL_SENHA)
+        .unwrap_or_else(|_| menus::ler_senha("Senha do banco de dados: "));
+    crypto::carregar_chave(diretorio, &senha)
+}
+
+/// Com a criptografia ligada na configuração e nenhuma chave ainda, cria a
+/// chave e regrava cifrados os dados que já existem. A senha pode vir da
+/// variável de ambiente `crypto::VARIAVEL_SENHA`.
+fn ativar_criptografia(db: &mut Database, config: &Config) -> Result<(), io::Error> {
+    if !config.criptografia || !db.persistente() || crypto::existe_chave(db.diretorio()) {
+        return Ok(());
+    }
+    println!("Criptografia habilitada e nenhuma chave encontrada. Os arquivos serão criptografados.");
+    let senha = match env::var(crypto::VARIAVEL_SENHA) {
+        Ok(senha) => senha,
+        Err(_) => menus::ler_nova_senha().ok_or_else(|| io::Error::other("senha não confirmada"))?,
+    };
+    if menus::criptografar(db, &senha) {
+        Ok(())
+    } else {
+        Err(io::Error::other("os arquivos não puderam ser criptografados"))
+    }
+}
@@ -1,12 +1,37 @@
-use chrono::{Local, NaiveDate};
-
-use crate::db::file_manager::FileManager;
+use crate::db::backup;
+use crate::db::crypto;
+use crate::db::database::Database;
+use crate::db::file_manager::{Entity, FileManager};
 use crate::structs::{
-    cidade::Cidade, consulta::Consulta, diaria::Diaria, especialidade::Especialidade, exame::Exame,
-    medico::Medico, paciente::Paciente,
+    agenda_medico::AgendaMedico,
+    cid::Cid,
+    cidade::Cidade,
+    diaria::Diaria,
+    consulta::{Consulta, MudancaStatus, StatusConsulta},
+    consulta_exame::{ConsultaExame, ResultadoExame},
+    especialidade::{DURACAO_PADRAO, Especialidade},
+    exame::Exame,
+    medico::Medico,
+    medicao::Medicao,
+    paciente::{FaixaEtaria, Paciente, Sexo},
+    prontuario::Prontuario,
 };
+use crate::utils::agendamento::{self, BuscaHorario, HorarioLivre};
+use crate::utils::cid10;
+use crate::utils::clinical::{self, ReferenciaImc};
+use crate::utils::cpf;
+use crate::utils::crm;
+use crate::utils::datas;
+use crate::utils::descricoes;
+use crate::utils::exames_consulta;
+use crate::utils::pesquisa;
+use crate::utils::regras::{self, Agendamento, RegraAgendamento};
+use crate::utils::relatorios;
 use crate::utils::print_data::print_data;
+use crate::utils::prontuarios::{self, Evento, EventoHistorico};
+use chrono::{Local, NaiveDate};
 use std::io::{self, Write};
+use std::path::Path;
 
 pub fn ler_opcao_menu() -> u32 {
     print!("Digite sua opção: ");
@@ -44,6 +69,126 @@ pub fn ler_f32(prompt: &str) -> f32 {
     }
 }
 
+/// Aceita `DD/MM/AAAA` ou `AAAAMMDD`.
+pub fn ler_data(prompt: &str) -> NaiveDate {
+    loop {
+        let input = ler_string(prompt);
+        match datas::interpretar(&input) {
+            Some(data) => return data,
+            None => println!("Entrada inválida. Por favor, digite a data como DD/MM/AAAA."),
+        }
+    }
+}
+
+/// Variantes de leitura para as telas de alteração: mostram o valor atual entre
+/// colchetes e o mantêm se o usuário apenas pressionar Enter.
+pub fn ler_string_ou_manter(prompt: &str, atual: &str) -> String {
+    let input = ler_string(&format!("{} [{}]: ", prompt, atual));
+    if input.is_empty() { atual.to_string() } else { input }
+}
+
+pub fn ler_u32_ou_manter(prompt: &str, atual: u32) -> u32 {
+    loop {
+        let input = ler_string(&format!("{} [{}]: ", prompt, atual));
+        if input.is_empty() {
+            return atual;
+        }
+        match input.parse() {
+            Ok(num) => return num,
+            Err(_) => println!("Entrada inválida. Por favor, digite um número."),
+        }
+    }
+}
+
+pub fn ler_data_ou_manter(prompt: &str, atual: Option<NaiveDate>) -> Option<NaiveDate> {
+    loop {
+        let input = ler_string(&format!("{} [{}]: ", prompt, datas::formatar(atual)));
+        if input.is_empty() {
+            return atual;
+        }
+        match datas::interpretar(&input) {
+            Some(data) => return Some(data),
+            None => println!("Entrada inválida. Por favor, digite a data como DD/MM/AAAA."),
+        }
+    }
+}
+
+pub fn ler_f32_ou_manter(prompt: &str, atual: f32) -> f32 {
+    loop {
+        let input = ler_string(&format!("{} [{:.2}]: ", prompt, atual));
+        if input.is_empty() {
+            return atual;
+        }
+        match input.parse() {
+            Ok(num) => return num,
+            Err(_) => println!("Entrada inválida. Por favor, digite um número decimal."),
+        }
+    }
+}
+
+fn cidade_existe(cidade_manager: &FileManager<Cidade>, codigo_cidade: u32) -> bool {
+    match cidade_manager.read_record(codigo_cidade) {
+        Ok(Some(_)) => true,
+        Ok(None) => {
+            println!(
+                "\n[ERRO]: Cidade com código {} não encontrada.",
+                codigo_cidade
+            );
+            false
+        }
+        Err(e) => {
+            eprintln!("[ERRO]: Falha ao consultar arquivo de Cidades: {}", e);
+            false
+        }
+    }
+}
+
+fn especialidade_existe(
+    especialidade_manager: &FileManager<Especialidade>,
+    codigo_especialidade: u32,
+) -> bool {
+    match especialidade_manager.read_record(codigo_especialidade) {
+        Ok(Some(_)) => true,
+        Ok(None) => {
+            println!(
+                "\n[ERRO]: Especialidade com código {} não encontrada.",
+                codigo_especialidade
+            );
+            false
+        }
+        Err(e) => {
+            eprintln!(
+                "[ERRO]: Falha ao consultar arquivo de Especialidades: {}",
+                e
+            );
+            false
+        }
+    }
+}
+
+/// Lê a senha sem mostrá-la no terminal. Sem terminal (ex.: entrada
+/// redirecionada), lê da entrada padrão como `ler_string`.
+pub fn ler_senha(prompt: &str) -> String {
+    match rpassword::prompt_password(prompt) {
+        Ok(senha) => senha.trim().to_string(),
+        Err(_) => ler_string(prompt),
+    }
+}
+
+/// Lê a senha duas vezes; retorna `None` se estiver vazia ou não conferir.
+pub fn ler_nova_senha() -> Option<String> {
+    let senha = ler_senha("Nova senha: ");
+    if senha.is_empty() {
+        println!("A senha não pode ser vazia.");
+        return None;
+    }
+    if ler_senha("Confirme a nova senha: ") != senha {
+        println!("As senhas não conferem.");
+        return None;
+    }
+    Some(senha)
+}
+
 pub fn exibir_menu_principal() {
     println!("\n--- Menu Principal ---");
     println!("1. Gerenciar Pacientes");
@@ -55,17 +200,32 @@ pub fn exibir_menu_principal() {
     println!("7. Gerenciar Diárias");
     println!("8. Relatórios de Faturamento");
     println!("9. Relatório de Consultas");
-    println!("10. Sair");
+    println!("10. Backup e Restauração");
+    println!("11. Criptografia");
+    println!("12. Agendas dos Médicos");
+    println!("13. Prontuários");
+    println!("14. CID-10 e Diagnósticos");
+    println!("15. Pedidos e Resultados de Exames");
+    println!("16. Pesquisar Registros");
+    println!("17. Sair");
 }
 
-pub fn menu_pacientes(manager: &mut FileManager<Paciente>, cidade_manager: &FileManager<Cidade>) {
+pub fn menu_pacientes(db: &mut Database, referencia_imc: &ReferenciaImc) {
+    let manager = &mut db.pacientes;
+    let cidade_manager = &db.cidades;
+    let medicoes = &mut db.medicoes;
     loop {
         println!("\n--- Gerenciamento de Pacientes ---");
         println!("1. Inserir novo paciente");
         println!("2. Consultar paciente por código");
         println!("3. Excluir paciente por código");
         println!("4. Listar todos os pacientes");
-        println!("5. Voltar ao menu principal");
+        println!("5. Alterar paciente");
+        println!("6. Buscar paciente por CPF");
+        println!("7. Registrar medição de peso e altura");
+        println!("8. Histórico de medições");
+        println!("9. Buscar paciente por nome");
+        println!("10. Voltar ao menu principal");
 
         let choice = ler_opcao_menu();
         match choice {
@@ -82,51 +242,72 @@ pub fn menu_pacientes(manager: &mut FileManager<Paciente>, cidade_manager: &File
                         continue;
                     }
                 }
+                let Some(cpf) = ler_cpf(manager, codigo, None) else {
+                    continue;
+                };
                 let nome = ler_string("Nome: ");
-                let data_nascimento = ler_string("Data de Nascimento (AAAAMMDD): ");
+                let data_nascimento = ler_data("Data de Nascimento (DD/MM/AAAA): ");
+                if !data_nascimento_valida(data_nascimento) {
+                    continue;
+                }
+                let sexo = ler_sexo();
                 let endereco = ler_string("Endereço: ");
                 let telefone = ler_string("Telefone: ");
                 let codigo_cidade = ler_u32("Código da Cidade: ");
-                match cidade_manager.read_record(codigo_cidade) {
-                    Ok(Some(_)) => {}
-                    Ok(None) => {
-                        println!(
-                            "\n[ERRO]: Cidade com código {} não encontrada.",
-                            codigo_cidade
-                        );
-                        continue;
-                    }
-                    Err(e) => {
-                        eprintln!("[ERRO]: Falha ao consultar arquivo de Cidades: {}", e);
-                        continue;
-                    }
+                if !cidade_existe(cidade_manager, codigo_cidade) {
+                    continue;
                 }
-                let peso = ler_f32("Peso (kg): ");
-                let altura = ler_f32("Altura (m): ");
+                let Some(medicao) = ler_medicao(codigo, Local::now().date_naive()) else {
+                    continue;
+                };
 
                 let novo_paciente = Paciente {
                     codigo_paciente: codigo,
                     nome,
-                    data_nascimento,
+                    data_nascimento: Some(data_nascimento),
                     endereco,
                     telefone,
                     codigo_cidade,
-                    peso,
-                    altura,
+                    cpf,
+                    sexo: Some(sexo),
                 };
                 if let Err(e) = manager.create_record(&novo_paciente, codigo) {
                     eprintln!("Erro ao inserir paciente: {}", e);
                 } else {
                     println!("Paciente inserido com sucesso!");
+                    if let Err(e) = clinical::registrar_medicao(medicoes, &medicao) {
+                        eprintln!("Erro ao gravar medição: {}", e);
+                    }
                 }
             }
-            2 => {
-                let codigo = ler_u32("Digite o código do paciente para consulta: ");
-                if let Ok(Some(paciente)) = manager.read_record(codigo) {
+            2 | 6 => {
+                let paciente = if choice == 2 {
+                    let codigo = ler_u32("Digite o código do paciente para consulta: ");
+                    manager.read_record(codigo)
+                } else {
+                    match cpf::normalizar(&ler_string("Digite o CPF do paciente: ")) {
+                        Some(cpf) => manager.find_by_unique(&cpf),
+                        None => {
+                            println!("\n[ERRO]: CPF inválido.");
+                            continue;
+                        }
+                    }
+                };
+                if let Ok(Some(paciente)) = paciente {
                     println!("\n--- Dados do Paciente ---");
                     println!("Código: {}", paciente.codigo_paciente);
                     println!("Nome: {}", paciente.nome);
-                    print_data("Data Nascimento", &paciente.data_nascimento);
+                    if paciente.cpf.is_empty() {
+                        println!("CPF: Não cadastrado");
+                    } else {
+                        println!("CPF: {}", cpf::formatar(&paciente.cpf));
+                    }
+                    print_data("Data Nascimento", paciente.data_nascimento);
+                    println!("Idade: {}", descrever_idade(&paciente, Local::now().date_naive()));
+                    println!(
+                        "Sexo: {}",
+                        paciente.sexo.map_or("Não informado", Sexo::descricao)
+                    );
                     println!("Endereço: {}", paciente.endereco);
                     println!("Telefone: {}", paciente.telefone);
 
@@ -136,17 +317,21 @@ pub fn menu_pacientes(manager: &mut FileManager<Paciente>, cidade_manager: &File
                         println!("Cidade: Não encontrada");
                     }
 
-                    let imc = paciente.peso / (paciente.altura * paciente.altura);
-                    let diagnostico = match imc {
-                        _ if imc < 18.5 => "Abaixo do peso",
-                        _ if imc < 25.0 => "Peso normal",
-                        _ if imc < 30.0 => "Sobrepeso",
-                        _ => "Obesidade",
-                    };
-                    println!("Peso: {:.2} kg", paciente.peso);
-                    println!("Altura: {:.2} m", paciente.altura);
-                    println!("IMC: {:.2}", imc);
-                    println!("Diagnóstico: {}", diagnostico);
+                    match clinical::medicoes_do_paciente(medicoes, paciente.codigo_paciente) {
+                        Ok(lista) => match lista.last() {
+                            Some(medicao) => {
+                                print_data("Última medição", Some(medicao.data));
+                                println!("Peso: {:.2} kg", medicao.peso);
+                                println!("Altura: {:.2} m", medicao.altura);
+                                println!(
+                                    "{}",
+                                    clinical::avaliar(&paciente, medicao, referencia_imc)
+                                );
+                            }
+                            None => println!("Nenhuma medição de peso e altura registrada."),
+                        },
+                        Err(e) => eprintln!("[ERRO]: Falha ao ler medições: {}", e),
+                    }
                 } else {
                     println!("Paciente não encontrado.");
                 }
@@ -163,30 +348,262 @@ pub fn menu_pacientes(manager: &mut FileManager<Paciente>, cidade_manager: &File
                 if let Ok(pacientes) = manager.read_all_records() {
                     println!("--- Lista de Todos os Pacientes ---");
                     for p in pacientes {
-                        println!("{:?}", p);
+                        println!();
+                        exibir_paciente(&p);
                     }
                 } else {
                     println!("Erro ao listar pacientes.");
                 }
             }
-            5 => break,
+            5 => {
+                let codigo = ler_u32("Digite o código do paciente para alteração: ");
+                let Ok(Some(paciente)) = manager.read_record(codigo) else {
+                    println!("Paciente não encontrado.");
+                    continue;
+                };
+                println!("Pressione Enter para manter o valor atual.");
+                let Some(cpf) = ler_cpf(manager, codigo, Some(&paciente.cpf)) else {
+                    continue;
+                };
+                let nome = ler_string_ou_manter("Nome", &paciente.nome);
+                let data_nascimento =
+                    ler_data_ou_manter("Data de Nascimento (DD/MM/AAAA)", paciente.data_nascimento);
+                if let Some(data) = data_nascimento
+                    && !data_nascimento_valida(data)
+                {
+                    continue;
+                }
+                let sexo = ler_sexo_ou_manter(paciente.sexo);
+                let endereco = ler_string_ou_manter("Endereço", &paciente.endereco);
+                let telefone = ler_string_ou_manter("Telefone", &paciente.telefone);
+                let codigo_cidade = ler_u32_ou_manter("Código da Cidade", paciente.codigo_cidade);
+                if !cidade_existe(cidade_manager, codigo_cidade) {
+                    continue;
+                }
+
+                let paciente_alterado = Paciente {
+                    codigo_paciente: codigo,
+                    nome,
+                    data_nascimento,
+                    endereco,
+                    telefone,
+                    codigo_cidade,
+                    cpf,
+                    sexo,
+                };
+                match manager.update_record(&paciente_alterado, codigo) {
+                    Ok(true) => println!("Paciente alterado com sucesso!"),
+                    Ok(false) => println!("Paciente não encontrado."),
+                    Err(e) => eprintln!("Erro ao alterar paciente: {}", e),
+                }
+            }
+            7 => {
+                let codigo = ler_u32("Código do paciente: ");
+                let Ok(Some(paciente)) = manager.read_record(codigo) else {
+                    println!("Paciente não encontrado.");
+                    continue;
+                };
+                let hoje = Local::now().date_naive();
+                let Some(data) = ler_data_ou_manter("Data da medição (DD/MM/AAAA)", Some(hoje))
+                else {
+                    continue;
+                };
+                if data > hoje || paciente.data_nascimento.is_some_and(|n| data < n) {
+                    println!(
+                        "\n[ERRO]: A data da medição deve estar entre o nascimento e hoje."
+                    );
+                    continue;
+                }
+                let Some(medicao) = ler_medicao(codigo, data) else {
+                    continue;
+                };
+                match clinical::registrar_medicao(medicoes, &medicao) {
+                    Ok(()) => {
+                        println!("Medição registrada com sucesso!");
+                        println!("{}", clinical::avaliar(&paciente, &medicao, referencia_imc));
+                    }
+                    Err(e) => eprintln!("Erro ao gravar medição: {}", e),
+                }
+            }
+            8 => {
+                let codigo = ler_u32("Código do paciente: ");
+                let Ok(Some(paciente)) = manager.read_record(codigo) else {
+                    println!("Paciente não encontrado.");
+                    continue;
+                };
+                match clinical::medicoes_do_paciente(medicoes, codigo) {
+                    Ok(lista) if lista.is_empty() => {
+                        println!("Nenhuma medição de peso e altura registrada.")
+                    }
+                    Ok(lista) => {
+                        println!("\n--- Medições de {} ---", paciente.nome);
+                        for medicao in &lista {
+                            println!(
+                                "{} - {:.2} kg, {:.2} m",
+                                medicao.data.format("%d/%m/%Y"),
+                                medicao.peso,
+                                medicao.altura
+                            );
+                            for linha in clinical::avaliar(&paciente, medicao, referencia_imc)
+                                .to_string()
+                                .lines()
+                            {
+                                println!("  {}", linha);
+                            }
+                        }
+                    }
+                    Err(e) => eprintln!("[ERRO]: Falha ao ler medições: {}", e),
+                }
+            }
+            9 => buscar_por_texto(manager, "Nome (ou parte dele): ", descricoes::paciente),
+            10 => break,
             _ => println!("Opção inválida."),
         }
     }
 }
 
-pub fn menu_medicos(
-    manager: &mut FileManager<Medico>,
-    cidade_manager: &FileManager<Cidade>,
-    especialidade_manager: &FileManager<Especialidade>,
-) {
+/// Busca pelo texto de `Entity::search_text` sem diferenciar maiúsculas nem
+/// acentos, tolerando erros de digitação; lista do mais relevante ao menos relevante.
+fn buscar_por_texto<T: Entity>(manager: &FileManager<T>, prompt: &str, descrever: impl Fn(&T) -> String) {
+    let termo = ler_string(prompt);
+    match manager.search(&termo) {
+        Ok(resultados) if resultados.is_empty() => println!("Nenhum resultado encontrado."),
+        Ok(resultados) => {
+            println!("--- {} resultado(s), do mais relevante ao menos relevante ---", resultados.len());
+            for (posicao, registro) in resultados.iter().enumerate() {
+                println!("{}. {}", posicao + 1, descrever(registro));
+            }
+        }
+        Err(e) => println!("\nERRO: {}", e),
+    }
+}
+
+/// Ex.: "8 anos, faixa Pediátrica (0 a 17 anos)", ou "Não informada".
+pub fn descrever_idade(paciente: &Paciente, em: NaiveDate) -> String {
+    match paciente.idade(em) {
+        Some(idade) => format!(
+            "{} anos, faixa {}",
+            idade,
+            FaixaEtaria::de_idade(idade).descricao()
+        ),
+        None => "Não informada".to_string(),
+    }
+}
+
+fn exibir_paciente(paciente: &Paciente) {
+    println!("Código: {}", paciente.codigo_paciente);
+    println!("Nome: {}", paciente.nome);
+    if paciente.cpf.is_empty() {
+        println!("CPF: Não cadastrado");
+    } else {
+        println!("CPF: {}", cpf::formatar(&paciente.cpf));
+    }
+    print_data("Data Nascimento", paciente.data_nascimento);
+    println!("Sexo: {}", paciente.sexo.map_or("Não informado", Sexo::descricao));
+    println!("Endereço: {}", paciente.endereco);
+    println!("Telefone: {}", paciente.telefone);
+    println!("Cidade: {}", paciente.codigo_cidade);
+}
+
+fn ler_sexo() -> Sexo {
+    loop {
+        match Sexo::interpretar(&ler_string("Sexo (M/F): ")) {
+            Some(sexo) => return sexo,
+            None => println!("Entrada inválida. Por favor, digite M ou F."),
+        }
+    }
+}
+
+fn ler_sexo_ou_manter(atual: Option<Sexo>) -> Option<Sexo> {
+    loop {
+        let atual_str = atual.map_or("não informado", Sexo::descricao);
+        let input = ler_string(&format!("Sexo (M/F) [{}]: ", atual_str));
+        if input.is_empty() {
+            return atual;
+        }
+        match Sexo::interpretar(&input) {
+            Some(sexo) => return Some(sexo),
+            None => println!("Entrada inválida. Por favor, digite M ou F."),
+        }
+    }
+}
+
+/// Lê peso e altura e confere as faixas aceitas.
+fn ler_medicao(codigo_paciente: u32, data: NaiveDate) -> Option<Medicao> {
+    let peso = ler_f32("Peso (kg): ");
+    let altura = ler_f32("Altura (m): ");
+    if let Err(e) = clinical::validar_medidas(peso, altura) {
+        println!("\nERRO DE VALIDAÇÃO: {}", e);
+        return None;
+    }
+    Some(Medicao {
+        codigo_paciente,
+        data,
+        peso,
+        altura,
+    })
+}
+
+fn data_nascimento_valida(data: NaiveDate) -> bool {
+    if data > Local::now().date_naive() {
+        println!("\n[ERRO]: A data de nascimento não pode estar no futuro.");
+        return false;
+    }
+    true
+}
+
+/// Lê e valida o CPF, recusando um que já pertença a outro paciente. Na
+/// alteração (`atual` presente), Enter mantém o valor; pacientes antigos
+/// ainda sem CPF podem continuar sem.
+fn ler_cpf(
+    manager: &FileManager<Paciente>,
+    codigo_paciente: u32,
+    atual: Option<&str>,
+) -> Option<String> {
+    let texto = match atual {
+        Some("") => ler_string("CPF (não cadastrado, Enter para deixar em branco): "),
+        Some(atual) => ler_string_ou_manter("CPF", &cpf::formatar(atual)),
+        None => ler_string("CPF: "),
+    };
+    if texto.is_empty() && atual == Some("") {
+        return Some(String::new());
+    }
+
+    let Some(cpf) = cpf::normalizar(&texto) else {
+        println!("\n[ERRO]: CPF inválido: {}", texto);
+        return None;
+    };
+    match manager.find_by_unique(&cpf) {
+        Ok(Some(outro)) if outro.codigo_paciente != codigo_paciente => {
+            println!(
+                "\n[ERRO]: CPF já cadastrado para o paciente {} ({}).",
+                outro.codigo_paciente, outro.nome
+            );
+            None
+        }
+        Ok(_) => Some(cpf),
+        Err(e) => {
+            eprintln!("[ERRO]: Falha ao consultar arquivo de Pacientes: {}", e);
+            None
+        }
+    }
+}
+
+pub fn menu_medicos(db: &mut Database) {
+    let manager = &mut db.medicos;
+    let cidade_manager = &db.cidades;
+    let especialidade_manager = &db.especialidades;
+    let consulta_manager = &db.consultas;
     loop {
         println!("\n--- Gerenciamento de Médicos ---");
         println!("1. Inserir novo médico");
         println!("2. Consultar médico por código");
         println!("3. Excluir médico por código");
         println!("4. Listar todos os médicos");
-        println!("5. Voltar ao menu principal");
+        println!("5. Alterar médico");
+        println!("6. Buscar médico por CRM");
+        println!("7. Buscar médico por nome");
+        println!("8. Voltar ao menu principal");
 
         let choice = ler_opcao_menu();
         match choice {
@@ -203,43 +620,21 @@ pub fn menu_medicos(
                         continue;
                     }
                 }
+                let Some((crm, uf_crm)) = ler_crm(manager, codigo, None) else {
+                    continue;
+                };
                 let nome = ler_string("Nome: ");
                 let endereco = ler_string("Endereço: ");
                 let telefone = ler_string("Telefone: ");
 
                 let codigo_cidade = ler_u32("Código da Cidade: ");
-                match cidade_manager.read_record(codigo_cidade) {
-                    Ok(Some(_)) => {}
-                    Ok(None) => {
-                        println!(
-                            "\n[ERRO]: Cidade com código {} não encontrada.",
-                            codigo_cidade
-                        );
-                        continue;
-                    }
-                    Err(e) => {
-                        eprintln!("[ERRO]: Falha ao consultar arquivo de Cidades: {}", e);
-                        continue;
-                    }
+                if !cidade_existe(cidade_manager, codigo_cidade) {
+                    continue;
                 }
 
                 let codigo_especialidade = ler_u32("Código da Especialidade: ");
-                match especialidade_manager.read_record(codigo_especialidade) {
-                    Ok(Some(_)) => {}
-                    Ok(None) => {
-                        println!(
-                            "\n[ERRO]: Especialidade com código {} não encontrada.",
-                            codigo_especialidade
-                        );
-                        continue;
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "[ERRO]: Falha ao consultar arquivo de Especialidades: {}",
-                            e
-                        );
-                        continue;
-                    }
+                if !especialidade_existe(especialidade_manager, codigo_especialidade) {
+                    continue;
                 }
 
                 let novo_medico = Medico {
@@ -249,6 +644,8 @@ pub fn menu_medicos(
                     telefone,
                     codigo_cidade,
                     codigo_especialidade,
+                    crm,
+                    uf_crm,
                 };
 
                 if let Err(e) = manager.create_record(&novo_medico, codigo) {
@@ -257,12 +654,26 @@ pub fn menu_medicos(
                     println!("Médico inserido com sucesso!");
                 }
             }
-            2 => {
-                let codigo = ler_u32("Digite o código do médico para consulta: ");
-                if let Ok(Some(medico)) = manager.read_record(codigo) {
+            2 | 6 => {
+                let medico = if choice == 2 {
+                    let codigo = ler_u32("Digite o código do médico para consulta: ");
+                    manager.read_record(codigo)
+                } else {
+                    let numero = ler_string("Número do CRM: ");
+                    let uf = ler_string("UF do CRM: ");
+                    match crm::normalizar(&numero, &uf) {
+                        Some((numero, uf)) => manager.find_by_unique(&crm::chave(&numero, &uf)),
+                        None => {
+                            println!("\n[ERRO]: CRM inválido: {}/{}", numero, uf);
+                            continue;
+                        }
+                    }
+                };
+                if let Ok(Some(medico)) = medico {
                     println!("\n--- Dados do Médico ---");
                     println!("Código: {}", medico.codigo_medico);
                     println!("Nome: {}", medico.nome);
+                    println!("CRM: {}", crm::formatar(&medico.crm, &medico.uf_crm));
                     println!("Endereço: {}", medico.endereco);
                     println!("Telefone: {}", medico.telefone);
 
@@ -300,26 +711,149 @@ pub fn menu_medicos(
                 if let Ok(medicos) = manager.read_all_records() {
                     println!("--- Lista de Todos os Médicos ---");
                     for m in medicos {
-                        println!("{:?}", m);
+                        println!();
+                        exibir_medico(&m);
                     }
                 } else {
                     println!("Erro ao listar médicos.");
                 }
             }
-            5 => break,
+            5 => {
+                let codigo = ler_u32("Digite o código do médico para alteração: ");
+                let Ok(Some(medico)) = manager.read_record(codigo) else {
+                    println!("Médico não encontrado.");
+                    continue;
+                };
+                println!("Pressione Enter para manter o valor atual.");
+                let Some((crm, uf_crm)) = ler_crm(manager, codigo, Some(&medico)) else {
+                    continue;
+                };
+                let nome = ler_string_ou_manter("Nome", &medico.nome);
+                let endereco = ler_string_ou_manter("Endereço", &medico.endereco);
+                let telefone = ler_string_ou_manter("Telefone", &medico.telefone);
+
+                let codigo_cidade = ler_u32_ou_manter("Código da Cidade", medico.codigo_cidade);
+                if !cidade_existe(cidade_manager, codigo_cidade) {
+                    continue;
+                }
+
+                let codigo_especialidade =
+                    ler_u32_ou_manter("Código da Especialidade", medico.codigo_especialidade);
+                if !especialidade_existe(especialidade_manager, codigo_especialidade) {
+                    continue;
+                }
+                if codigo_especialidade != medico.codigo_especialidade {
+                    match agendamento::consultas_em_aberto_do_medico(consulta_manager, codigo) {
+                        Ok(abertas) if abertas.is_empty() => {}
+                        Ok(abertas) => {
+                            let codigos: Vec<String> =
+                                abertas.iter().map(|c| c.codigo_consulta.to_string()).collect();
+                            println!(
+                                "\n[ERRO]: O médico tem consultas agendadas ou confirmadas ({}). Conclua ou cancele essas consultas antes de trocar a especialidade.",
+                                codigos.join(", ")
+                            );
+                            continue;
+                        }
+                        Err(e) => {
+                            eprintln!("[ERRO]: Falha ao consultar arquivo de Consultas: {}", e);
+                            continue;
+                        }
+                    }
+                }
+
+                let medico_alterado = Medico {
+                    codigo_medico: codigo,
+                    nome,
+                    endereco,
+                    telefone,
+                    codigo_cidade,
+                    codigo_especialidade,
+                    crm,
+                    uf_crm,
+                };
+                match manager.update_record(&medico_alterado, codigo) {
+                    Ok(true) => println!("Médico alterado com sucesso!"),
+                    Ok(false) => println!("Médico não encontrado."),
+                    Err(e) => eprintln!("Erro ao alterar médico: {}", e),
+                }
+            }
+            7 => buscar_por_texto(manager, "Nome (ou parte dele): ", descricoes::medico),
+            8 => break,
             _ => println!("Opção inválida."),
         }
     }
 }
 
-pub fn menu_especialidades(manager: &mut FileManager<Especialidade>) {
+fn exibir_medico(medico: &Medico) {
+    println!("Código: {}", medico.codigo_medico);
+    println!("Nome: {}", medico.nome);
+    if medico.crm.is_empty() {
+        println!("CRM: Não cadastrado");
+    } else {
+        println!("CRM: {}", crm::formatar(&medico.crm, &medico.uf_crm));
+    }
+    println!("Endereço: {}", medico.endereco);
+    println!("Telefone: {}", medico.telefone);
+    println!("Cidade: {}", medico.codigo_cidade);
+    println!("Especialidade: {}", medico.codigo_especialidade);
+}
+
+/// Lê e valida número e UF do CRM, recusando um que já pertença a outro
+/// médico. Na alteração, Enter mantém os valores; médicos antigos ainda sem
+/// CRM podem continuar sem.
+fn ler_crm(
+    manager: &FileManager<Medico>,
+    codigo_medico: u32,
+    atual: Option<&Medico>,
+) -> Option<(String, String)> {
+    let (numero, uf) = match atual {
+        Some(medico) if medico.crm.is_empty() => {
+            let numero = ler_string("Número do CRM (não cadastrado, Enter para deixar em branco): ");
+            if numero.is_empty() {
+                return Some((String::new(), String::new()));
+            }
+            (numero, ler_string("UF do CRM: "))
+        }
+        Some(medico) => (
+            ler_string_ou_manter("Número do CRM", &medico.crm),
+            ler_string_ou_manter("UF do CRM", &medico.uf_crm),
+        ),
+        None => (ler_string("Número do CRM: "), ler_string("UF do CRM: ")),
+    };
+
+    let Some((numero, uf)) = crm::normalizar(&numero, &uf) else {
+        println!("\n[ERRO]: CRM inválido: {}/{}", numero, uf);
+        return None;
+    };
+    match manager.find_by_unique(&crm::chave(&numero, &uf)) {
+        Ok(Some(outro)) if outro.codigo_medico != codigo_medico => {
+            println!(
+                "\n[ERRO]: {} já cadastrado para o médico {} ({}).",
+                crm::formatar(&numero, &uf),
+                outro.codigo_medico,
+                outro.nome
+            );
+            None
+        }
+        Ok(_) => Some((numero, uf)),
+        Err(e) => {
+            eprintln!("[ERRO]: Falha ao consultar arquivo de Medicos: {}", e);
+            None
+        }
+    }
+}
+
+pub fn menu_especialidades(db: &mut Database) {
+    let manager = &mut db.especialidades;
     loop {
         println!("\n--- Gerenciar Especialidades ---");
         println!("1. Incluir Especialidade");
         println!("2. Consultar Especialidade por código");
         println!("3. Excluir Especialidade");
         println!("4. Exibir todas as Especialidades");
-        println!("5. Voltar");
+        println!("5. Alterar Especialidade");
+        println!("6. Buscar Especialidade por descrição");
+        println!("7. Voltar");
         let choice = ler_opcao_menu();
 
         match choice {
@@ -343,11 +877,22 @@ pub fn menu_especialidades(manager: &mut FileManager<Especialidade>) {
                 let descricao = ler_string("Descrição: ");
                 let valor_consulta = ler_f32("Valor da Consulta: ");
                 let limite_diario = ler_u32("Limite Diário de Consultas: ");
+                let duracao_consulta = ler_u32("Duração da Consulta (minutos): ");
+                if duracao_consulta == 0 {
+                    println!("\n[ERRO]: A duração da consulta deve ser maior que zero.");
+                    continue;
+                }
+                let Some((idade_minima, idade_maxima)) = ler_limites_idade(None, None) else {
+                    continue;
+                };
                 let especialidade = Especialidade {
                     codigo_especialidade: codigo,
                     descricao,
                     valor_consulta,
                     limite_diario,
+                    duracao_consulta,
+                    idade_minima,
+                    idade_maxima,
                 };
                 if let Err(e) = manager.create_record(&especialidade, codigo) {
                     println!("Erro ao incluir Especialidade: {}", e);
@@ -358,7 +903,7 @@ pub fn menu_especialidades(manager: &mut FileManager<Especialidade>) {
             2 => {
                 let codigo = ler_u32("Digite o código da Especialidade: ");
                 if let Ok(Some(especialidade)) = manager.read_record(codigo) {
-                    println!("{:?}", especialidade);
+                    exibir_especialidade(&especialidade);
                 } else {
                     println!("Especialidade não encontrada.");
                 }
@@ -374,24 +919,112 @@ pub fn menu_especialidades(manager: &mut FileManager<Especialidade>) {
             4 => {
                 if let Ok(especialidades) = manager.read_all_records() {
                     for especialidade in especialidades {
-                        println!("{:?}", especialidade);
+                        println!();
+                        exibir_especialidade(&especialidade);
                     }
                 }
             }
-            5 => break,
+            5 => {
+                let codigo = ler_u32("Digite o código da Especialidade para alterar: ");
+                let Ok(Some(especialidade)) = manager.read_record(codigo) else {
+                    println!("Especialidade não encontrada.");
+                    continue;
+                };
+                println!("Pressione Enter para manter o valor atual.");
+                let mut especialidade_alterada = Especialidade {
+                    codigo_especialidade: codigo,
+                    descricao: ler_string_ou_manter("Descrição", &especialidade.descricao),
+                    valor_consulta: ler_f32_ou_manter(
+                        "Valor da Consulta",
+                        especialidade.valor_consulta,
+                    ),
+                    limite_diario: ler_u32_ou_manter(
+                        "Limite Diário de Consultas",
+                        especialidade.limite_diario,
+                    ),
+                    duracao_consulta: ler_u32_ou_manter(
+                        "Duração da Consulta (minutos)",
+                        especialidade.duracao_consulta,
+                    ),
+                    ..Default::default()
+                };
+                if especialidade_alterada.duracao_consulta == 0 {
+                    println!("\n[ERRO]: A duração da consulta deve ser maior que zero.");
+                    continue;
+                }
+                let Some((idade_minima, idade_maxima)) =
+                    ler_limites_idade(especialidade.idade_minima, especialidade.idade_maxima)
+                else {
+                    continue;
+                };
+                especialidade_alterada.idade_minima = idade_minima;
+                especialidade_alterada.idade_maxima = idade_maxima;
+                match manager.update_record(&especialidade_alterada, codigo) {
+                    Ok(true) => println!("Especialidade alterada com sucesso!"),
+                    Ok(false) => println!("Especialidade não encontrada."),
+                    Err(e) => println!("Erro ao alterar Especialidade: {}", e),
+                }
+            }
+            6 => buscar_por_texto(manager, "Descrição (ou parte dela): ", descricoes::especialidade),
+            7 => break,
             _ => println!("Opção inválida."),
         }
     }
 }
 
-pub fn menu_cidades(manager: &mut FileManager<Cidade>) {
-    loop {
+fn exibir_especialidade(especialidade: &Especialidade) {
+    println!("Código: {}", especialidade.codigo_especialidade);
+    println!("Descrição: {}", especialidade.descricao);
+    println!("Valor da Consulta: R$ {:.2}", especialidade.valor_consulta);
+    println!("Limite Diário: {} consulta(s)", especialidade.limite_diario);
+    println!("Duração da Consulta: {} minutos", especialidade.duracao_consulta);
+    println!("Atende: {}", especialidade.descricao_idades());
+}
+
+/// Lê a idade mínima e a máxima atendidas pela especialidade. Enter mantém o
+/// valor mostrado e "-" deixa o lado sem limite.
+fn ler_limites_idade(
+    minima: Option<u32>,
+    maxima: Option<u32>,
+) -> Option<(Option<u32>, Option<u32>)> {
+    let minima = ler_limite_idade("Idade mínima", minima)?;
+    let maxima = ler_limite_idade("Idade máxima", maxima)?;
+    if let (Some(minima), Some(maxima)) = (minima, maxima)
+        && minima > maxima
+    {
+        println!("\n[ERRO]: A idade mínima não pode ser maior que a máxima.");
+        return None;
+    }
+    Some((minima, maxima))
+}
+
+fn ler_limite_idade(prompt: &str, atual: Option<u32>) -> Option<Option<u32>> {
+    let mostrado = atual.map_or("sem limite".to_string(), |idade| idade.to_string());
+    let input = ler_string(&format!("{} [{}]: ", prompt, mostrado));
+    match input.as_str() {
+        "" => Some(atual),
+        "-" => Some(None),
+        _ => match input.parse() {
+            Ok(idade) => Some(Some(idade)),
+            Err(_) => {
+                println!("\n[ERRO]: Idade inválida: {}", input);
+                None
+            }
+        },
+    }
+}
+
+pub fn menu_cidades(db: &mut Database) {
+    let manager = &mut db.cidades;
+    loop {
         println!("\n--- Gerenciar Cidades ---");
         println!("1. Incluir Cidade");
         println!("2. Consultar Cidade por código");
         println!("3. Excluir Cidade");
         println!("4. Exibir todas as Cidades");
-        println!("5. Voltar");
+        println!("5. Alterar Cidade");
+        println!("6. Buscar Cidade por descrição");
+        println!("7. Voltar");
         let choice = ler_opcao_menu();
 
         match choice {
@@ -424,7 +1057,7 @@ pub fn menu_cidades(manager: &mut FileManager<Cidade>) {
             2 => {
                 let codigo = ler_u32("Digite o código da Cidade: ");
                 if let Ok(Some(cidade)) = manager.read_record(codigo) {
-                    println!("{:?}", cidade);
+                    exibir_cidade(&cidade);
                 } else {
                     println!("Cidade não encontrada.");
                 }
@@ -440,27 +1073,53 @@ pub fn menu_cidades(manager: &mut FileManager<Cidade>) {
             4 => {
                 if let Ok(cidades) = manager.read_all_records() {
                     for cidade in cidades {
-                        println!("{:?}", cidade);
+                        println!();
+                        exibir_cidade(&cidade);
                     }
                 }
             }
-            5 => break,
+            5 => {
+                let codigo = ler_u32("Digite o código da Cidade para alterar: ");
+                let Ok(Some(cidade)) = manager.read_record(codigo) else {
+                    println!("Cidade não encontrada.");
+                    continue;
+                };
+                println!("Pressione Enter para manter o valor atual.");
+                let cidade_alterada = Cidade {
+                    codigo_cidade: codigo,
+                    descricao: ler_string_ou_manter("Descrição", &cidade.descricao),
+                    estado: ler_string_ou_manter("Estado", &cidade.estado),
+                };
+                match manager.update_record(&cidade_alterada, codigo) {
+                    Ok(true) => println!("Cidade alterada com sucesso!"),
+                    Ok(false) => println!("Cidade não encontrada."),
+                    Err(e) => println!("Erro ao alterar Cidade: {}", e),
+                }
+            }
+            6 => buscar_por_texto(manager, "Descrição (ou parte dela): ", descricoes::cidade),
+            7 => break,
             _ => println!("Opção inválida."),
         }
     }
 }
 
-pub fn menu_exames(
-    manager: &mut FileManager<Exame>,
-    especialidade_manager: &FileManager<Especialidade>,
-) {
+fn exibir_cidade(cidade: &Cidade) {
+    println!("Código: {}", cidade.codigo_cidade);
+    println!("Cidade: {}, Estado: {}", cidade.descricao, cidade.estado);
+}
+
+pub fn menu_exames(db: &mut Database) {
+    let manager = &mut db.exames;
+    let especialidade_manager = &db.especialidades;
     loop {
         println!("\n--- Gerenciamento de Exames ---");
         println!("1. Inserir novo exame");
         println!("2. Consultar exame por código");
         println!("3. Excluir exame por código");
         println!("4. Listar todos os exames");
-        println!("5. Voltar ao menu principal");
+        println!("5. Alterar exame");
+        println!("6. Buscar exame por descrição");
+        println!("7. Voltar ao menu principal");
 
         let choice = ler_opcao_menu();
         match choice {
@@ -535,40 +1194,73 @@ pub fn menu_exames(
                 if let Ok(exames) = manager.read_all_records() {
                     println!("--- Lista de Todos os Exames ---");
                     for e in exames {
-                        println!("{:?}", e);
+                        println!();
+                        exibir_exame(&e);
                     }
                 } else {
                     println!("Erro ao listar exames.");
                 }
             }
-            5 => break,
+            5 => {
+                let codigo = ler_u32("Digite o código do exame para alteração: ");
+                let Ok(Some(exame)) = manager.read_record(codigo) else {
+                    println!("Exame não encontrado.");
+                    continue;
+                };
+                println!("Pressione Enter para manter o valor atual.");
+                let descricao = ler_string_ou_manter("Descrição", &exame.descricao);
+                let codigo_especialidade =
+                    ler_u32_ou_manter("Código da Especialidade", exame.codigo_especialidade);
+                let valor = ler_f32_ou_manter("Valor do Exame", exame.valor_exame);
+
+                if let Ok(Some(especialidade)) =
+                    especialidade_manager.read_record(codigo_especialidade)
+                {
+                    println!("Especialidade selecionada: {}", especialidade.descricao);
+                } else {
+                    println!(
+                        "Atenção: A especialidade com o código {} não foi encontrada.",
+                        codigo_especialidade
+                    );
+                }
+
+                let exame_alterado = Exame {
+                    codigo_exame: codigo,
+                    descricao,
+                    codigo_especialidade,
+                    valor_exame: valor,
+                };
+                match manager.update_record(&exame_alterado, codigo) {
+                    Ok(true) => println!("Exame alterado com sucesso!"),
+                    Ok(false) => println!("Exame não encontrado."),
+                    Err(e) => eprintln!("Erro ao alterar exame: {}", e),
+                }
+            }
+            6 => buscar_por_texto(manager, "Descrição (ou parte dela): ", descricoes::exame),
+            7 => break,
             _ => println!("Opção inválida."),
         }
     }
 }
 
-pub fn menu_consultas(
-    manager: &mut FileManager<Consulta>,
-    paciente_manager: &FileManager<Paciente>,
-    medico_manager: &FileManager<Medico>,
-    cidade_manager: &FileManager<Cidade>,
-    especialidade_manager: &FileManager<Especialidade>,
-    exame_manager: &FileManager<Exame>,
-    diaria_manager: &mut FileManager<Diaria>,
-) {
+pub fn menu_consultas(db: &mut Database, regras: &[Box<dyn RegraAgendamento>]) {
     loop {
         println!("\n--- Gerenciamento de Consultas ---");
         println!("1. Inserir nova consulta");
         println!("2. Consultar consulta por código");
-        println!("3. Excluir consulta por código");
+        println!("3. Cancelar consulta");
         println!("4. Listar todas as consultas");
-        println!("5. Voltar ao menu principal");
+        println!("5. Alterar consulta");
+        println!("6. Remarcar consulta");
+        println!("7. Buscar próximos horários livres");
+        println!("8. Atualizar status da consulta");
+        println!("9. Voltar ao menu principal");
 
         let choice = ler_opcao_menu();
         match choice {
             1 => {
                 let codigo = ler_u32("Código da Consulta: ");
-                match manager.read_record(codigo) {
+                match db.consultas.read_record(codigo) {
                     Ok(Some(_)) => {
                         println!("\n[ERRO]: Consulta com código {} já existe.", codigo);
                         continue;
@@ -580,7 +1272,7 @@ pub fn menu_consultas(
                     }
                 }
                 let codigo_paciente = ler_u32("Código do Paciente: ");
-                let paciente = paciente_manager
+                let paciente = db.pacientes
                     .read_record(codigo_paciente)
                     .unwrap_or(None);
                 if paciente.is_none() {
@@ -592,7 +1284,7 @@ pub fn menu_consultas(
                 }
 
                 let codigo_medico = ler_u32("Código do Médico: ");
-                let medico = medico_manager.read_record(codigo_medico).unwrap_or(None);
+                let medico = db.medicos.read_record(codigo_medico).unwrap_or(None);
                 if medico.is_none() {
                     println!(
                         "\nERRO DE VALIDAÇÃO: Médico com código {} não encontrado.",
@@ -601,60 +1293,81 @@ pub fn menu_consultas(
                     continue;
                 }
 
-                let codigo_exame = ler_u32("Código do Exame: ");
-                let exame = exame_manager.read_record(codigo_exame).unwrap_or(None);
-                if exame.is_none() {
-                    println!(
-                        "\nERRO DE VALIDAÇÃO: Exame com código {} não encontrado.",
-                        codigo_exame
-                    );
+                let Some(exames) = ler_exames(db, None) else {
                     continue;
-                }
+                };
 
-                let data_str = ler_string("Data (AAAAMMDD): ");
-                let hoje = Local::now().date_naive();
-                let data_consulta_res = NaiveDate::parse_from_str(&data_str, "%Y%m%d");
-                let _data_consulta = match data_consulta_res {
-                    Ok(d) => {
-                        if d < hoje {
-                            println!(
-                                "\nERRO: Não é possível agendar consultas para uma data passada ({}).",
-                                data_str
-                            );
-                            continue;
-                        }
-                        d
+                let data = match agendamento::validar_data(&ler_string("Data (DD/MM/AAAA): ")) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        println!("\nERRO: {}", e);
+                        continue;
                     }
-                    Err(_) => {
-                        println!("\nERRO: Formato de data inválido. Use AAAAMMDD.");
+                };
+                let hora = match agendamento::validar_hora(&ler_string("Hora (HH:MM): ")) {
+                    Ok(hora) => hora,
+                    Err(e) => {
+                        println!("\nERRO: {}", e);
                         continue;
                     }
                 };
 
                 let especialidade_medico = if let Some(m) = &medico {
-                    especialidade_manager
+                    db.especialidades
                         .read_record(m.codigo_especialidade)
                         .unwrap_or(None)
                 } else {
                     None
                 };
 
-                if let Some(esp) = &especialidade_medico {
-                    let codigo_dia = data_str.parse::<u32>().unwrap();
-                    let diaria = diaria_manager.read_record(codigo_dia).unwrap_or(None);
-                    let consultas_do_dia = if let Some(d) = &diaria {
-                        d.quantidade_consultas
-                    } else {
-                        0
-                    };
+                let mut nova_consulta = Consulta {
+                    codigo_consulta: codigo,
+                    codigo_paciente,
+                    codigo_medico,
+                    codigo_exame: 0,
+                    data,
+                    hora: hora.format("%H:%M").to_string(),
+                    status: StatusConsulta::Agendada,
+                    historico_status: vec![MudancaStatus::agora(StatusConsulta::Agendada)],
+                };
 
-                    if consultas_do_dia >= esp.limite_diario {
-                        println!(
-                            "ATENÇÃO: Não há mais vagas para esta especialidade nesta data. Limite diário de {} atingido.",
-                            esp.limite_diario
-                        );
+                if let (Some(p), Some(m)) = (&paciente, &medico) {
+                    let agendamento = Agendamento {
+                        paciente: p,
+                        medico: m,
+                        exames: &exames,
+                        data: nova_consulta.data,
+                    };
+                    if let Err(e) = regras::validar(regras, db, &agendamento) {
+                        println!("\nERRO DE VALIDAÇÃO: {}", e);
                         continue;
                     }
+                }
+
+                let mut medico = medico;
+                if let Some(esp) = &especialidade_medico {
+                    if let Err(e) = agendamento::verificar_horario(db, &nova_consulta, esp) {
+                        println!("ATENÇÃO: {}", e);
+                        let Some(horario) = escolher_horario_livre(
+                            db,
+                            esp.codigo_especialidade,
+                            nova_consulta.data,
+                            codigo_paciente,
+                        ) else {
+                            continue;
+                        };
+                        nova_consulta.codigo_medico = horario.codigo_medico;
+                        nova_consulta.data = horario.data;
+                        nova_consulta.hora = horario.hora.format("%H:%M").to_string();
+                        // Médico e data mudaram: tudo é validado de novo antes de gravar.
+                        if let Err(e) =
+                            agendamento::validar_consulta(db, regras, &nova_consulta, &exames)
+                        {
+                            println!("\nERRO DE VALIDAÇÃO: {}", e);
+                            continue;
+                        }
+                        medico = db.medicos.read_record(horario.codigo_medico).unwrap_or(None);
+                    }
                 } else {
                     println!(
                         "Atenção: Não foi possível carregar a especialidade do médico. Não é possível verificar o limite diário."
@@ -665,8 +1378,8 @@ pub fn menu_consultas(
                 let valor_consulta = especialidade_medico
                     .as_ref()
                     .map_or(0.0, |e| e.valor_consulta);
-                let valor_exame = exame.as_ref().map_or(0.0, |e| e.valor_exame);
-                let valor_total = valor_consulta + valor_exame;
+                let valor_exames: f32 = exames.iter().map(|e| e.valor_exame).sum();
+                let valor_total = valor_consulta + valor_exames;
 
                 let nome_paciente = paciente
                     .as_ref()
@@ -678,54 +1391,47 @@ pub fn menu_consultas(
                     .map_or("Erro (Não deveria acontecer)".to_string(), |m| {
                         m.nome.clone()
                     });
-                let desc_exame = exame
-                    .as_ref()
-                    .map_or("Erro (Não deveria acontecer)".to_string(), |e| {
-                        e.descricao.clone()
-                    });
 
                 println!("\n--- Resumo da Consulta ---");
                 println!("Paciente: {}", nome_paciente);
                 println!("Médico: {}", nome_medico);
-                println!("Exame: {}", desc_exame);
+                exibir_exames(&exames);
+                print_data("Data", Some(nova_consulta.data));
+                println!("Hora: {}", nova_consulta.hora);
                 println!("Valor Total a Pagar: R$ {:.2}", valor_total);
                 println!("--------------------------");
 
-                let nova_consulta = Consulta {
-                    codigo_consulta: codigo,
-                    codigo_paciente,
-                    codigo_medico,
-                    codigo_exame,
-                    data: data_str,
-                    hora: ler_string("Hora (HH:MM): "),
-                };
-
-                if let Err(e) = manager.create_record(&nova_consulta, codigo) {
+                if let Err(e) = db.consultas.create_record(&nova_consulta, codigo) {
                     eprintln!("Erro ao inserir consulta: {}", e);
                 } else {
                     println!("Consulta inserida com sucesso!");
-                    if let Some(esp) = especialidade_medico {
-                        atualizar_diaria(
-                            diaria_manager,
-                            nova_consulta.data.parse().unwrap(),
+                    let codigos: Vec<u32> = exames.iter().map(|e| e.codigo_exame).collect();
+                    if let Err(e) = exames_consulta::definir_exames(db, codigo, &codigos) {
+                        eprintln!("Erro ao gravar exames da consulta: {}", e);
+                    }
+                    if let Some(esp) = especialidade_medico
+                        && let Err(e) = agendamento::atualizar_diaria(
+                            &mut db.diarias,
+                            datas::codigo(nova_consulta.data),
                             esp.codigo_especialidade,
                             1,
-                        );
+                        )
+                    {
+                        eprintln!("Erro ao atualizar diária: {}", e);
                     }
                 }
             }
             2 => {
                 let codigo = ler_u32("Digite o código da consulta para consulta: ");
-                if let Ok(Some(consulta)) = manager.read_record(codigo) {
-                    let paciente = paciente_manager
+                if let Ok(Some(consulta)) = db.consultas.read_record(codigo) {
+                    let paciente = db.pacientes
                         .read_record(consulta.codigo_paciente)
                         .unwrap_or(None);
-                    let medico = medico_manager
+                    let medico = db.medicos
                         .read_record(consulta.codigo_medico)
                         .unwrap_or(None);
-                    let exame = exame_manager
-                        .read_record(consulta.codigo_exame)
-                        .unwrap_or(None);
+                    let exames = exames_consulta::exames_da_consulta(db, codigo)
+                        .unwrap_or_default();
 
                     let nome_paciente = paciente
                         .as_ref()
@@ -733,12 +1439,9 @@ pub fn menu_consultas(
                     let nome_medico = medico
                         .as_ref()
                         .map_or("Não encontrado".to_string(), |m| m.nome.clone());
-                    let desc_exame = exame
-                        .as_ref()
-                        .map_or("Não encontrado".to_string(), |e| e.descricao.clone());
 
                     let nome_cidade = if let Some(p) = &paciente {
-                        cidade_manager
+                        db.cidades
                             .read_record(p.codigo_cidade)
                             .unwrap_or(None)
                             .map_or("Não encontrada".to_string(), |c| c.descricao)
@@ -749,120 +1452,1357 @@ pub fn menu_consultas(
                     let valor_consulta = medico
                         .as_ref()
                         .and_then(|m| {
-                            especialidade_manager
+                            db.especialidades
                                 .read_record(m.codigo_especialidade)
                                 .unwrap_or(None)
                         })
                         .map_or(0.0, |e| e.valor_consulta);
-                    let valor_exame = exame.as_ref().map_or(0.0, |e| e.valor_exame);
-                    let valor_total = valor_consulta + valor_exame;
+                    let valor_exames: f32 = exames.iter().map(|e| e.valor_exame).sum();
+                    let valor_total = valor_consulta + valor_exames;
 
                     println!("\n--- Detalhes da Consulta ---");
                     println!("Código: {}", consulta.codigo_consulta);
                     println!("Paciente: {}", nome_paciente);
+                    if let Some(p) = &paciente {
+                        println!(
+                            "Idade na data da consulta: {}",
+                            descrever_idade(p, consulta.data)
+                        );
+                    }
                     println!("Cidade do Paciente: {}", nome_cidade);
                     println!("Médico: {}", nome_medico);
-                    println!("Exame: {}", desc_exame);
-                    print_data("Data Consulta:", &consulta.data);
+                    if let Some(m) = &medico {
+                        println!("CRM: {}", crm::formatar(&m.crm, &m.uf_crm));
+                    }
+                    exibir_exames(&exames);
+                    let diagnosticos = cid10::diagnosticos_da_consulta(db, consulta.codigo_consulta)
+                        .unwrap_or_default();
+                    exibir_diagnosticos(&diagnosticos);
+                    print_data("Data Consulta", Some(consulta.data));
                     println!("Hora: {}", consulta.hora);
+                    println!("Status: {}", consulta.status.descricao());
+                    for mudanca in &consulta.historico_status {
+                        println!("  {} em {}", mudanca.status.descricao(), mudanca.em);
+                    }
                     println!("Valor Total a Pagar: R$ {:.2}", valor_total);
                 } else {
                     println!("Consulta não encontrada.");
                 }
             }
             3 => {
-                let codigo = ler_u32("Digite o código da consulta para exclusão: ");
-                if let Ok(Some(consulta)) = manager.read_record(codigo) {
-                    if let Ok(true) = manager.delete_record(codigo) {
-                        println!("Consulta excluída (logicamente) com sucesso!");
-                        if let Some(medico) = medico_manager
-                            .read_record(consulta.codigo_medico)
-                            .unwrap_or(None)
-                        {
-                            if let Some(especialidade) = especialidade_manager
-                                .read_record(medico.codigo_especialidade)
-                                .unwrap_or(None)
-                            {
-                                atualizar_diaria(
-                                    diaria_manager,
-                                    consulta.data.parse().unwrap(),
-                                    especialidade.codigo_especialidade,
-                                    -1,
-                                );
-                            }
-                        }
-                    } else {
-                        println!("Consulta não encontrada ou erro na exclusão.");
-                    }
-                } else {
-                    println!("Consulta não encontrada.");
+                let codigo = ler_u32("Digite o código da consulta para cancelamento: ");
+                let confirmacao = ler_string("Confirma o cancelamento? (s/n): ");
+                if !confirmacao.eq_ignore_ascii_case("s") {
+                    println!("Nada foi alterado.");
+                    continue;
+                }
+                match agendamento::alterar_status(db, codigo, StatusConsulta::Cancelada) {
+                    Ok(_) => println!("Consulta cancelada com sucesso!"),
+                    Err(e) => println!("\nERRO: {}", e),
                 }
             }
             4 => {
-                if let Ok(consultas) = manager.read_all_records() {
+                if let Ok(consultas) = db.consultas.read_all_records() {
                     println!("--- Lista de Todas as Consultas ---");
                     for c in consultas {
-                        println!("{:?}", c);
+                        println!();
+                        exibir_consulta(&c);
                     }
                 } else {
                     println!("Erro ao listar consultas.");
                 }
             }
-            5 => break,
+            5 => {
+                let codigo = ler_u32("Digite o código da consulta para alteração: ");
+                let Ok(Some(consulta)) = db.consultas.read_record(codigo) else {
+                    println!("Consulta não encontrada.");
+                    continue;
+                };
+                if !consulta.status.em_aberto() {
+                    println!(
+                        "\nERRO: {}",
+                        agendamento::ErroAgendamento::ConsultaEncerrada(consulta.status)
+                    );
+                    continue;
+                }
+                println!("Pressione Enter para manter o valor atual.");
+                println!("(Para mudar data ou hora, use a opção Remarcar.)");
+
+                let codigo_paciente =
+                    ler_u32_ou_manter("Código do Paciente", consulta.codigo_paciente);
+                let Some(paciente) = db.pacientes.read_record(codigo_paciente).unwrap_or(None)
+                else {
+                    println!(
+                        "\nERRO DE VALIDAÇÃO: Paciente com código {} não encontrado.",
+                        codigo_paciente
+                    );
+                    continue;
+                };
+
+                // A diária é contada por especialidade; trocar para um médico de outra
+                // especialidade exigiria mover a vaga, então só é aceito dentro da mesma.
+                let codigo_medico = ler_u32_ou_manter("Código do Médico", consulta.codigo_medico);
+                let Some(medico) = db.medicos.read_record(codigo_medico).unwrap_or(None) else {
+                    println!(
+                        "\nERRO DE VALIDAÇÃO: Médico com código {} não encontrado.",
+                        codigo_medico
+                    );
+                    continue;
+                };
+                let medico_atual = db.medicos
+                    .read_record(consulta.codigo_medico)
+                    .unwrap_or(None);
+                if let Some(atual) = &medico_atual
+                    && atual.codigo_especialidade != medico.codigo_especialidade
+                {
+                    println!(
+                        "\nERRO DE VALIDAÇÃO: O novo médico deve ser da mesma especialidade do atual."
+                    );
+                    continue;
+                }
+
+                let atuais = exames_consulta::codigos_exames(db, codigo).unwrap_or_default();
+                let Some(exames) = ler_exames(db, Some(&atuais)) else {
+                    continue;
+                };
+
+                let consulta_alterada = Consulta {
+                    codigo_paciente,
+                    codigo_medico,
+                    ..consulta
+                };
+                let agendamento = Agendamento {
+                    paciente: &paciente,
+                    medico: &medico,
+                    exames: &exames,
+                    data: consulta_alterada.data,
+                };
+                if let Err(e) = regras::validar(regras, db, &agendamento) {
+                    println!("\nERRO DE VALIDAÇÃO: {}", e);
+                    continue;
+                }
+                let duracao = db
+                    .especialidades
+                    .read_record(medico.codigo_especialidade)
+                    .unwrap_or(None)
+                    .map_or(DURACAO_PADRAO, |e| e.duracao_consulta);
+                if let Err(e) = agendamento::verificar_expediente(db, &consulta_alterada, duracao)
+                    .and_then(|_| agendamento::verificar_conflitos(db, &consulta_alterada, duracao))
+                {
+                    println!("\nERRO: {}", e);
+                    continue;
+                }
+                let codigos: Vec<u32> = exames.iter().map(|e| e.codigo_exame).collect();
+                if let Err(e) = exames_consulta::verificar_remocao(db, codigo, &codigos) {
+                    println!("\nERRO DE VALIDAÇÃO: {}", e);
+                    continue;
+                }
+                match db.consultas.update_record(&consulta_alterada, codigo) {
+                    Ok(true) => match exames_consulta::definir_exames(db, codigo, &codigos) {
+                        Ok(()) => println!("Consulta alterada com sucesso!"),
+                        Err(e) => eprintln!("Erro ao gravar exames da consulta: {}", e),
+                    },
+                    Ok(false) => println!("Consulta não encontrada."),
+                    Err(e) => eprintln!("Erro ao alterar consulta: {}", e),
+                }
+            }
+            6 => {
+                let codigo = ler_u32("Digite o código da consulta para remarcar: ");
+                let Ok(Some(consulta)) = db.consultas.read_record(codigo) else {
+                    println!("Consulta não encontrada.");
+                    continue;
+                };
+                print_data("Data atual", Some(consulta.data));
+                println!("Hora atual: {}", consulta.hora);
+
+                let nova_data = ler_string_ou_manter(
+                    "Nova data (DD/MM/AAAA)",
+                    &consulta.data.format("%d/%m/%Y").to_string(),
+                );
+                let nova_hora = ler_string_ou_manter("Nova hora (HH:MM)", &consulta.hora);
+                match agendamento::remarcar_consulta(db, regras, codigo, &nova_data, &nova_hora) {
+                    Ok(remarcada) => {
+                        print_data("Consulta remarcada para", Some(remarcada.data));
+                        println!("Hora: {}", remarcada.hora);
+                    }
+                    Err(e) => println!("\nERRO: {}", e),
+                }
+            }
+            7 => {
+                println!("Buscar por: 1. Especialidade  2. Médico");
+                let busca = match ler_opcao_menu() {
+                    1 => BuscaHorario::Especialidade(ler_u32("Código da Especialidade: ")),
+                    2 => BuscaHorario::Medico(ler_u32("Código do Médico: ")),
+                    _ => {
+                        println!("Opção inválida.");
+                        continue;
+                    }
+                };
+                let hoje = Local::now().date_naive();
+                let texto = ler_string_ou_manter(
+                    "A partir de (DD/MM/AAAA)",
+                    &hoje.format("%d/%m/%Y").to_string(),
+                );
+                let a_partir = match agendamento::validar_data(&texto) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        println!("\nERRO: {}", e);
+                        continue;
+                    }
+                };
+                let quantidade = ler_u32_ou_manter(
+                    "Quantidade de horários",
+                    QUANTIDADE_SUGESTOES as u32,
+                ) as usize;
+
+                match agendamento::proximos_horarios(db, busca, a_partir, quantidade, None) {
+                    Ok(horarios) if horarios.is_empty() => println!(
+                        "Nenhum horário livre nos próximos {} dias.",
+                        agendamento::DIAS_BUSCA
+                    ),
+                    Ok(horarios) => listar_horarios(db, &horarios),
+                    Err(e) => println!("\nERRO: {}", e),
+                }
+            }
+            8 => {
+                let codigo = ler_u32("Digite o código da consulta: ");
+                let Ok(Some(consulta)) = db.consultas.read_record(codigo) else {
+                    println!("Consulta não encontrada.");
+                    continue;
+                };
+                let proximos: Vec<StatusConsulta> = StatusConsulta::TODOS
+                    .into_iter()
+                    .filter(|s| consulta.status.pode_mudar_para(*s))
+                    .collect();
+                println!("Status atual: {}", consulta.status.descricao());
+                if proximos.is_empty() {
+                    println!("Esta consulta está encerrada; o status não pode mais mudar.");
+                    continue;
+                }
+                for (i, status) in proximos.iter().enumerate() {
+                    println!("{}. {}", i + 1, status.descricao());
+                }
+                let escolha = ler_opcao_menu() as usize;
+                let Some(novo) = escolha.checked_sub(1).and_then(|i| proximos.get(i)) else {
+                    println!("Opção inválida.");
+                    continue;
+                };
+                match agendamento::alterar_status(db, codigo, *novo) {
+                    Ok(c) => println!("Status alterado para {}.", c.status.descricao()),
+                    Err(e) => println!("\nERRO: {}", e),
+                }
+            }
+            9 => break,
             _ => println!("Opção inválida."),
         }
     }
 }
 
-pub fn menu_diarias(manager: &mut FileManager<Diaria>) {
+fn exibir_exame(exame: &Exame) {
+    println!("Código: {}", exame.codigo_exame);
+    println!("Descrição: {}", exame.descricao);
+    println!("Especialidade: {}", exame.codigo_especialidade);
+    println!("Valor: R$ {:.2}", exame.valor_exame);
+}
+
+fn exibir_consulta(consulta: &Consulta) {
+    println!("Código: {}", consulta.codigo_consulta);
+    println!("Paciente: {}", consulta.codigo_paciente);
+    println!("Médico: {}", consulta.codigo_medico);
+    print_data("Data Consulta", Some(consulta.data));
+    println!("Hora: {}", consulta.hora);
+    println!("Status: {}", consulta.status.descricao());
+}
+
+/// Lê os códigos dos exames da consulta; com `atuais`, Enter mantém a lista.
+/// `None` se algum código for inválido ou não existir.
+fn ler_exames(db: &Database, atuais: Option<&[u32]>) -> Option<Vec<Exame>> {
+    let texto = match atuais {
+        Some(atuais) => {
+            let lista: Vec<String> = atuais.iter().map(|c| c.to_string()).collect();
+            println!("Digite - para remover todos os exames.");
+            match ler_string(&format!("Códigos dos Exames [{}]: ", lista.join(", "))).as_str() {
+                "" => lista.join(","),
+                "-" => String::new(),
+                texto => texto.to_string(),
+            }
+        }
+        None => ler_string("Códigos dos Exames (separados por vírgula, vazio para nenhum): "),
+    };
+    let Some(codigos) = exames_consulta::interpretar_codigos(&texto) else {
+        println!("\nERRO DE VALIDAÇÃO: Lista de exames inválida ({}).", texto);
+        return None;
+    };
+
+    let mut exames = Vec::new();
+    for codigo_exame in codigos {
+        match db.exames.read_record(codigo_exame).unwrap_or(None) {
+            Some(exame) => exames.push(exame),
+            None => {
+                println!(
+                    "\nERRO DE VALIDAÇÃO: Exame com código {} não encontrado.",
+                    codigo_exame
+                );
+                return None;
+            }
+        }
+    }
+    Some(exames)
+}
+
+fn exibir_exames(exames: &[Exame]) {
+    if exames.is_empty() {
+        println!("Exames: nenhum");
+        return;
+    }
+    println!("Exames:");
+    for exame in exames {
+        println!("  - {} (R$ {:.2})", exame.descricao, exame.valor_exame);
+    }
+}
+
+/// Quantos horários livres são sugeridos quando o horário pedido não pode ser usado.
+const QUANTIDADE_SUGESTOES: usize = 5;
+
+fn listar_horarios(db: &Database, horarios: &[HorarioLivre]) {
+    for (i, horario) in horarios.iter().enumerate() {
+        let nome_medico = db
+            .medicos
+            .read_record(horario.codigo_medico)
+            .unwrap_or(None)
+            .map_or("Não encontrado".to_string(), |m| m.nome);
+        println!(
+            "{}. {} às {} - {} (médico {})",
+            i + 1,
+            horario.data.format("%d/%m/%Y"),
+            horario.hora.format("%H:%M"),
+            nome_medico,
+            horario.codigo_medico
+        );
+    }
+}
+
+/// Lista os próximos horários livres da especialidade e deixa o usuário
+/// escolher um; `None` se ele desistir ou não houver horários.
+fn escolher_horario_livre(
+    db: &Database,
+    codigo_especialidade: u32,
+    a_partir: NaiveDate,
+    codigo_paciente: u32,
+) -> Option<HorarioLivre> {
+    let resposta = ler_string("Deseja ver os próximos horários livres? (s/n): ");
+    if !resposta.eq_ignore_ascii_case("s") {
+        return None;
+    }
+
+    let horarios = match agendamento::proximos_horarios(
+        db,
+        BuscaHorario::Especialidade(codigo_especialidade),
+        a_partir,
+        QUANTIDADE_SUGESTOES,
+        Some(codigo_paciente),
+    ) {
+        Ok(horarios) => horarios,
+        Err(e) => {
+            println!("\nERRO: {}", e);
+            return None;
+        }
+    };
+    if horarios.is_empty() {
+        println!("Nenhum horário livre nos próximos {} dias.", agendamento::DIAS_BUSCA);
+        return None;
+    }
+
+    listar_horarios(db, &horarios);
+    let escolha = ler_u32("Escolha um horário (0 para cancelar): ") as usize;
+    if escolha == 0 || escolha > horarios.len() {
+        return None;
+    }
+    horarios.into_iter().nth(escolha - 1)
+}
+
+pub fn menu_diarias(db: &mut Database) {
     loop {
         println!("\n--- Gerenciar Diárias ---");
-        println!("1. Consultar Diária por código (AAAAMMDD)");
+        println!("1. Consultar Diárias de um dia");
         println!("2. Exibir todas as Diárias");
-        println!("3. Voltar");
+        println!("3. Reconciliar Diárias com as consultas");
+        println!("4. Voltar");
         let choice = ler_opcao_menu();
 
         match choice {
             1 => {
-                let codigo = ler_u32("Digite o código (AAAAMMDD) da Diária: ");
-                if let Ok(Some(diaria)) = manager.read_record(codigo) {
-                    println!("{:?}", diaria);
-                } else {
-                    println!("Diária não encontrada.");
+                let codigo = datas::codigo(ler_data("Digite o dia (DD/MM/AAAA): "));
+                match db.diarias.read_all_records() {
+                    Ok(diarias) => {
+                        let do_dia: Vec<_> =
+                            diarias.iter().filter(|d| d.codigo_dia == codigo).collect();
+                        if do_dia.is_empty() {
+                            println!("Nenhuma diária encontrada para este dia.");
+                        }
+                        for diaria in do_dia {
+                            println!();
+                            exibir_diaria(diaria);
+                        }
+                    }
+                    Err(e) => eprintln!("[ERRO]: Falha ao ler diárias: {}", e),
                 }
             }
             2 => {
-                if let Ok(diarias) = manager.read_all_records() {
+                if let Ok(diarias) = db.diarias.read_all_records() {
                     for diaria in diarias {
-                        println!("{:?}", diaria);
+                        println!();
+                        exibir_diaria(&diaria);
                     }
                 }
             }
-            3 => break,
+            3 => reconciliar_diarias(db),
+            4 => break,
             _ => println!("Opção inválida."),
         }
     }
 }
 
-pub fn atualizar_diaria(
-    diaria_manager: &mut FileManager<Diaria>,
-    codigo_dia: u32,
-    codigo_especialidade: u32,
-    incremento: i32,
-) {
-    if let Ok(Some(mut diaria)) = diaria_manager.read_record(codigo_dia) {
-        diaria.quantidade_consultas = (diaria.quantidade_consultas as i32 + incremento) as u32;
-        if let Err(e) = diaria_manager.create_record(&diaria, codigo_dia) {
-            eprintln!("Erro ao atualizar diária: {}", e);
-        }
-    } else if incremento > 0 {
-        let nova_diaria = Diaria {
-            codigo_dia,
-            codigo_especialidade,
-            quantidade_consultas: incremento as u32,
-        };
-        if let Err(e) = diaria_manager.create_record(&nova_diaria, codigo_dia) {
-            eprintln!("Erro ao criar nova diária: {}", e);
+fn exibir_diaria(diaria: &Diaria) {
+    print_data("Dia", datas::de_codigo(diaria.codigo_dia));
+    println!("Especialidade: {}", diaria.codigo_especialidade);
+    println!("Consultas marcadas: {}", diaria.quantidade_consultas);
+}
+
+/// Lê os dias de atendimento no formato "12345" (1 = segunda ... 7 = domingo).
+fn ler_dias_semana(prompt: &str, atual: Option<u32>) -> Option<u32> {
+    let texto = match atual {
+        Some(mascara) => ler_string_ou_manter(prompt, &descrever_mascara(mascara)),
+        None => ler_string(&format!("{}: ", prompt)),
+    };
+    let mut mascara = 0;
+    for c in texto.chars().filter(|c| !c.is_whitespace()) {
+        match c.to_digit(10) {
+            Some(dia @ 1..=7) => mascara |= 1 << (dia - 1),
+            _ => return None,
+        }
+    }
+    (mascara != 0).then_some(mascara)
+}
+
+fn descrever_mascara(mascara: u32) -> String {
+    (1..=7)
+        .filter(|dia| mascara & (1 << (dia - 1)) != 0)
+        .map(|dia| dia.to_string())
+        .collect()
+}
+
+/// Lê uma hora `HH:MM`; com `opcional`, Enter em branco deixa o campo vazio.
+fn ler_hora(prompt: &str, atual: Option<&str>, opcional: bool) -> Option<String> {
+    let texto = match atual {
+        Some(atual) if !atual.is_empty() => ler_string_ou_manter(prompt, atual),
+        _ => ler_string(&format!("{}: ", prompt)),
+    };
+    if opcional && (texto.is_empty() || texto == "-") {
+        return Some(String::new());
+    }
+    match agendamento::validar_hora(&texto) {
+        Ok(hora) => Some(hora.format("%H:%M").to_string()),
+        Err(e) => {
+            println!("\n[ERRO]: {}", e);
+            None
+        }
+    }
+}
+
+/// Lê os campos da agenda; com `atual`, Enter mantém cada valor. Datas
+/// bloqueadas são preservadas e editadas pelas opções próprias do menu.
+fn ler_agenda(codigo_medico: u32, atual: Option<&AgendaMedico>) -> Option<AgendaMedico> {
+    let Some(dias_semana) = ler_dias_semana(
+        "Dias de atendimento (1=seg ... 7=dom, ex.: 12345)",
+        atual.map(|a| a.dias_semana),
+    ) else {
+        println!("\n[ERRO]: Dias de atendimento inválidos.");
+        return None;
+    };
+    let hora_inicio = ler_hora(
+        "Início do expediente (HH:MM)",
+        atual.map(|a| a.hora_inicio.as_str()),
+        false,
+    )?;
+    let hora_fim = ler_hora(
+        "Fim do expediente (HH:MM)",
+        atual.map(|a| a.hora_fim.as_str()),
+        false,
+    )?;
+    if hora_fim <= hora_inicio {
+        println!("\n[ERRO]: O fim do expediente deve ser depois do início.");
+        return None;
+    }
+
+    if atual.is_some() {
+        println!("Digite - para remover o intervalo.");
+    }
+    let intervalo_inicio = ler_hora(
+        "Início do intervalo (HH:MM, vazio se não houver)",
+        atual.map(|a| a.intervalo_inicio.as_str()),
+        true,
+    )?;
+    let intervalo_fim = if intervalo_inicio.is_empty() {
+        String::new()
+    } else {
+        ler_hora(
+            "Fim do intervalo (HH:MM)",
+            atual.map(|a| a.intervalo_fim.as_str()),
+            false,
+        )?
+    };
+    if !intervalo_inicio.is_empty()
+        && (intervalo_fim <= intervalo_inicio
+            || intervalo_inicio < hora_inicio
+            || intervalo_fim > hora_fim)
+    {
+        println!("\n[ERRO]: O intervalo deve estar dentro do expediente.");
+        return None;
+    }
+
+    Some(AgendaMedico {
+        codigo_medico,
+        dias_semana,
+        hora_inicio,
+        hora_fim,
+        intervalo_inicio,
+        intervalo_fim,
+        datas_bloqueadas: atual.map_or_else(Vec::new, |a| a.datas_bloqueadas.clone()),
+    })
+}
+
+fn exibir_agenda(agenda: &AgendaMedico) {
+    println!("Médico: {}", agenda.codigo_medico);
+    println!("Dias de atendimento: {}", agenda.descricao_dias());
+    println!("Expediente: {} às {}", agenda.hora_inicio, agenda.hora_fim);
+    if agenda.tem_intervalo() {
+        println!("Intervalo: {} às {}", agenda.intervalo_inicio, agenda.intervalo_fim);
+    }
+    if !agenda.datas_bloqueadas.is_empty() {
+        println!("Datas bloqueadas:");
+        for data in &agenda.datas_bloqueadas {
+            print_data("  Data", datas::de_codigo(*data));
+        }
+    }
+}
+
+pub fn menu_agendas(db: &mut Database) {
+    loop {
+        println!("\n--- Agendas dos Médicos ---");
+        println!("1. Definir agenda de um médico");
+        println!("2. Consultar agenda de um médico");
+        println!("3. Excluir agenda de um médico");
+        println!("4. Listar todas as agendas");
+        println!("5. Bloquear data");
+        println!("6. Desbloquear data");
+        println!("7. Horários livres de um médico em uma data");
+        println!("8. Voltar");
+        let choice = ler_opcao_menu();
+
+        match choice {
+            1 => {
+                let codigo_medico = ler_u32("Código do Médico: ");
+                match db.medicos.read_record(codigo_medico) {
+                    Ok(Some(medico)) => println!("Médico selecionado: {}", medico.nome),
+                    Ok(None) => {
+                        println!("\n[ERRO]: Médico com código {} não encontrado.", codigo_medico);
+                        continue;
+                    }
+                    Err(e) => {
+                        eprintln!("[ERRO]: Falha ao consultar arquivo de Médicos: {}", e);
+                        continue;
+                    }
+                }
+                let atual = db.agendas.read_record(codigo_medico).unwrap_or(None);
+                if atual.is_some() {
+                    println!("Pressione Enter para manter o valor atual.");
+                }
+                let Some(agenda) = ler_agenda(codigo_medico, atual.as_ref()) else {
+                    continue;
+                };
+                let resultado = if atual.is_some() {
+                    db.agendas.update_record(&agenda, codigo_medico).map(|_| ())
+                } else {
+                    db.agendas.create_record(&agenda, codigo_medico)
+                };
+                match resultado {
+                    Ok(()) => println!("Agenda gravada com sucesso!"),
+                    Err(e) => eprintln!("Erro ao gravar agenda: {}", e),
+                }
+            }
+            2 => {
+                let codigo_medico = ler_u32("Código do Médico: ");
+                match db.agendas.read_record(codigo_medico) {
+                    Ok(Some(agenda)) => exibir_agenda(&agenda),
+                    _ => println!("Agenda não encontrada."),
+                }
+            }
+            3 => {
+                let codigo_medico = ler_u32("Código do Médico: ");
+                if let Ok(true) = db.agendas.delete_record(codigo_medico) {
+                    println!("Agenda excluída com sucesso!");
+                } else {
+                    println!("Agenda não encontrada.");
+                }
+            }
+            4 => {
+                if let Ok(agendas) = db.agendas.read_all_records() {
+                    for agenda in agendas {
+                        println!();
+                        exibir_agenda(&agenda);
+                    }
+                }
+            }
+            5 | 6 => {
+                let codigo_medico = ler_u32("Código do Médico: ");
+                let Ok(Some(mut agenda)) = db.agendas.read_record(codigo_medico) else {
+                    println!("Agenda não encontrada.");
+                    continue;
+                };
+                let codigo_dia = datas::codigo(ler_data("Data (DD/MM/AAAA): "));
+                if choice == 5 {
+                    if agenda.data_bloqueada(codigo_dia) {
+                        println!("Esta data já está bloqueada.");
+                        continue;
+                    }
+                    agenda.datas_bloqueadas.push(codigo_dia);
+                    agenda.datas_bloqueadas.sort();
+                } else if agenda.data_bloqueada(codigo_dia) {
+                    agenda.datas_bloqueadas.retain(|d| *d != codigo_dia);
+                } else {
+                    println!("Esta data não está bloqueada.");
+                    continue;
+                }
+                match db.agendas.update_record(&agenda, codigo_medico) {
+                    Ok(_) => println!("Agenda atualizada com sucesso!"),
+                    Err(e) => eprintln!("Erro ao atualizar agenda: {}", e),
+                }
+            }
+            7 => {
+                let codigo_medico = ler_u32("Código do Médico: ");
+                let data = ler_data("Data (DD/MM/AAAA): ");
+                match agendamento::horarios_livres(db, codigo_medico, data) {
+                    Ok(horarios) if horarios.is_empty() => {
+                        println!("Nenhum horário livre nesta data.")
+                    }
+                    Ok(horarios) => {
+                        let horarios: Vec<String> =
+                            horarios.iter().map(|h| h.format("%H:%M").to_string()).collect();
+                        println!("Horários livres: {}", horarios.join(", "));
+                    }
+                    Err(e) => println!("\nERRO: {}", e),
+                }
+            }
+            8 => break,
+            _ => println!("Opção inválida."),
+        }
+    }
+}
+
+fn reconciliar_diarias(db: &mut Database) {
+    listar_consultas_com_hora_invalida(db);
+
+    let diferencas = match agendamento::comparar_diarias(db) {
+        Ok(diferencas) => diferencas,
+        Err(e) => {
+            eprintln!("[ERRO]: Falha ao comparar diárias: {}", e);
+            return;
+        }
+    };
+
+    if diferencas.is_empty() {
+        println!("Todas as diárias conferem com as consultas marcadas.");
+        return;
+    }
+
+    println!(
+        "{:<10} {:>13} {:>10} {:>10}",
+        "Dia", "Especialidade", "Gravado", "Correto"
+    );
+    for d in &diferencas {
+        println!(
+            "{:<10} {:>13} {:>10} {:>10}",
+            d.codigo_dia, d.codigo_especialidade, d.armazenado, d.calculado
+        );
+    }
+    println!("{} diária(s) divergente(s).", diferencas.len());
+
+    let confirmacao = ler_string("Regravar diarias.dat com os valores corretos? (s/n): ");
+    if !confirmacao.eq_ignore_ascii_case("s") {
+        println!("Nenhuma alteração feita.");
+        return;
+    }
+    match agendamento::reconstruir_diarias(db) {
+        Ok(total) => println!("Diárias reconstruídas ({} registros).", total),
+        Err(e) => eprintln!("[ERRO]: Falha ao reconstruir diárias: {}", e),
+    }
+}
+
+/// As verificações de conflito ignoram essas consultas, então o horário delas
+/// pode acabar ocupado duas vezes até a hora ser corrigida.
+fn listar_consultas_com_hora_invalida(db: &Database) {
+    match agendamento::consultas_com_hora_invalida(db) {
+        Ok(consultas) if consultas.is_empty() => {}
+        Ok(consultas) => {
+            println!(
+                "ATENÇÃO: {} consulta(s) com hora inválida não entram na verificação de conflitos. Corrija pela opção Alterar Consulta:",
+                consultas.len()
+            );
+            for consulta in &consultas {
+                println!("  {}", descricoes::consulta(consulta));
+            }
+        }
+        Err(e) => eprintln!("[ERRO]: Falha ao ler consultas: {}", e),
+    }
+}
+
+pub fn menu_backup(db: &mut Database) {
+    if !db.persistente() {
+        println!("Backup indisponível no modo em memória.");
+        return;
+    }
+
+    // Garante que tudo que foi escrito está em disco antes de copiar.
+    if let Err(e) = db.sync() {
+        eprintln!("[ERRO]: Falha ao sincronizar arquivos: {}", e);
+        return;
+    }
+
+    loop {
+        println!("\n--- Backup e Restauração ---");
+        println!("1. Criar backup");
+        println!("2. Listar backups");
+        println!("3. Restaurar backup");
+        println!("4. Voltar");
+        let choice = ler_opcao_menu();
+
+        match choice {
+            1 => match backup::criar_backup(db.diretorio(), &db.arquivos()) {
+                Ok(destino) => println!("Backup criado em {}", destino.display()),
+                Err(e) => eprintln!("[ERRO]: Falha ao criar backup: {}", e),
+            },
+            2 => match backup::listar_backups(db.diretorio()) {
+                Ok(backups) if backups.is_empty() => println!("Nenhum backup encontrado."),
+                Ok(backups) => {
+                    println!("--- Backups Disponíveis ---");
+                    for nome in backups {
+                        println!("{}", nome);
+                    }
+                }
+                Err(e) => eprintln!("[ERRO]: Falha ao listar backups: {}", e),
+            },
+            3 => {
+                let nome = ler_string("Nome do backup a restaurar: ");
+                let confirmacao = ler_string(
+                    "Os dados atuais serão substituídos. Confirma a restauração? (s/n): ",
+                );
+                if !confirmacao.eq_ignore_ascii_case("s") {
+                    println!("Restauração cancelada.");
+                    continue;
+                }
+                // Nenhum arquivo pode ficar aberto enquanto é substituído.
+                let arquivos = db.arquivos();
+                if let Err(e) = db.fechar() {
+                    eprintln!("[ERRO]: Falha ao fechar os arquivos: {}", e);
+                    continue;
+                }
+                match backup::restaurar_backup(db.diretorio(), &nome, &arquivos) {
+                    Ok(manifesto) => {
+                        println!(
+                            "Backup de {} restaurado com sucesso ({} arquivos).",
+                            manifesto.criado_em,
+                            manifesto.arquivos.len()
+                        );
+                        // Backups anteriores às migrações não trazem a versão;
+                        // sem ela, os dados restaurados são migrados ao reabrir.
+                        let senha = if crypto::existe_chave(db.diretorio()) {
+                            std::env::var(crypto::VARIAVEL_SENHA)
+                                .unwrap_or_else(|_| ler_senha("Senha do backup restaurado: "))
+                        } else {
+                            String::new()
+                        };
+                        if let Err(e) = db.reabrir(Some(&senha)) {
+                            eprintln!(
+                                "[ERRO]: Falha ao reabrir arquivos restaurados: {}. Reinicie o programa.",
+                                e
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("[ERRO]: Falha ao restaurar backup: {}", e);
+                        if let Err(e) = db.reabrir(None) {
+                            eprintln!("[ERRO]: Falha ao reabrir arquivos: {}. Reinicie o programa.", e);
+                        }
+                    }
+                }
+            }
+            4 => break,
+            _ => println!("Opção inválida."),
+        }
+    }
+}
+
+/// Regrava todos os arquivos cifrados com uma chave nova derivada de `senha`,
+/// com um backup de segurança enquanto isso. Retorna se deu certo.
+pub fn criptografar(db: &mut Database, senha: &str) -> bool {
+    let Some(seguranca) = backup_antes_de_regravar(db) else {
+        return false;
+    };
+    let resultado = crypto::gerar_chave(senha).and_then(|(cipher, arquivo)| {
+        db.rekey(Some(cipher))?;
+        crypto::gravar_chave(db.diretorio(), &arquivo)
+    });
+    match resultado {
+        Ok(()) => {
+            println!("Arquivos recriptografados com a nova senha.");
+            descartar_backup_de_seguranca(db, &seguranca);
+            remover_backups_sem_criptografia(db);
+            true
+        }
+        Err(e) => {
+            eprintln!("[ERRO]: Falha ao trocar a chave: {}", e);
+            false
+        }
+    }
+}
+
+pub fn menu_criptografia(db: &mut Database) {
+    if !db.persistente() {
+        println!("Criptografia indisponível no modo em memória.");
+        return;
+    }
+
+    loop {
+        println!("\n--- Criptografia ---");
+        if crypto::existe_chave(db.diretorio()) {
+            println!("Situação: arquivos protegidos por senha");
+        } else {
+            println!("Situação: arquivos sem criptografia");
+        }
+        println!("1. Definir ou trocar senha (recriptografa todos os arquivos)");
+        println!("2. Remover criptografia");
+        println!("3. Voltar");
+        let choice = ler_opcao_menu();
+
+        match choice {
+            1 => {
+                let Some(senha) = ler_nova_senha() else {
+                    continue;
+                };
+                criptografar(db, &senha);
+            }
+            2 => {
+                if !crypto::existe_chave(db.diretorio()) {
+                    println!("Os arquivos já estão sem criptografia.");
+                    continue;
+                }
+                let confirmacao =
+                    ler_string("Os dados ficarão legíveis em disco. Confirma? (s/n): ");
+                if !confirmacao.eq_ignore_ascii_case("s") {
+                    continue;
+                }
+                let Some(seguranca) = backup_antes_de_regravar(db) else {
+                    continue;
+                };
+                match db.rekey(None).and_then(|_| crypto::remover_chave(db.diretorio())) {
+                    Ok(()) => {
+                        println!("Criptografia removida.");
+                        descartar_backup_de_seguranca(db, &seguranca);
+                    }
+                    Err(e) => eprintln!("[ERRO]: Falha ao remover criptografia: {}", e),
+                }
+            }
+            3 => break,
+            _ => println!("Opção inválida."),
+        }
+    }
+}
+
+pub fn menu_prontuarios(db: &mut Database) {
+    loop {
+        println!("\n--- Prontuários ---");
+        println!("1. Registrar anotação de uma consulta");
+        println!("2. Consultar anotação por código");
+        println!("3. Alterar anotação");
+        println!("4. Anotações de uma consulta");
+        println!("5. Linha do tempo do paciente");
+        println!("6. Voltar ao menu principal");
+        let choice = ler_opcao_menu();
+
+        match choice {
+            1 => {
+                let codigo_prontuario = ler_u32("Código da anotação: ");
+                let codigo_consulta = ler_u32("Código da consulta: ");
+                let anamnese = ler_string("Anamnese: ");
+                let diagnosticos = ler_diagnosticos(&db.cids, None);
+                let prescricoes = ler_prescricoes(None);
+                let observacoes = ler_string("Observações: ");
+                let prontuario = Prontuario {
+                    codigo_prontuario,
+                    codigo_consulta,
+                    anamnese,
+                    diagnosticos,
+                    prescricoes,
+                    observacoes,
+                    ..Default::default()
+                };
+                match prontuarios::registrar(db, prontuario) {
+                    Ok(p) => println!("Anotação registrada para o paciente {}.", p.codigo_paciente),
+                    Err(e) => println!("\n[ERRO]: {}", e),
+                }
+            }
+            2 => {
+                let codigo = ler_u32("Digite o código da anotação: ");
+                match db.prontuarios.read_record(codigo) {
+                    Ok(Some(prontuario)) => exibir_prontuario(&prontuario),
+                    _ => println!("Anotação não encontrada."),
+                }
+            }
+            3 => {
+                let codigo = ler_u32("Digite o código da anotação para alteração: ");
+                let Ok(Some(atual)) = db.prontuarios.read_record(codigo) else {
+                    println!("Anotação não encontrada.");
+                    continue;
+                };
+                println!("Pressione Enter para manter o valor atual.");
+                let prontuario = Prontuario {
+                    anamnese: ler_string_ou_manter("Anamnese", &atual.anamnese),
+                    diagnosticos: ler_diagnosticos(&db.cids, Some(&atual.diagnosticos)),
+                    prescricoes: ler_prescricoes(Some(&atual.prescricoes)),
+                    observacoes: ler_string_ou_manter("Observações", &atual.observacoes),
+                    ..atual
+                };
+                match prontuarios::alterar(db, prontuario) {
+                    Ok(_) => println!("Anotação alterada com sucesso!"),
+                    Err(e) => println!("\n[ERRO]: {}", e),
+                }
+            }
+            4 => {
+                let codigo_consulta = ler_u32("Digite o código da consulta: ");
+                match prontuarios::prontuarios_da_consulta(db, codigo_consulta) {
+                    Ok(lista) if lista.is_empty() => println!("Nenhuma anotação para esta consulta."),
+                    Ok(lista) => lista.iter().for_each(exibir_prontuario),
+                    Err(e) => println!("\nERRO: {}", e),
+                }
+            }
+            5 => {
+                let codigo_paciente = ler_u32("Digite o código do paciente: ");
+                let Ok(Some(paciente)) = db.pacientes.read_record(codigo_paciente) else {
+                    println!("Paciente não encontrado.");
+                    continue;
+                };
+                match prontuarios::linha_do_tempo(db, codigo_paciente) {
+                    Ok(eventos) => exibir_linha_do_tempo(db, &paciente, &eventos),
+                    Err(e) => println!("\nERRO: {}", e),
+                }
+            }
+            6 => break,
+            _ => println!("Opção inválida."),
+        }
+    }
+}
+
+/// Lê os códigos CID-10 separados por vírgula; repete a pergunta até todos
+/// serem válidos e estarem no catálogo. Com `atuais`, Enter mantém a lista e "-" a esvazia.
+fn ler_diagnosticos(catalogo: &FileManager<Cid>, atuais: Option<&[String]>) -> Vec<String> {
+    loop {
+        let texto = match atuais {
+            Some(atuais) => {
+                let texto = ler_string(&format!(
+                    "Diagnósticos CID-10 separados por vírgula [{}] (\"-\" remove todos): ",
+                    atuais.join(", ")
+                ));
+                if texto.is_empty() {
+                    return atuais.to_vec();
+                }
+                if texto == "-" {
+                    return Vec::new();
+                }
+                texto
+            }
+            None => ler_string("Diagnósticos CID-10 separados por vírgula (Enter se nenhum): "),
+        };
+        let codigos: Vec<String> = texto
+            .split(',')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(str::to_string)
+            .collect();
+        match cid10::validar(catalogo, &codigos) {
+            Ok(diagnosticos) => return diagnosticos,
+            Err(e) => println!("\nERRO DE VALIDAÇÃO: {}", e),
+        }
+    }
+}
+
+/// Uma prescrição por linha, até uma linha em branco. Com `atuais`, Enter na
+/// primeira linha mantém as prescrições gravadas.
+fn ler_prescricoes(atuais: Option<&[String]>) -> Vec<String> {
+    if let Some(atuais) = atuais {
+        println!("Prescrições atuais: {}", if atuais.is_empty() { "Nenhuma".to_string() } else { atuais.join("; ") });
+    }
+    let mut prescricoes = Vec::new();
+    loop {
+        let item = ler_string("Prescrição (Enter para terminar): ");
+        if item.is_empty() {
+            break;
+        }
+        prescricoes.push(item);
+    }
+    match atuais {
+        Some(atuais) if prescricoes.is_empty() => atuais.to_vec(),
+        _ => prescricoes,
+    }
+}
+
+fn exibir_prontuario(prontuario: &Prontuario) {
+    println!("\n--- Anotação {} ---", prontuario.codigo_prontuario);
+    println!("Consulta: {}", prontuario.codigo_consulta);
+    println!("Paciente: {}", prontuario.codigo_paciente);
+    exibir_conteudo_prontuario(prontuario, "");
+    println!("Criada em: {}", prontuario.criado_em);
+    if prontuario.alterado_em != prontuario.criado_em {
+        println!("Alterada em: {}", prontuario.alterado_em);
+    }
+}
+
+fn exibir_conteudo_prontuario(prontuario: &Prontuario, recuo: &str) {
+    let ou_nenhum = |lista: &[String], separador: &str| {
+        if lista.is_empty() { "Nenhum".to_string() } else { lista.join(separador) }
+    };
+    println!("{}Anamnese: {}", recuo, prontuario.anamnese);
+    println!("{}Diagnósticos: {}", recuo, ou_nenhum(&prontuario.diagnosticos, ", "));
+    println!("{}Prescrições: {}", recuo, ou_nenhum(&prontuario.prescricoes, "; "));
+    println!("{}Observações: {}", recuo, prontuario.observacoes);
+}
+
+fn exibir_linha_do_tempo(db: &Database, paciente: &Paciente, eventos: &[EventoHistorico]) {
+    println!("\n--- Linha do Tempo de {} ---", paciente.nome);
+    if eventos.is_empty() {
+        println!("Nenhuma consulta ou anotação registrada.");
+        return;
+    }
+    for evento in eventos {
+        let momento = evento.momento.format("%d/%m/%Y %H:%M");
+        match &evento.evento {
+            Evento::Consulta(consulta) => {
+                let medico = db
+                    .medicos
+                    .read_record(consulta.codigo_medico)
+                    .unwrap_or(None)
+                    .map_or("Médico não encontrado".to_string(), |m| m.nome);
+                println!(
+                    "[{}] Consulta {} com {} ({})",
+                    momento,
+                    consulta.codigo_consulta,
+                    medico,
+                    consulta.status.descricao()
+                );
+            }
+            Evento::Anotacao(prontuario) => {
+                println!(
+                    "[{}] Anotação {} da consulta {}",
+                    momento, prontuario.codigo_prontuario, prontuario.codigo_consulta
+                );
+                exibir_conteudo_prontuario(prontuario, "    ");
+            }
+        }
+    }
+}
+
+pub fn menu_cid10(db: &mut Database) {
+    loop {
+        println!("\n--- CID-10 e Diagnósticos ---");
+        println!("1. Buscar diagnóstico por código");
+        println!("2. Buscar diagnóstico por descrição");
+        println!("3. Diagnósticos de uma consulta");
+        println!("4. Definir diagnósticos de uma consulta");
+        println!("5. Diagnósticos mais frequentes por especialidade e período");
+        println!("6. Importar catálogo CID-10 de arquivo CSV");
+        println!("7. Voltar ao menu principal");
+        let choice = ler_opcao_menu();
+
+        match choice {
+            1 => {
+                let codigo = ler_string("Código CID-10 (ex.: J11.1): ");
+                match cid10::buscar_por_codigo(&db.cids, &codigo) {
+                    Ok(Some(cid)) => println!("{} - {}", cid.codigo, cid.descricao),
+                    Ok(None) => println!("Código não encontrado no catálogo."),
+                    Err(e) => println!("\nERRO: {}", e),
+                }
+            }
+            2 => buscar_por_texto(&db.cids, "Descrição (ou parte dela): ", descricoes::cid),
+            3 => {
+                let codigo_consulta = ler_u32("Digite o código da consulta: ");
+                match cid10::diagnosticos_da_consulta(db, codigo_consulta) {
+                    Ok(diagnosticos) => exibir_diagnosticos(&diagnosticos),
+                    Err(e) => println!("\nERRO: {}", e),
+                }
+            }
+            4 => {
+                let codigo_consulta = ler_u32("Digite o código da consulta: ");
+                let atuais = cid10::codigos_da_consulta(db, codigo_consulta).unwrap_or_default();
+                let codigos = ler_diagnosticos(&db.cids, Some(&atuais));
+                match cid10::definir_diagnosticos(db, codigo_consulta, &codigos) {
+                    Ok(_) => println!("Diagnósticos da consulta atualizados."),
+                    Err(e) => println!("\n[ERRO]: {}", e),
+                }
+            }
+            5 => relatorios::relatorio_diagnosticos_frequentes(db),
+            6 => {
+                let caminho = ler_string("Caminho do arquivo CSV: ");
+                match cid10::importar_arquivo(&mut db.cids, Path::new(&caminho)) {
+                    Ok(quantidade) => println!("{} código(s) importado(s).", quantidade),
+                    Err(e) => println!("\n[ERRO]: Falha ao importar catálogo: {}", e),
+                }
+            }
+            7 => break,
+            _ => println!("Opção inválida."),
+        }
+    }
+}
+
+fn exibir_diagnosticos(diagnosticos: &[Cid]) {
+    if diagnosticos.is_empty() {
+        println!("Diagnósticos: Nenhum");
+        return;
+    }
+    println!("Diagnósticos:");
+    for cid in diagnosticos {
+        println!("  {} - {}", cid.codigo, cid.descricao);
+    }
+}
+
+pub fn menu_resultados_exames(db: &mut Database) {
+    loop {
+        println!("\n--- Pedidos e Resultados de Exames ---");
+        println!("1. Pedidos de exame de uma consulta");
+        println!("2. Registrar coleta");
+        println!("3. Registrar resultado");
+        println!("4. Revisar resultado");
+        println!("5. Exames pendentes de um paciente");
+        println!("6. Voltar ao menu principal");
+        let choice = ler_opcao_menu();
+
+        match choice {
+            1 => {
+                let codigo_consulta = ler_u32("Digite o código da consulta: ");
+                match exames_consulta::pedidos_da_consulta(db, codigo_consulta) {
+                    Ok(pedidos) if pedidos.is_empty() => println!("Nenhum exame pedido nesta consulta."),
+                    Ok(pedidos) => pedidos.iter().for_each(|p| exibir_pedido_exame(db, p)),
+                    Err(e) => println!("\nERRO: {}", e),
+                }
+            }
+            2 => {
+                let codigo_consulta = ler_u32("Código da consulta: ");
+                let codigo_exame = ler_u32("Código do exame: ");
+                match exames_consulta::registrar_coleta(db, codigo_consulta, codigo_exame) {
+                    Ok(_) => println!("Coleta registrada."),
+                    Err(e) => println!("\n[ERRO]: {}", e),
+                }
+            }
+            3 => {
+                let codigo_consulta = ler_u32("Código da consulta: ");
+                let codigo_exame = ler_u32("Código do exame: ");
+                let Ok(Some(pedido)) = db.consulta_exames.read_record((codigo_consulta, codigo_exame)) else {
+                    println!("Pedido de exame não encontrado.");
+                    continue;
+                };
+                if !pedido.resultados.is_empty() {
+                    println!("Os valores informados substituem o resultado anterior.");
+                }
+                let resultados = ler_resultados();
+                match exames_consulta::registrar_resultados(db, codigo_consulta, codigo_exame, resultados) {
+                    Ok(pedido) => {
+                        println!("Resultado registrado.");
+                        exibir_pedido_exame(db, &pedido);
+                    }
+                    Err(e) => println!("\n[ERRO]: {}", e),
+                }
+            }
+            4 => {
+                let codigo_consulta = ler_u32("Código da consulta: ");
+                let codigo_exame = ler_u32("Código do exame: ");
+                match exames_consulta::revisar(db, codigo_consulta, codigo_exame) {
+                    Ok(_) => println!("Resultado revisado."),
+                    Err(e) => println!("\n[ERRO]: {}", e),
+                }
+            }
+            5 => {
+                let codigo_paciente = ler_u32("Digite o código do paciente: ");
+                let Ok(Some(paciente)) = db.pacientes.read_record(codigo_paciente) else {
+                    println!("Paciente não encontrado.");
+                    continue;
+                };
+                match exames_consulta::pendentes_do_paciente(db, codigo_paciente) {
+                    Ok(pendentes) if pendentes.is_empty() => {
+                        println!("Nenhum exame pendente para {}.", paciente.nome)
+                    }
+                    Ok(pendentes) => {
+                        println!("Exames pendentes de {}:", paciente.nome);
+                        pendentes.iter().for_each(|p| exibir_pedido_exame(db, p));
+                    }
+                    Err(e) => println!("\nERRO: {}", e),
+                }
+            }
+            6 => break,
+            _ => println!("Opção inválida."),
+        }
+    }
+}
+
+/// Lê os itens do laudo até uma linha em branco no nome. A faixa de
+/// referência é opcional e pode ter só um dos limites.
+fn ler_resultados() -> Vec<ResultadoExame> {
+    println!("Informe os itens do resultado (Enter no nome para terminar).");
+    let mut resultados = Vec::new();
+    loop {
+        let analito = ler_string("Item (ex.: Hemoglobina): ");
+        if analito.is_empty() {
+            return resultados;
+        }
+        let valor = ler_string("Valor: ");
+        let unidade = ler_string("Unidade: ");
+        let referencia_minima = ler_referencia("Referência mínima (Enter se não houver): ");
+        let referencia_maxima = ler_referencia("Referência máxima (Enter se não houver): ");
+        resultados.push(ResultadoExame {
+            analito,
+            valor,
+            unidade,
+            referencia_minima,
+            referencia_maxima,
+        });
+    }
+}
+
+fn ler_referencia(prompt: &str) -> Option<f32> {
+    loop {
+        let input = ler_string(prompt);
+        if input.is_empty() {
+            return None;
+        }
+        match input.replace(',', ".").parse() {
+            Ok(num) => return Some(num),
+            Err(_) => println!("Entrada inválida. Por favor, digite um número decimal."),
+        }
+    }
+}
+
+fn exibir_pedido_exame(db: &Database, pedido: &ConsultaExame) {
+    let descricao = db
+        .exames
+        .read_record(pedido.codigo_exame)
+        .unwrap_or(None)
+        .map_or("Exame não encontrado".to_string(), |e| e.descricao);
+    println!(
+        "\nConsulta {} - Exame {} ({}): {}",
+        pedido.codigo_consulta,
+        pedido.codigo_exame,
+        descricao,
+        pedido.status.descricao()
+    );
+    for mudanca in &pedido.historico_status {
+        println!("  {} em {}", mudanca.status.descricao(), mudanca.em);
+    }
+    for resultado in &pedido.resultados {
+        let faixa = match (resultado.referencia_minima, resultado.referencia_maxima) {
+            (Some(minimo), Some(maximo)) => format!(" (ref. {} a {})", minimo, maximo),
+            (Some(minimo), None) => format!(" (ref. mín. {})", minimo),
+            (None, Some(maximo)) => format!(" (ref. máx. {})", maximo),
+            (None, None) => String::new(),
+        };
+        let sinal = resultado
+            .sinalizacao()
+            .map_or(String::new(), |s| format!(" <- {}", s.descricao()));
+        println!(
+            "  {}: {} {}{}{}",
+            resultado.analito, resultado.valor, resultado.unidade, faixa, sinal
+        );
+    }
+}
+
+/// Consultas livres na linguagem de `db::query`; linha em branco volta ao menu principal.
+pub fn menu_pesquisa(db: &Database) {
+    println!("\n--- Pesquisar Registros ---");
+    println!("Exemplo: {}", pesquisa::EXEMPLO);
+    println!("Operadores: = != < <= > >= between .. and .. contains; combine com and/or.");
+    println!("Datas como AAAAMMDD ou DD/MM/AAAA. Opcional: order by <campo> [desc], limit <n>.");
+    for (colecao, campos) in pesquisa::COLECOES {
+        println!("  {}: {}", colecao, campos.join(", "));
+    }
+    loop {
+        let texto = ler_string("\nConsulta (Enter para voltar): ");
+        if texto.is_empty() {
+            break;
+        }
+        match pesquisa::executar(db, &texto) {
+            Ok(linhas) if linhas.is_empty() => println!("Nenhum registro encontrado."),
+            Ok(linhas) => {
+                for linha in &linhas {
+                    println!("{}", linha);
+                }
+                println!("{} registro(s).", linhas.len());
+            }
+            Err(e) => println!("\n[ERRO]: {}", e),
+        }
+    }
+}
+
+/// Regravar todos os arquivos não é atômico; um backup garante que dá para voltar atrás.
+/// Devolve o nome do backup, para ser descartado quando a regravação der certo.
+fn backup_antes_de_regravar(db: &Database) -> Option<String> {
+    let resultado = db
+        .sync()
+        .and_then(|_| backup::criar_backup(db.diretorio(), &db.arquivos()));
+    match resultado {
+        Ok(destino) => {
+            println!("Backup de segurança criado em {}", destino.display());
+            destino.file_name().map(|nome| nome.to_string_lossy().into_owned())
+        }
+        Err(e) => {
+            eprintln!("[ERRO]: Falha ao criar backup de segurança: {}", e);
+            None
+        }
+    }
+}
+
+/// O backup de segurança tem os dados como estavam antes da troca de chave
+/// (talvez em texto puro); depois que ela dá certo, ele não deve ficar em disco.
+fn descartar_backup_de_seguranca(db: &Database, nome: &str) {
+    match backup::remover_backup(db.diretorio(), nome) {
+        Ok(()) => println!("Backup de segurança removido."),
+        Err(e) => eprintln!(
+            "[ERRO]: Falha ao remover o backup de segurança {}: {}. Apague-o manualmente.",
+            nome, e
+        ),
+    }
+}
+
+/// Backups feitos antes da criptografia guardam os dados legíveis; oferece apagá-los.
+fn remover_backups_sem_criptografia(db: &Database) {
+    let sem_chave: Vec<String> = backup::listar_backups(db.diretorio())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|nome| {
+            !backup::contem_arquivo(db.diretorio(), nome, crypto::ARQUIVO_CHAVE).unwrap_or(true)
+        })
+        .collect();
+    if sem_chave.is_empty() {
+        return;
+    }
+    println!("\nATENÇÃO: estes backups não são criptografados e guardam os dados legíveis:");
+    for nome in &sem_chave {
+        println!("  {}", nome);
+    }
+    let confirmacao = ler_string("Apagar esses backups? (s/n): ");
+    if !confirmacao.eq_ignore_ascii_case("s") {
+        println!("Backups mantidos.");
+        return;
+    }
+    for nome in &sem_chave {
+        if let Err(e) = backup::remover_backup(db.diretorio(), nome) {
+            eprintln!("[ERRO]: Falha ao apagar o backup {}: {}", nome, e);
         }
     }
 }
@@ -0,0 +1,115 @@
+use crate::db::file_manager::Entity;
+use chrono::{Datelike, NaiveDate};
+use std::io::{self};
+use std::mem::size_of;
+
+const NOMES_DIAS: [&str; 7] = ["seg", "ter", "qua", "qui", "sex", "sáb", "dom"];
+
+/// Horário de atendimento de um médico. As horas ficam no formato `HH:MM`,
+/// como em `Consulta::hora`; o intervalo fica vazio quando não há pausa.
+#[derive(Debug, Default)]
+pub struct AgendaMedico {
+    pub codigo_medico: u32,
+    /// Um bit por dia da semana: bit 0 = segunda-feira, ..., bit 6 = domingo.
+    pub dias_semana: u32,
+    pub hora_inicio: String,
+    pub hora_fim: String,
+    pub intervalo_inicio: String,
+    pub intervalo_fim: String,
+    /// Férias, folgas e outros dias sem atendimento, no formato AAAAMMDD.
+    pub datas_bloqueadas: Vec<u32>,
+}
+
+impl AgendaMedico {
+    pub fn atende_no_dia(&self, data: NaiveDate) -> bool {
+        self.dias_semana & (1 << data.weekday().num_days_from_monday()) != 0
+    }
+
+    pub fn data_bloqueada(&self, codigo_dia: u32) -> bool {
+        self.datas_bloqueadas.contains(&codigo_dia)
+    }
+
+    pub fn tem_intervalo(&self) -> bool {
+        !self.intervalo_inicio.is_empty() && !self.intervalo_fim.is_empty()
+    }
+
+    /// Ex.: "seg ter qua qui sex".
+    pub fn descricao_dias(&self) -> String {
+        NOMES_DIAS
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.dias_semana & (1 << i) != 0)
+            .map(|(_, nome)| *nome)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Entity for AgendaMedico {
+    type Key = u32;
+
+    fn get_key(&self) -> u32 {
+        self.codigo_medico
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.codigo_medico.to_le_bytes());
+        bytes.extend_from_slice(&self.dias_semana.to_le_bytes());
+
+        for hora in [
+            &self.hora_inicio,
+            &self.hora_fim,
+            &self.intervalo_inicio,
+            &self.intervalo_fim,
+        ] {
+            bytes.extend_from_slice(&(hora.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(hora.as_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.datas_bloqueadas.len() as u32).to_le_bytes());
+        for data in &self.datas_bloqueadas {
+            bytes.extend_from_slice(&data.to_le_bytes());
+        }
+
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, io::Error> {
+        let mut cursor = 0;
+        let read_u32 = |c: &mut usize| -> u32 {
+            let val = u32::from_le_bytes([bytes[*c], bytes[*c + 1], bytes[*c + 2], bytes[*c + 3]]);
+            *c += size_of::<u32>();
+            val
+        };
+        let read_string = |c: &mut usize| -> Result<String, io::Error> {
+            let len = read_u32(c) as usize;
+            let start = *c;
+            let end = start + len;
+            let s = String::from_utf8(bytes[start..end].to_vec())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            *c = end;
+            Ok(s)
+        };
+
+        let codigo_medico = read_u32(&mut cursor);
+        let dias_semana = read_u32(&mut cursor);
+        let hora_inicio = read_string(&mut cursor)?;
+        let hora_fim = read_string(&mut cursor)?;
+        let intervalo_inicio = read_string(&mut cursor)?;
+        let intervalo_fim = read_string(&mut cursor)?;
+
+        let quantidade = read_u32(&mut cursor);
+        let datas_bloqueadas = (0..quantidade).map(|_| read_u32(&mut cursor)).collect();
+
+        Ok(AgendaMedico {
+            codigo_medico,
+            dias_semana,
+            hora_inicio,
+            hora_fim,
+            intervalo_inicio,
+            intervalo_fim,
+            datas_bloqueadas,
+        })
+    }
+}
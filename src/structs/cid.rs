@@ -0,0 +1,70 @@
+use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
+use crate::utils::cid10;
+use std::io::{self};
+use std::mem::size_of;
+
+/// Categoria ("J11") ou subcategoria ("J11.1") do catálogo CID-10. A chave é o
+/// código convertido em número (ver `cid10::chave`).
+#[derive(Debug, Default, Clone)]
+pub struct Cid {
+    pub codigo: String,
+    pub descricao: String,
+}
+
+impl Entity for Cid {
+    type Key = u32;
+
+    fn get_key(&self) -> u32 {
+        cid10::chave(&self.codigo)
+    }
+
+    fn search_text(&self) -> Option<String> {
+        Some(self.descricao.clone())
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(self.codigo.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.codigo.as_bytes());
+        bytes.extend_from_slice(&(self.descricao.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.descricao.as_bytes());
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, io::Error> {
+        let mut cursor = 0;
+        let read_u32 = |c: &mut usize| -> u32 {
+            let val = u32::from_le_bytes([bytes[*c], bytes[*c + 1], bytes[*c + 2], bytes[*c + 3]]);
+            *c += size_of::<u32>();
+            val
+        };
+
+        let read_string = |c: &mut usize| -> Result<String, io::Error> {
+            let len = read_u32(c) as usize;
+            let start = *c;
+            let end = start + len;
+            let s = String::from_utf8(bytes[start..end].to_vec())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            *c = end;
+            Ok(s)
+        };
+
+        let codigo = read_string(&mut cursor)?;
+        let descricao = read_string(&mut cursor)?;
+
+        Ok(Cid { codigo, descricao })
+    }
+}
+
+impl Queryable for Cid {
+    const FIELDS: &'static [&'static str] = &["codigo", "descricao"];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo" => self.codigo.as_str().into(),
+            "descricao" => self.descricao.as_str().into(),
+            _ => Value::Null,
+        }
+    }
+}
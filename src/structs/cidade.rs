@@ -1,4 +1,5 @@
 use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
 use std::{io, mem::size_of};
 
 #[derive(Debug, Default)]
@@ -9,10 +10,16 @@ pub struct Cidade {
 }
 
 impl Entity for Cidade {
+    type Key = u32;
+
     fn get_key(&self) -> u32 {
         self.codigo_cidade
     }
 
+    fn search_text(&self) -> Option<String> {
+        Some(self.descricao.clone())
+    }
+
     fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
         let mut bytes = Vec::new();
         bytes.extend_from_slice(&self.codigo_cidade.to_le_bytes());
@@ -50,4 +57,21 @@ impl Entity for Cidade {
         
         Ok(Cidade { codigo_cidade, descricao, estado })
     }
+}
+
+impl Queryable for Cidade {
+    const FIELDS: &'static [&'static str] = &[
+        "codigo_cidade",
+        "descricao",
+        "estado",
+    ];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo_cidade" => self.codigo_cidade.into(),
+            "descricao" => self.descricao.as_str().into(),
+            "estado" => self.estado.as_str().into(),
+            _ => Value::Null,
+        }
+    }
 }
\ No newline at end of file
@@ -1,18 +1,112 @@
 use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
+use crate::utils::datas;
+use chrono::{Local, NaiveDate};
+use serde::Deserialize;
 use std::io::{self};
 use std::mem::size_of;
 
-#[derive(Debug, Default)]
+/// Situação da consulta. Realizada, Faltou e Cancelada são estados finais.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusConsulta {
+    #[default]
+    Agendada,
+    Confirmada,
+    Realizada,
+    Faltou,
+    Cancelada,
+}
+
+impl StatusConsulta {
+    pub const TODOS: [StatusConsulta; 5] = [
+        StatusConsulta::Agendada,
+        StatusConsulta::Confirmada,
+        StatusConsulta::Realizada,
+        StatusConsulta::Faltou,
+        StatusConsulta::Cancelada,
+    ];
+
+    fn codigo(self) -> u8 {
+        self as u8
+    }
+
+    fn from_codigo(codigo: u8) -> Result<StatusConsulta, io::Error> {
+        StatusConsulta::TODOS
+            .get(codigo as usize)
+            .copied()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("status de consulta desconhecido: {}", codigo),
+                )
+            })
+    }
+
+    pub fn descricao(self) -> &'static str {
+        match self {
+            StatusConsulta::Agendada => "Agendada",
+            StatusConsulta::Confirmada => "Confirmada",
+            StatusConsulta::Realizada => "Realizada",
+            StatusConsulta::Faltou => "Faltou",
+            StatusConsulta::Cancelada => "Cancelada",
+        }
+    }
+
+    pub fn pode_mudar_para(self, novo: StatusConsulta) -> bool {
+        use StatusConsulta::*;
+        matches!(
+            (self, novo),
+            (Agendada, Confirmada | Realizada | Faltou | Cancelada)
+                | (Confirmada, Realizada | Faltou | Cancelada)
+        )
+    }
+
+    /// A consulta ainda pode ser alterada ou remarcada.
+    pub fn em_aberto(self) -> bool {
+        matches!(self, StatusConsulta::Agendada | StatusConsulta::Confirmada)
+    }
+
+    /// Consultas canceladas liberam o horário e a vaga da diária; as demais,
+    /// inclusive faltas, continuam ocupando.
+    pub fn ocupa_horario(self) -> bool {
+        self != StatusConsulta::Cancelada
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MudancaStatus {
+    pub status: StatusConsulta,
+    pub em: String, //AAAA-MM-DD HH:MM:SS
+}
+
+impl MudancaStatus {
+    pub fn agora(status: StatusConsulta) -> MudancaStatus {
+        MudancaStatus {
+            status,
+            em: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct Consulta {
     pub codigo_consulta: u32,
     pub codigo_paciente: u32,
     pub codigo_medico: u32,
+    /// Exame único das consultas antigas. Os exames agora ficam em
+    /// `consulta_exames.dat`; a migração move este valor para lá e o zera.
     pub codigo_exame: u32,
-    pub data: String, //AAAAMMDD
+    pub data: NaiveDate,
     pub hora: String, //HH:MM
+    pub status: StatusConsulta,
+    /// Cada mudança de status com o momento em que aconteceu, da mais antiga à mais recente.
+    pub historico_status: Vec<MudancaStatus>,
 }
 
 impl Entity for Consulta {
+    type Key = u32;
+
     fn get_key(&self) -> u32 {
         self.codigo_consulta
     }
@@ -24,12 +118,19 @@ impl Entity for Consulta {
         bytes.extend_from_slice(&self.codigo_medico.to_le_bytes());
         bytes.extend_from_slice(&self.codigo_exame.to_le_bytes());
 
-        bytes.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
-        bytes.extend_from_slice(self.data.as_bytes());
+        datas::gravar(&mut bytes, Some(self.data));
 
         bytes.extend_from_slice(&(self.hora.len() as u32).to_le_bytes());
         bytes.extend_from_slice(self.hora.as_bytes());
 
+        bytes.push(self.status.codigo());
+        bytes.extend_from_slice(&(self.historico_status.len() as u32).to_le_bytes());
+        for mudanca in &self.historico_status {
+            bytes.push(mudanca.status.codigo());
+            bytes.extend_from_slice(&(mudanca.em.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(mudanca.em.as_bytes());
+        }
+
         Ok(bytes)
     }
 
@@ -55,9 +156,30 @@ impl Entity for Consulta {
         let codigo_paciente = read_u32(&mut cursor);
         let codigo_medico = read_u32(&mut cursor);
         let codigo_exame = read_u32(&mut cursor);
-        let data = read_string(&mut cursor)?;
+        // A data sempre foi validada na marcação; se não for legível, o arquivo está corrompido.
+        let data = datas::ler(bytes, &mut cursor)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("consulta {}: data ilegível", codigo_consulta),
+            )
+        })?;
         let hora = read_string(&mut cursor)?;
 
+        // Registros gravados antes do status terminam na hora e contam como agendados.
+        let mut status = StatusConsulta::Agendada;
+        let mut historico_status = Vec::new();
+        if cursor < bytes.len() {
+            status = StatusConsulta::from_codigo(bytes[cursor])?;
+            cursor += 1;
+            let quantidade = read_u32(&mut cursor);
+            for _ in 0..quantidade {
+                let status = StatusConsulta::from_codigo(bytes[cursor])?;
+                cursor += 1;
+                let em = read_string(&mut cursor)?;
+                historico_status.push(MudancaStatus { status, em });
+            }
+        }
+
         Ok(Consulta {
             codigo_consulta,
             codigo_paciente,
@@ -65,6 +187,31 @@ impl Entity for Consulta {
             codigo_exame,
             data,
             hora,
+            status,
+            historico_status,
         })
     }
 }
+
+impl Queryable for Consulta {
+    const FIELDS: &'static [&'static str] = &[
+        "codigo_consulta",
+        "codigo_paciente",
+        "codigo_medico",
+        "data",
+        "hora",
+        "status",
+    ];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo_consulta" => self.codigo_consulta.into(),
+            "codigo_paciente" => self.codigo_paciente.into(),
+            "codigo_medico" => self.codigo_medico.into(),
+            "data" => self.data.into(),
+            "hora" => self.hora.as_str().into(),
+            "status" => self.status.descricao().into(),
+            _ => Value::Null,
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
+use crate::utils::cid10;
+use std::io::{self};
+use std::mem::size_of;
+
+/// Diagnóstico (código CID-10) atribuído a uma consulta. Uma consulta pode ter
+/// vários; a chave é o par (consulta, chave do código no catálogo).
+#[derive(Debug, Default)]
+pub struct ConsultaDiagnostico {
+    pub codigo_consulta: u32,
+    pub codigo_cid: String,
+}
+
+impl Entity for ConsultaDiagnostico {
+    type Key = (u32, u32);
+
+    fn get_key(&self) -> (u32, u32) {
+        (self.codigo_consulta, cid10::chave(&self.codigo_cid))
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.codigo_consulta.to_le_bytes());
+        bytes.extend_from_slice(&(self.codigo_cid.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.codigo_cid.as_bytes());
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, io::Error> {
+        let mut cursor = 0;
+        let read_u32 = |c: &mut usize| -> u32 {
+            let val = u32::from_le_bytes([bytes[*c], bytes[*c + 1], bytes[*c + 2], bytes[*c + 3]]);
+            *c += size_of::<u32>();
+            val
+        };
+
+        let codigo_consulta = read_u32(&mut cursor);
+        let len = read_u32(&mut cursor) as usize;
+        let codigo_cid = String::from_utf8(bytes[cursor..cursor + len].to_vec())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(ConsultaDiagnostico { codigo_consulta, codigo_cid })
+    }
+}
+
+impl Queryable for ConsultaDiagnostico {
+    const FIELDS: &'static [&'static str] = &["codigo_consulta", "codigo_cid"];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo_consulta" => self.codigo_consulta.into(),
+            "codigo_cid" => self.codigo_cid.as_str().into(),
+            _ => Value::Null,
+        }
+    }
+}
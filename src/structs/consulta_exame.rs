@@ -0,0 +1,348 @@
+use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
+use chrono::Local;
+use std::io::{self};
+use std::mem::size_of;
+
+/// Etapas do pedido de exame, sempre nesta ordem. Revisado é o estado final.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StatusExame {
+    #[default]
+    Solicitado,
+    Coletado,
+    ComResultado,
+    Revisado,
+}
+
+impl StatusExame {
+    pub const TODOS: [StatusExame; 4] = [
+        StatusExame::Solicitado,
+        StatusExame::Coletado,
+        StatusExame::ComResultado,
+        StatusExame::Revisado,
+    ];
+
+    fn codigo(self) -> u8 {
+        self as u8
+    }
+
+    fn from_codigo(codigo: u8) -> Result<StatusExame, io::Error> {
+        StatusExame::TODOS
+            .get(codigo as usize)
+            .copied()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("status de exame desconhecido: {}", codigo),
+                )
+            })
+    }
+
+    pub fn descricao(self) -> &'static str {
+        match self {
+            StatusExame::Solicitado => "Solicitado",
+            StatusExame::Coletado => "Coletado",
+            StatusExame::ComResultado => "Com resultado",
+            StatusExame::Revisado => "Revisado",
+        }
+    }
+
+    /// O resultado ainda não foi revisado pelo médico.
+    pub fn pendente(self) -> bool {
+        self != StatusExame::Revisado
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MudancaStatusExame {
+    pub status: StatusExame,
+    pub em: String, //AAAA-MM-DD HH:MM:SS
+}
+
+impl MudancaStatusExame {
+    pub fn agora(status: StatusExame) -> MudancaStatusExame {
+        MudancaStatusExame {
+            status,
+            em: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
+
+/// Valor fora da faixa de referência.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sinalizacao {
+    Baixo,
+    Alto,
+}
+
+impl Sinalizacao {
+    pub fn descricao(self) -> &'static str {
+        match self {
+            Sinalizacao::Baixo => "ABAIXO da referência",
+            Sinalizacao::Alto => "ACIMA da referência",
+        }
+    }
+}
+
+/// Um item do laudo (ex.: hemoglobina de um hemograma). O valor fica como
+/// texto porque há resultados qualitativos ("Não reagente"); a faixa de
+/// referência só é conferida quando o valor é numérico.
+#[derive(Debug, Default, Clone)]
+pub struct ResultadoExame {
+    pub analito: String,
+    pub valor: String,
+    pub unidade: String,
+    pub referencia_minima: Option<f32>,
+    pub referencia_maxima: Option<f32>,
+}
+
+impl ResultadoExame {
+    /// Aceita vírgula como separador decimal ("10,5").
+    pub fn valor_numerico(&self) -> Option<f32> {
+        self.valor.trim().replace(',', ".").parse().ok()
+    }
+
+    pub fn sinalizacao(&self) -> Option<Sinalizacao> {
+        let valor = self.valor_numerico()?;
+        if self.referencia_minima.is_some_and(|minimo| valor < minimo) {
+            Some(Sinalizacao::Baixo)
+        } else if self.referencia_maxima.is_some_and(|maximo| valor > maximo) {
+            Some(Sinalizacao::Alto)
+        } else {
+            None
+        }
+    }
+}
+
+/// Exame pedido em uma consulta. Uma consulta pode ter vários exames ou nenhum;
+/// a chave é o par (consulta, exame). O pedido acompanha o exame da solicitação
+/// até a revisão do resultado.
+#[derive(Debug, Default, Clone)]
+pub struct ConsultaExame {
+    pub codigo_consulta: u32,
+    pub codigo_exame: u32,
+    pub status: StatusExame,
+    /// Cada mudança de status com o momento em que aconteceu, da mais antiga à mais recente.
+    pub historico_status: Vec<MudancaStatusExame>,
+    pub resultados: Vec<ResultadoExame>,
+}
+
+impl ConsultaExame {
+    pub fn novo(codigo_consulta: u32, codigo_exame: u32) -> ConsultaExame {
+        ConsultaExame {
+            codigo_consulta,
+            codigo_exame,
+            status: StatusExame::Solicitado,
+            historico_status: vec![MudancaStatusExame::agora(StatusExame::Solicitado)],
+            resultados: Vec::new(),
+        }
+    }
+
+    /// Itens do laudo fora da faixa de referência.
+    pub fn alteracoes(&self) -> Vec<(&ResultadoExame, Sinalizacao)> {
+        self.resultados
+            .iter()
+            .filter_map(|r| r.sinalizacao().map(|s| (r, s)))
+            .collect()
+    }
+}
+
+const TEM_MINIMO: u8 = 0b01;
+const TEM_MAXIMO: u8 = 0b10;
+
+impl Entity for ConsultaExame {
+    type Key = (u32, u32);
+
+    fn get_key(&self) -> (u32, u32) {
+        (self.codigo_consulta, self.codigo_exame)
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
+        let mut bytes = Vec::new();
+        let write_string = |bytes: &mut Vec<u8>, s: &str| {
+            bytes.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(s.as_bytes());
+        };
+
+        bytes.extend_from_slice(&self.codigo_consulta.to_le_bytes());
+        bytes.extend_from_slice(&self.codigo_exame.to_le_bytes());
+
+        bytes.push(self.status.codigo());
+        bytes.extend_from_slice(&(self.historico_status.len() as u32).to_le_bytes());
+        for mudanca in &self.historico_status {
+            bytes.push(mudanca.status.codigo());
+            write_string(&mut bytes, &mudanca.em);
+        }
+
+        bytes.extend_from_slice(&(self.resultados.len() as u32).to_le_bytes());
+        for resultado in &self.resultados {
+            write_string(&mut bytes, &resultado.analito);
+            write_string(&mut bytes, &resultado.valor);
+            write_string(&mut bytes, &resultado.unidade);
+            let mut faixa = 0;
+            if resultado.referencia_minima.is_some() {
+                faixa |= TEM_MINIMO;
+            }
+            if resultado.referencia_maxima.is_some() {
+                faixa |= TEM_MAXIMO;
+            }
+            bytes.push(faixa);
+            bytes.extend_from_slice(&resultado.referencia_minima.unwrap_or(0.0).to_le_bytes());
+            bytes.extend_from_slice(&resultado.referencia_maxima.unwrap_or(0.0).to_le_bytes());
+        }
+
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, io::Error> {
+        let mut cursor = 0;
+        let truncado = || io::Error::new(io::ErrorKind::InvalidData, "pedido de exame truncado");
+        let read_slice = |c: &mut usize, len: usize| -> Result<&[u8], io::Error> {
+            let end = c.checked_add(len).ok_or_else(truncado)?;
+            let slice = bytes.get(*c..end).ok_or_else(truncado)?;
+            *c = end;
+            Ok(slice)
+        };
+        let read_u8 = |c: &mut usize| -> Result<u8, io::Error> { Ok(read_slice(c, 1)?[0]) };
+        let read_u32 = |c: &mut usize| -> Result<u32, io::Error> {
+            Ok(u32::from_le_bytes(read_slice(c, size_of::<u32>())?.try_into().unwrap()))
+        };
+        let read_f32 = |c: &mut usize| -> Result<f32, io::Error> {
+            Ok(f32::from_le_bytes(read_slice(c, size_of::<f32>())?.try_into().unwrap()))
+        };
+
+        let read_string = |c: &mut usize| -> Result<String, io::Error> {
+            let len = read_u32(c)? as usize;
+            String::from_utf8(read_slice(c, len)?.to_vec())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        };
+
+        let codigo_consulta = read_u32(&mut cursor)?;
+        let codigo_exame = read_u32(&mut cursor)?;
+
+        // Itens gravados antes do acompanhamento terminam no código do exame e
+        // contam como apenas solicitados.
+        let mut status = StatusExame::Solicitado;
+        let mut historico_status = Vec::new();
+        let mut resultados = Vec::new();
+        if cursor < bytes.len() {
+            status = StatusExame::from_codigo(read_u8(&mut cursor)?)?;
+            let quantidade = read_u32(&mut cursor)?;
+            for _ in 0..quantidade {
+                let status = StatusExame::from_codigo(read_u8(&mut cursor)?)?;
+                let em = read_string(&mut cursor)?;
+                historico_status.push(MudancaStatusExame { status, em });
+            }
+
+            let quantidade = read_u32(&mut cursor)?;
+            for _ in 0..quantidade {
+                let analito = read_string(&mut cursor)?;
+                let valor = read_string(&mut cursor)?;
+                let unidade = read_string(&mut cursor)?;
+                let faixa = read_u8(&mut cursor)?;
+                let minimo = read_f32(&mut cursor)?;
+                let maximo = read_f32(&mut cursor)?;
+                resultados.push(ResultadoExame {
+                    analito,
+                    valor,
+                    unidade,
+                    referencia_minima: (faixa & TEM_MINIMO != 0).then_some(minimo),
+                    referencia_maxima: (faixa & TEM_MAXIMO != 0).then_some(maximo),
+                });
+            }
+        }
+
+        Ok(ConsultaExame {
+            codigo_consulta,
+            codigo_exame,
+            status,
+            historico_status,
+            resultados,
+        })
+    }
+}
+
+impl Queryable for ConsultaExame {
+    const FIELDS: &'static [&'static str] = &[
+        "codigo_consulta",
+        "codigo_exame",
+        "status",
+        "alterados",
+    ];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo_consulta" => self.codigo_consulta.into(),
+            "codigo_exame" => self.codigo_exame.into(),
+            "status" => self.status.descricao().into(),
+            "alterados" => (self.alteracoes().len() as u32).into(),
+            _ => Value::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resultado(valor: &str, minimo: Option<f32>, maximo: Option<f32>) -> ResultadoExame {
+        ResultadoExame {
+            analito: "Glicose".to_string(),
+            valor: valor.to_string(),
+            unidade: "mg/dL".to_string(),
+            referencia_minima: minimo,
+            referencia_maxima: maximo,
+        }
+    }
+
+    #[test]
+    fn sinaliza_valores_fora_da_referencia() {
+        let faixa = (Some(70.0), Some(99.0));
+        for (valor, esperado) in [
+            ("69,9", Some(Sinalizacao::Baixo)),
+            ("70", None),
+            (" 99,0 ", None),
+            ("99.5", Some(Sinalizacao::Alto)),
+            ("Não reagente", None),
+            ("", None),
+        ] {
+            assert_eq!(resultado(valor, faixa.0, faixa.1).sinalizacao(), esperado, "{}", valor);
+        }
+
+        // Só o mínimo ou só o máximo: o outro lado não tem limite.
+        assert_eq!(resultado("5", Some(10.0), None).sinalizacao(), Some(Sinalizacao::Baixo));
+        assert_eq!(resultado("5000", Some(10.0), None).sinalizacao(), None);
+        assert_eq!(resultado("200,5", None, Some(200.0)).sinalizacao(), Some(Sinalizacao::Alto));
+        assert_eq!(resultado("-3", None, Some(200.0)).sinalizacao(), None);
+        assert_eq!(resultado("42", None, None).sinalizacao(), None);
+    }
+
+    #[test]
+    fn le_pedidos_antigos_e_recusa_registros_truncados() {
+        let mut pedido = ConsultaExame::novo(3, 7);
+        pedido.status = StatusExame::ComResultado;
+        pedido.resultados = vec![resultado("120", None, Some(99.0))];
+        let bytes = pedido.to_bytes().unwrap();
+
+        let lido = ConsultaExame::from_bytes(&bytes).unwrap();
+        assert_eq!(lido.get_key(), (3, 7));
+        assert_eq!(lido.status, StatusExame::ComResultado);
+        assert_eq!(lido.historico_status.len(), 1);
+        assert_eq!(lido.resultados[0].referencia_minima, None);
+        assert_eq!(lido.resultados[0].referencia_maxima, Some(99.0));
+        assert_eq!(lido.alteracoes().len(), 1);
+
+        // Formato de antes do acompanhamento: só consulta e exame.
+        let antigo = ConsultaExame::from_bytes(&bytes[..8]).unwrap();
+        assert_eq!(antigo.status, StatusExame::Solicitado);
+        assert!(antigo.historico_status.is_empty() && antigo.resultados.is_empty());
+
+        for tamanho in [0, 3, 9, 12, bytes.len() - 1] {
+            let erro = ConsultaExame::from_bytes(&bytes[..tamanho]).unwrap_err();
+            assert_eq!(erro.kind(), io::ErrorKind::InvalidData, "{} bytes", tamanho);
+        }
+        let mut status_invalido = bytes.clone();
+        status_invalido[8] = 9;
+        assert!(ConsultaExame::from_bytes(&status_invalido).is_err());
+    }
+}
@@ -1,7 +1,10 @@
 use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
 use std::io::{self};
 use std::mem::size_of;
 
+/// Quantidade de consultas marcadas em um dia para uma especialidade.
+/// A chave é o par (dia, especialidade), pois cada especialidade tem seu limite.
 #[derive(Debug, Default)]
 pub struct Diaria {
     pub codigo_dia: u32,
@@ -10,8 +13,10 @@ pub struct Diaria {
 }
 
 impl Entity for Diaria {
-    fn get_key(&self) -> u32 {
-        self.codigo_dia
+    type Key = (u32, u32);
+
+    fn get_key(&self) -> (u32, u32) {
+        (self.codigo_dia, self.codigo_especialidade)
     }
 
     fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
@@ -36,4 +41,21 @@ impl Entity for Diaria {
         
         Ok(Diaria { codigo_dia, codigo_especialidade, quantidade_consultas })
     }
+}
+
+impl Queryable for Diaria {
+    const FIELDS: &'static [&'static str] = &[
+        "codigo_dia",
+        "codigo_especialidade",
+        "quantidade_consultas",
+    ];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo_dia" => self.codigo_dia.into(),
+            "codigo_especialidade" => self.codigo_especialidade.into(),
+            "quantidade_consultas" => self.quantidade_consultas.into(),
+            _ => Value::Null,
+        }
+    }
 }
\ No newline at end of file
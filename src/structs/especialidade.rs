@@ -1,20 +1,60 @@
 use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
 use std::io::{self};
 use std::mem::size_of;
 
+/// Duração assumida para especialidades gravadas antes de o campo existir.
+pub const DURACAO_PADRAO: u32 = 30;
+
+/// Gravado no lugar de uma idade mínima ou máxima ausente.
+const SEM_LIMITE: u32 = u32::MAX;
+
 #[derive(Debug, Default)]
 pub struct Especialidade {
     pub codigo_especialidade: u32,
     pub descricao: String,
     pub valor_consulta: f32,
     pub limite_diario: u32,
+    /// Duração de cada consulta, em minutos.
+    pub duracao_consulta: u32,
+    /// Faixa de idade atendida, em anos, com os dois extremos incluídos
+    /// (ex.: pediatria com máxima 17). `None` deixa o lado sem limite.
+    pub idade_minima: Option<u32>,
+    pub idade_maxima: Option<u32>,
+}
+
+impl Especialidade {
+    pub fn restringe_idade(&self) -> bool {
+        self.idade_minima.is_some() || self.idade_maxima.is_some()
+    }
+
+    pub fn aceita_idade(&self, idade: u32) -> bool {
+        self.idade_minima.is_none_or(|minima| idade >= minima)
+            && self.idade_maxima.is_none_or(|maxima| idade <= maxima)
+    }
+
+    /// Ex.: "até 17 anos", "de 60 anos em diante", "qualquer idade".
+    pub fn descricao_idades(&self) -> String {
+        match (self.idade_minima, self.idade_maxima) {
+            (Some(minima), Some(maxima)) => format!("de {} a {} anos", minima, maxima),
+            (Some(minima), None) => format!("de {} anos em diante", minima),
+            (None, Some(maxima)) => format!("até {} anos", maxima),
+            (None, None) => "qualquer idade".to_string(),
+        }
+    }
 }
 
 impl Entity for Especialidade {
+    type Key = u32;
+
     fn get_key(&self) -> u32 {
         self.codigo_especialidade
     }
 
+    fn search_text(&self) -> Option<String> {
+        Some(self.descricao.clone())
+    }
+
     fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
         let mut bytes = Vec::new();
         bytes.extend_from_slice(&self.codigo_especialidade.to_le_bytes());
@@ -24,6 +64,10 @@ impl Entity for Especialidade {
 
         bytes.extend_from_slice(&self.valor_consulta.to_le_bytes());
         bytes.extend_from_slice(&self.limite_diario.to_le_bytes());
+        bytes.extend_from_slice(&self.duracao_consulta.to_le_bytes());
+        for limite in [self.idade_minima, self.idade_maxima] {
+            bytes.extend_from_slice(&limite.unwrap_or(SEM_LIMITE).to_le_bytes());
+        }
 
         Ok(bytes)
     }
@@ -54,7 +98,56 @@ impl Entity for Especialidade {
         let descricao = read_string(&mut cursor)?;
         let valor_consulta = read_f32(&mut cursor);
         let limite_diario = read_u32(&mut cursor);
-        
-        Ok(Especialidade { codigo_especialidade, descricao, valor_consulta, limite_diario })
+        // Registros antigos terminam no limite diário.
+        let duracao_consulta = if cursor < bytes.len() {
+            read_u32(&mut cursor)
+        } else {
+            DURACAO_PADRAO
+        };
+        // Depois vieram os limites de idade; sem eles, a especialidade atende qualquer idade.
+        let read_limite = |c: &mut usize| {
+            if *c < bytes.len() {
+                Some(read_u32(c)).filter(|limite| *limite != SEM_LIMITE)
+            } else {
+                None
+            }
+        };
+        let idade_minima = read_limite(&mut cursor);
+        let idade_maxima = read_limite(&mut cursor);
+
+        Ok(Especialidade {
+            codigo_especialidade,
+            descricao,
+            valor_consulta,
+            limite_diario,
+            duracao_consulta,
+            idade_minima,
+            idade_maxima,
+        })
+    }
+}
+
+impl Queryable for Especialidade {
+    const FIELDS: &'static [&'static str] = &[
+        "codigo_especialidade",
+        "descricao",
+        "valor_consulta",
+        "limite_diario",
+        "duracao_consulta",
+        "idade_minima",
+        "idade_maxima",
+    ];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo_especialidade" => self.codigo_especialidade.into(),
+            "descricao" => self.descricao.as_str().into(),
+            "valor_consulta" => self.valor_consulta.into(),
+            "limite_diario" => self.limite_diario.into(),
+            "duracao_consulta" => self.duracao_consulta.into(),
+            "idade_minima" => self.idade_minima.into(),
+            "idade_maxima" => self.idade_maxima.into(),
+            _ => Value::Null,
+        }
     }
 }
\ No newline at end of file
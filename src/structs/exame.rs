@@ -1,4 +1,5 @@
 use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
 use std::io::{self};
 use std::mem::size_of;
 
@@ -11,10 +12,16 @@ pub struct Exame {
 }
 
 impl Entity for Exame {
+    type Key = u32;
+
     fn get_key(&self) -> u32 {
         self.codigo_exame
     }
 
+    fn search_text(&self) -> Option<String> {
+        Some(self.descricao.clone())
+    }
+
     fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
         let mut bytes = Vec::new();
         bytes.extend_from_slice(&self.codigo_exame.to_le_bytes());
@@ -57,4 +64,23 @@ impl Entity for Exame {
         
         Ok(Exame { codigo_exame, descricao, codigo_especialidade, valor_exame })
     }
+}
+
+impl Queryable for Exame {
+    const FIELDS: &'static [&'static str] = &[
+        "codigo_exame",
+        "descricao",
+        "codigo_especialidade",
+        "valor_exame",
+    ];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo_exame" => self.codigo_exame.into(),
+            "descricao" => self.descricao.as_str().into(),
+            "codigo_especialidade" => self.codigo_especialidade.into(),
+            "valor_exame" => self.valor_exame.into(),
+            _ => Value::Null,
+        }
+    }
 }
\ No newline at end of file
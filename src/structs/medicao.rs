@@ -0,0 +1,84 @@
+use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
+use crate::utils::datas;
+use chrono::NaiveDate;
+use std::io::{self};
+use std::mem::size_of;
+
+/// Peso e altura de um paciente em uma data. A chave é o par (paciente, dia
+/// AAAAMMDD): uma nova medição no mesmo dia substitui a anterior.
+#[derive(Debug, Default, Clone)]
+pub struct Medicao {
+    pub codigo_paciente: u32,
+    pub data: NaiveDate,
+    pub peso: f32,
+    pub altura: f32,
+}
+
+impl Entity for Medicao {
+    type Key = (u32, u32);
+
+    fn get_key(&self) -> (u32, u32) {
+        (self.codigo_paciente, datas::codigo(self.data))
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.codigo_paciente.to_le_bytes());
+        bytes.extend_from_slice(&datas::codigo(self.data).to_le_bytes());
+        bytes.extend_from_slice(&self.peso.to_le_bytes());
+        bytes.extend_from_slice(&self.altura.to_le_bytes());
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, io::Error> {
+        let mut cursor = 0;
+        let read_u32 = |c: &mut usize| -> u32 {
+            let val = u32::from_le_bytes([bytes[*c], bytes[*c + 1], bytes[*c + 2], bytes[*c + 3]]);
+            *c += size_of::<u32>();
+            val
+        };
+        let read_f32 = |c: &mut usize| -> f32 {
+            let val = f32::from_le_bytes([bytes[*c], bytes[*c + 1], bytes[*c + 2], bytes[*c + 3]]);
+            *c += size_of::<f32>();
+            val
+        };
+
+        let codigo_paciente = read_u32(&mut cursor);
+        let codigo_dia = read_u32(&mut cursor);
+        let data = datas::de_codigo(codigo_dia).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("medição do paciente {}: data inválida {}", codigo_paciente, codigo_dia),
+            )
+        })?;
+        let peso = read_f32(&mut cursor);
+        let altura = read_f32(&mut cursor);
+
+        Ok(Medicao {
+            codigo_paciente,
+            data,
+            peso,
+            altura,
+        })
+    }
+}
+
+impl Queryable for Medicao {
+    const FIELDS: &'static [&'static str] = &[
+        "codigo_paciente",
+        "data",
+        "peso",
+        "altura",
+    ];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo_paciente" => self.codigo_paciente.into(),
+            "data" => self.data.into(),
+            "peso" => self.peso.into(),
+            "altura" => self.altura.into(),
+            _ => Value::Null,
+        }
+    }
+}
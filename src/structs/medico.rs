@@ -1,4 +1,6 @@
 use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
+use crate::utils::crm;
 use std::io::{self};
 use std::mem::size_of;
 
@@ -10,13 +12,26 @@ pub struct Medico {
     pub telefone: String,
     pub codigo_cidade: u32,
     pub codigo_especialidade: u32,
+    /// Número do CRM, sem zeros à esquerda. Vazio em médicos cadastrados antes do campo.
+    pub crm: String,
+    pub uf_crm: String,
 }
 
 impl Entity for Medico {
+    type Key = u32;
+
     fn get_key(&self) -> u32 {
         self.codigo_medico
     }
 
+    fn search_text(&self) -> Option<String> {
+        Some(self.nome.clone())
+    }
+
+    fn unique_key(&self) -> Option<String> {
+        (!self.crm.is_empty()).then(|| crm::chave(&self.crm, &self.uf_crm))
+    }
+
     fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
         let mut bytes = Vec::new();
         bytes.extend_from_slice(&self.codigo_medico.to_le_bytes());
@@ -33,6 +48,11 @@ impl Entity for Medico {
         bytes.extend_from_slice(&self.codigo_cidade.to_le_bytes());
         bytes.extend_from_slice(&self.codigo_especialidade.to_le_bytes());
 
+        for campo in [&self.crm, &self.uf_crm] {
+            bytes.extend_from_slice(&(campo.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(campo.as_bytes());
+        }
+
         Ok(bytes)
     }
 
@@ -60,7 +80,49 @@ impl Entity for Medico {
         let telefone = read_string(&mut cursor)?;
         let codigo_cidade = read_u32(&mut cursor);
         let codigo_especialidade = read_u32(&mut cursor);
-        
-        Ok(Medico { codigo_medico, nome, endereco, telefone, codigo_cidade, codigo_especialidade })
+        // Registros antigos terminam na especialidade.
+        let (crm, uf_crm) = if cursor < bytes.len() {
+            (read_string(&mut cursor)?, read_string(&mut cursor)?)
+        } else {
+            (String::new(), String::new())
+        };
+
+        Ok(Medico {
+            codigo_medico,
+            nome,
+            endereco,
+            telefone,
+            codigo_cidade,
+            codigo_especialidade,
+            crm,
+            uf_crm,
+        })
+    }
+}
+
+impl Queryable for Medico {
+    const FIELDS: &'static [&'static str] = &[
+        "codigo_medico",
+        "nome",
+        "crm",
+        "uf_crm",
+        "codigo_especialidade",
+        "endereco",
+        "telefone",
+        "codigo_cidade",
+    ];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo_medico" => self.codigo_medico.into(),
+            "nome" => self.nome.as_str().into(),
+            "crm" => (!self.crm.is_empty()).then_some(self.crm.as_str()).into(),
+            "uf_crm" => (!self.uf_crm.is_empty()).then_some(self.uf_crm.as_str()).into(),
+            "codigo_especialidade" => self.codigo_especialidade.into(),
+            "endereco" => self.endereco.as_str().into(),
+            "telefone" => self.telefone.as_str().into(),
+            "codigo_cidade" => self.codigo_cidade.into(),
+            _ => Value::Null,
+        }
     }
 }
\ No newline at end of file
@@ -4,4 +4,10 @@ pub mod especialidade;
 pub mod medico;
 pub mod exame;
 pub mod consulta;
-pub mod diaria;
\ No newline at end of file
+pub mod diaria;
+pub mod agenda_medico;
+pub mod consulta_exame;
+pub mod medicao;
+pub mod prontuario;
+pub mod cid;
+pub mod consulta_diagnostico;
\ No newline at end of file
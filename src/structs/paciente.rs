@@ -1,4 +1,7 @@
 use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
+use crate::utils::datas;
+use chrono::NaiveDate;
 use std::io::{self};
 use std::mem::size_of;
 
@@ -6,19 +9,165 @@ use std::mem::size_of;
 pub struct Paciente {
     pub codigo_paciente: u32,
     pub nome: String,
-    pub data_nascimento: String,
+    /// `None` quando a data gravada por versões antigas não pôde ser interpretada.
+    pub data_nascimento: Option<NaiveDate>,
     pub endereco: String,
     pub telefone: String,
     pub codigo_cidade: u32,
-    pub peso: f32,
-    pub altura: f32,
+    /// Só os 11 dígitos, sem pontuação. Vazio em pacientes cadastrados antes do campo.
+    pub cpf: String,
+    /// Usado nas curvas de IMC por idade. `None` em pacientes cadastrados antes do campo.
+    pub sexo: Option<Sexo>,
+}
+
+/// Gravada depois do código da cidade, no lugar do peso, pelas versões 6 e 7
+/// dos dados. Um peso nunca tem estes bits.
+const MARCA_SEM_MEDIDAS: u32 = u32::MAX;
+
+/// O que os registros de paciente trazem depois do código da cidade. Até a
+/// versão 7 dos dados vinham ali o peso e a altura, que passaram para
+/// `medicoes.dat`; a migração 8 regrava os pacientes sem esses bytes.
+#[derive(Debug, PartialEq)]
+pub enum MedidasAntigas {
+    /// Formato atual: ali já começa o CPF.
+    Nenhuma,
+    /// `MARCA_SEM_MEDIDAS` no lugar do par (versões 6 e 7).
+    Removidas,
+    /// Peso e altura, em registros anteriores a `medicoes.dat`.
+    Par(f32, f32),
+}
+
+impl MedidasAntigas {
+    /// Identifica o formato pelo que vem em `bytes[cursor..]`. No formato atual o
+    /// resto é o tamanho do CPF, o CPF e o byte do sexo; nos antigos o primeiro
+    /// campo é o peso, cujos bits nunca formam um tamanho tão pequeno.
+    pub fn ler(bytes: &[u8], cursor: usize) -> Result<MedidasAntigas, io::Error> {
+        let truncado = || io::Error::new(io::ErrorKind::InvalidData, "registro de paciente truncado");
+        let resto = bytes.get(cursor..).ok_or_else(truncado)?;
+        let ler_u32 = |i: usize| -> Result<u32, io::Error> {
+            let campo = resto.get(i..i + size_of::<u32>()).ok_or_else(truncado)?;
+            Ok(u32::from_le_bytes(campo.try_into().unwrap()))
+        };
+
+        let primeiro = ler_u32(0)?;
+        if primeiro == MARCA_SEM_MEDIDAS {
+            return Ok(MedidasAntigas::Removidas);
+        }
+        if primeiro as usize + size_of::<u32>() + 1 == resto.len() {
+            return Ok(MedidasAntigas::Nenhuma);
+        }
+        let altura = ler_u32(size_of::<u32>())?;
+        Ok(MedidasAntigas::Par(f32::from_bits(primeiro), f32::from_bits(altura)))
+    }
+
+    /// Bytes que o campo ocupa no registro.
+    pub fn tamanho(&self) -> usize {
+        match self {
+            MedidasAntigas::Nenhuma => 0,
+            MedidasAntigas::Removidas => size_of::<u32>(),
+            MedidasAntigas::Par(..) => 2 * size_of::<f32>(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sexo {
+    Masculino,
+    Feminino,
+}
+
+impl Sexo {
+    fn codigo(sexo: Option<Sexo>) -> u8 {
+        match sexo {
+            None => 0,
+            Some(Sexo::Masculino) => 1,
+            Some(Sexo::Feminino) => 2,
+        }
+    }
+
+    fn from_codigo(codigo: u8) -> Result<Option<Sexo>, io::Error> {
+        match codigo {
+            0 => Ok(None),
+            1 => Ok(Some(Sexo::Masculino)),
+            2 => Ok(Some(Sexo::Feminino)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("código de sexo desconhecido: {}", codigo),
+            )),
+        }
+    }
+
+    /// Aceita "M"/"F" ou o nome por extenso, sem diferenciar maiúsculas.
+    pub fn interpretar(texto: &str) -> Option<Sexo> {
+        match texto.trim().to_lowercase().as_str() {
+            "m" | "masculino" => Some(Sexo::Masculino),
+            "f" | "feminino" => Some(Sexo::Feminino),
+            _ => None,
+        }
+    }
+
+    pub fn descricao(self) -> &'static str {
+        match self {
+            Sexo::Masculino => "Masculino",
+            Sexo::Feminino => "Feminino",
+        }
+    }
+}
+
+impl Paciente {
+    /// Idade em anos completos na data `em`. `None` sem data de nascimento.
+    pub fn idade(&self, em: NaiveDate) -> Option<u32> {
+        self.data_nascimento.and_then(|nascimento| em.years_since(nascimento))
+    }
+}
+
+/// Faixas usadas nos relatórios: pediátrica até 17 anos, geriátrica a partir de 60.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FaixaEtaria {
+    Pediatrica,
+    Adulta,
+    Geriatrica,
+}
+
+impl FaixaEtaria {
+    pub const TODAS: [FaixaEtaria; 3] = [
+        FaixaEtaria::Pediatrica,
+        FaixaEtaria::Adulta,
+        FaixaEtaria::Geriatrica,
+    ];
+
+    pub fn de_idade(idade: u32) -> FaixaEtaria {
+        match idade {
+            0..18 => FaixaEtaria::Pediatrica,
+            18..60 => FaixaEtaria::Adulta,
+            _ => FaixaEtaria::Geriatrica,
+        }
+    }
+
+    pub fn descricao(self) -> &'static str {
+        match self {
+            FaixaEtaria::Pediatrica => "Pediátrica (0 a 17 anos)",
+            FaixaEtaria::Adulta => "Adulta (18 a 59 anos)",
+            FaixaEtaria::Geriatrica => "Geriátrica (60 anos ou mais)",
+        }
+    }
 }
 
 impl Entity for Paciente {
+    type Key = u32;
+
     fn get_key(&self) -> u32 {
         self.codigo_paciente
     }
 
+    fn search_text(&self) -> Option<String> {
+        Some(self.nome.clone())
+    }
+
+    fn unique_key(&self) -> Option<String> {
+        (!self.cpf.is_empty()).then(|| self.cpf.clone())
+    }
+
     fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
         let mut bytes = Vec::new();
         bytes.extend_from_slice(&self.codigo_paciente.to_le_bytes());
@@ -26,8 +175,7 @@ impl Entity for Paciente {
         bytes.extend_from_slice(&(self.nome.len() as u32).to_le_bytes());
         bytes.extend_from_slice(self.nome.as_bytes());
 
-        bytes.extend_from_slice(&(self.data_nascimento.len() as u32).to_le_bytes());
-        bytes.extend_from_slice(self.data_nascimento.as_bytes());
+        datas::gravar(&mut bytes, self.data_nascimento);
 
         bytes.extend_from_slice(&(self.endereco.len() as u32).to_le_bytes());
         bytes.extend_from_slice(self.endereco.as_bytes());
@@ -36,8 +184,10 @@ impl Entity for Paciente {
         bytes.extend_from_slice(self.telefone.as_bytes());
 
         bytes.extend_from_slice(&self.codigo_cidade.to_le_bytes());
-        bytes.extend_from_slice(&self.peso.to_le_bytes());
-        bytes.extend_from_slice(&self.altura.to_le_bytes());
+
+        bytes.extend_from_slice(&(self.cpf.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.cpf.as_bytes());
+        bytes.push(Sexo::codigo(self.sexo));
 
         Ok(bytes)
     }
@@ -50,12 +200,6 @@ impl Entity for Paciente {
             val
         };
 
-        let read_f32 = |c: &mut usize| -> f32 {
-            let val = f32::from_le_bytes([bytes[*c], bytes[*c + 1], bytes[*c + 2], bytes[*c + 3]]);
-            *c += size_of::<f32>();
-            val
-        };
-
         let read_string = |c: &mut usize| -> Result<String, io::Error> {
             let len = read_u32(c) as usize;
             let start = *c;
@@ -68,13 +212,23 @@ impl Entity for Paciente {
 
         let codigo_paciente = read_u32(&mut cursor);
         let nome = read_string(&mut cursor)?;
-        let data_nascimento = read_string(&mut cursor)?;
+        let data_nascimento = datas::ler(bytes, &mut cursor)?;
         let endereco = read_string(&mut cursor)?;
         let telefone = read_string(&mut cursor)?;
         let codigo_cidade = read_u32(&mut cursor);
-        let peso = read_f32(&mut cursor);
-        let altura = read_f32(&mut cursor);
-        
+        cursor += MedidasAntigas::ler(bytes, cursor)?.tamanho();
+        // Registros antigos terminam na altura.
+        let cpf = if cursor < bytes.len() {
+            read_string(&mut cursor)?
+        } else {
+            String::new()
+        };
+        let sexo = if cursor < bytes.len() {
+            Sexo::from_codigo(bytes[cursor])?
+        } else {
+            None
+        };
+
         Ok(Paciente {
             codigo_paciente,
             nome,
@@ -82,8 +236,97 @@ impl Entity for Paciente {
             endereco,
             telefone,
             codigo_cidade,
-            peso,
-            altura,
+            cpf,
+            sexo,
         })
     }
-}
\ No newline at end of file
+}
+
+impl Queryable for Paciente {
+    const FIELDS: &'static [&'static str] = &[
+        "codigo_paciente",
+        "nome",
+        "cpf",
+        "data_nascimento",
+        "sexo",
+        "endereco",
+        "telefone",
+        "codigo_cidade",
+    ];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo_paciente" => self.codigo_paciente.into(),
+            "nome" => self.nome.as_str().into(),
+            "cpf" => (!self.cpf.is_empty()).then_some(self.cpf.as_str()).into(),
+            "data_nascimento" => self.data_nascimento.into(),
+            "sexo" => self.sexo.map(Sexo::descricao).into(),
+            "endereco" => self.endereco.as_str().into(),
+            "telefone" => self.telefone.as_str().into(),
+            "codigo_cidade" => self.codigo_cidade.into(),
+            _ => Value::Null,
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Registro no formato de antes de `medicoes.dat`: data em texto, peso,
+    /// altura e, quando `cpf` vem, o CPF sem o byte do sexo.
+    fn registro_antigo(cpf: Option<&str>) -> Vec<u8> {
+        let mut bytes = 7u32.to_le_bytes().to_vec();
+        for texto in ["Ana", "01/02/2010", "Rua A", "5555"] {
+            bytes.extend_from_slice(&(texto.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(texto.as_bytes());
+        }
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&62.5f32.to_le_bytes());
+        bytes.extend_from_slice(&1.7f32.to_le_bytes());
+        if let Some(cpf) = cpf {
+            bytes.extend_from_slice(&(cpf.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(cpf.as_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn le_os_formatos_antigos_e_grava_sem_as_medidas() {
+        // Código, nome, data, endereço e telefone ocupam 4 + 7 + 14 + 9 + 8 bytes.
+        let inicio_medidas = 42 + size_of::<u32>();
+        for cpf in [None, Some(""), Some("52998224725")] {
+            let bytes = registro_antigo(cpf);
+            assert_eq!(
+                MedidasAntigas::ler(&bytes, inicio_medidas).unwrap(),
+                MedidasAntigas::Par(62.5, 1.7)
+            );
+            let paciente = Paciente::from_bytes(&bytes).unwrap();
+            assert_eq!(paciente.nome, "Ana");
+            assert_eq!(paciente.codigo_cidade, 3);
+            assert_eq!(paciente.cpf, cpf.unwrap_or(""));
+            assert_eq!(paciente.data_nascimento, NaiveDate::from_ymd_opt(2010, 2, 1));
+        }
+
+        let paciente = Paciente {
+            codigo_paciente: 7,
+            nome: "Ana".to_string(),
+            cpf: "52998224725".to_string(),
+            sexo: Some(Sexo::Feminino),
+            ..Default::default()
+        };
+        let mut atual = paciente.to_bytes().unwrap();
+        let inicio_medidas = atual.len() - (4 + 11 + 1);
+        assert_eq!(MedidasAntigas::ler(&atual, inicio_medidas).unwrap(), MedidasAntigas::Nenhuma);
+        let lido = Paciente::from_bytes(&atual).unwrap();
+        assert_eq!((lido.cpf.as_str(), lido.sexo), ("52998224725", Some(Sexo::Feminino)));
+
+        // Versões 6 e 7: a marca no lugar do par.
+        atual.splice(inicio_medidas..inicio_medidas, MARCA_SEM_MEDIDAS.to_le_bytes());
+        assert_eq!(MedidasAntigas::ler(&atual, inicio_medidas).unwrap(), MedidasAntigas::Removidas);
+        assert_eq!(Paciente::from_bytes(&atual).unwrap().sexo, Some(Sexo::Feminino));
+
+        let truncado = &registro_antigo(None)[..inicio_medidas + 6];
+        let erro = MedidasAntigas::ler(truncado, inicio_medidas).unwrap_err();
+        assert_eq!(erro.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::db::file_manager::Entity;
+use crate::db::query::{Queryable, Value};
+use chrono::Local;
+use std::io::{self};
+use std::mem::size_of;
+
+/// Anotação clínica de um atendimento. Uma consulta pode ter várias (ex.: a
+/// anotação do atendimento e, depois, a evolução com o resultado dos exames).
+#[derive(Debug, Default, Clone)]
+pub struct Prontuario {
+    pub codigo_prontuario: u32,
+    pub codigo_consulta: u32,
+    /// Copiado da consulta, para montar o histórico do paciente sem relê-las.
+    pub codigo_paciente: u32,
+    pub anamnese: String,
+    /// Códigos CID-10 (ex.: "J11.1").
+    pub diagnosticos: Vec<String>,
+    /// Um item por medicamento ou conduta.
+    pub prescricoes: Vec<String>,
+    pub observacoes: String,
+    pub criado_em: String, //AAAA-MM-DD HH:MM:SS
+    pub alterado_em: String, //AAAA-MM-DD HH:MM:SS
+}
+
+pub fn agora() -> String {
+    Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+impl Entity for Prontuario {
+    type Key = u32;
+
+    fn get_key(&self) -> u32 {
+        self.codigo_prontuario
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
+        let mut bytes = Vec::new();
+        let write_string = |bytes: &mut Vec<u8>, s: &str| {
+            bytes.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(s.as_bytes());
+        };
+
+        bytes.extend_from_slice(&self.codigo_prontuario.to_le_bytes());
+        bytes.extend_from_slice(&self.codigo_consulta.to_le_bytes());
+        bytes.extend_from_slice(&self.codigo_paciente.to_le_bytes());
+        write_string(&mut bytes, &self.anamnese);
+        for lista in [&self.diagnosticos, &self.prescricoes] {
+            bytes.extend_from_slice(&(lista.len() as u32).to_le_bytes());
+            for item in lista {
+                write_string(&mut bytes, item);
+            }
+        }
+        write_string(&mut bytes, &self.observacoes);
+        write_string(&mut bytes, &self.criado_em);
+        write_string(&mut bytes, &self.alterado_em);
+
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, io::Error> {
+        let mut cursor = 0;
+        let read_u32 = |c: &mut usize| -> u32 {
+            let val = u32::from_le_bytes([bytes[*c], bytes[*c + 1], bytes[*c + 2], bytes[*c + 3]]);
+            *c += size_of::<u32>();
+            val
+        };
+
+        let read_string = |c: &mut usize| -> Result<String, io::Error> {
+            let len = read_u32(c) as usize;
+            let start = *c;
+            let end = start + len;
+            let s = String::from_utf8(bytes[start..end].to_vec())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            *c = end;
+            Ok(s)
+        };
+
+        let read_list = |c: &mut usize| -> Result<Vec<String>, io::Error> {
+            let quantidade = read_u32(c);
+            (0..quantidade).map(|_| read_string(c)).collect()
+        };
+
+        let codigo_prontuario = read_u32(&mut cursor);
+        let codigo_consulta = read_u32(&mut cursor);
+        let codigo_paciente = read_u32(&mut cursor);
+        let anamnese = read_string(&mut cursor)?;
+        let diagnosticos = read_list(&mut cursor)?;
+        let prescricoes = read_list(&mut cursor)?;
+        let observacoes = read_string(&mut cursor)?;
+        let criado_em = read_string(&mut cursor)?;
+        let alterado_em = read_string(&mut cursor)?;
+
+        Ok(Prontuario {
+            codigo_prontuario,
+            codigo_consulta,
+            codigo_paciente,
+            anamnese,
+            diagnosticos,
+            prescricoes,
+            observacoes,
+            criado_em,
+            alterado_em,
+        })
+    }
+}
+
+impl Queryable for Prontuario {
+    const FIELDS: &'static [&'static str] = &[
+        "codigo_prontuario",
+        "codigo_consulta",
+        "codigo_paciente",
+        "anamnese",
+        "diagnosticos",
+        "prescricoes",
+        "observacoes",
+        "criado_em",
+        "alterado_em",
+    ];
+
+    fn field(&self, name: &str) -> Value {
+        match name {
+            "codigo_prontuario" => self.codigo_prontuario.into(),
+            "codigo_consulta" => self.codigo_consulta.into(),
+            "codigo_paciente" => self.codigo_paciente.into(),
+            "anamnese" => self.anamnese.as_str().into(),
+            "diagnosticos" => self.diagnosticos.join(", ").as_str().into(),
+            "prescricoes" => self.prescricoes.join("; ").as_str().into(),
+            "observacoes" => self.observacoes.as_str().into(),
+            "criado_em" => self.criado_em.as_str().into(),
+            "alterado_em" => self.alterado_em.as_str().into(),
+            _ => Value::Null,
+        }
+    }
+}
@@ -0,0 +1,843 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+use chrono::{Local, NaiveDate, NaiveTime, Timelike};
+
+use crate::db::database::Database;
+use crate::db::file_manager::{Entity, FileManager};
+use crate::structs::{
+    agenda_medico::AgendaMedico,
+    consulta::{Consulta, MudancaStatus, StatusConsulta},
+    diaria::Diaria,
+    especialidade::{DURACAO_PADRAO, Especialidade},
+    exame::Exame,
+};
+use crate::utils::{datas, exames_consulta};
+use crate::utils::regras::{self, Agendamento, RegraAgendamento};
+
+#[derive(Debug)]
+pub enum ErroAgendamento {
+    ConsultaNaoEncontrada(u32),
+    PacienteNaoEncontrado(u32),
+    MedicoNaoEncontrado(u32),
+    EspecialidadeNaoEncontrada(u32),
+    DataInvalida(String),
+    DataPassada(String),
+    HoraInvalida(String),
+    SemVagas { limite: u32 },
+    ConflitoMedico { codigo_consulta: u32, hora: String },
+    ConflitoPaciente { codigo_consulta: u32, hora: String },
+    AgendaNaoEncontrada(u32),
+    MedicoNaoAtende(String),
+    DataBloqueada(String),
+    ForaDoExpediente { inicio: String, fim: String },
+    NoIntervalo { inicio: String, fim: String },
+    ConsultaEncerrada(StatusConsulta),
+    TransicaoInvalida { de: StatusConsulta, para: StatusConsulta },
+    RegraViolada { regra: &'static str, mensagem: String },
+    Io(io::Error),
+}
+
+impl fmt::Display for ErroAgendamento {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErroAgendamento::ConsultaNaoEncontrada(codigo) => {
+                write!(f, "Consulta com código {} não encontrada.", codigo)
+            }
+            ErroAgendamento::PacienteNaoEncontrado(codigo) => {
+                write!(f, "Paciente com código {} não encontrado.", codigo)
+            }
+            ErroAgendamento::MedicoNaoEncontrado(codigo) => {
+                write!(f, "Médico com código {} não encontrado.", codigo)
+            }
+            ErroAgendamento::EspecialidadeNaoEncontrada(codigo) => write!(
+                f,
+                "Não foi possível carregar a especialidade {} do médico. Não é possível verificar o limite diário.",
+                codigo
+            ),
+            ErroAgendamento::DataInvalida(data) => {
+                write!(f, "Formato de data inválido ({}). Use DD/MM/AAAA.", data)
+            }
+            ErroAgendamento::DataPassada(data) => write!(
+                f,
+                "Não é possível agendar consultas para uma data passada ({}).",
+                data
+            ),
+            ErroAgendamento::HoraInvalida(hora) => {
+                write!(f, "Formato de hora inválido ({}). Use HH:MM.", hora)
+            }
+            ErroAgendamento::SemVagas { limite } => write!(
+                f,
+                "Não há mais vagas para esta especialidade nesta data. Limite diário de {} atingido.",
+                limite
+            ),
+            ErroAgendamento::ConflitoMedico {
+                codigo_consulta,
+                hora,
+            } => write!(
+                f,
+                "O médico já tem a consulta {} às {} neste horário.",
+                codigo_consulta, hora
+            ),
+            ErroAgendamento::ConflitoPaciente {
+                codigo_consulta,
+                hora,
+            } => write!(
+                f,
+                "O paciente já tem a consulta {} às {} neste horário.",
+                codigo_consulta, hora
+            ),
+            ErroAgendamento::AgendaNaoEncontrada(codigo) => {
+                write!(f, "O médico {} não tem agenda cadastrada.", codigo)
+            }
+            ErroAgendamento::MedicoNaoAtende(data) => {
+                write!(f, "O médico não atende neste dia da semana ({}).", data)
+            }
+            ErroAgendamento::DataBloqueada(data) => {
+                write!(f, "A agenda do médico está bloqueada em {}.", data)
+            }
+            ErroAgendamento::ForaDoExpediente { inicio, fim } => write!(
+                f,
+                "Horário fora do expediente do médico ({} às {}).",
+                inicio, fim
+            ),
+            ErroAgendamento::NoIntervalo { inicio, fim } => write!(
+                f,
+                "Horário coincide com o intervalo do médico ({} às {}).",
+                inicio, fim
+            ),
+            ErroAgendamento::ConsultaEncerrada(status) => write!(
+                f,
+                "A consulta está com status {} e não pode mais ser alterada.",
+                status.descricao()
+            ),
+            ErroAgendamento::TransicaoInvalida { de, para } => write!(
+                f,
+                "Não é possível mudar o status de {} para {}.",
+                de.descricao(),
+                para.descricao()
+            ),
+            ErroAgendamento::RegraViolada { regra, mensagem } => {
+                write!(f, "{} (regra: {})", mensagem, regra)
+            }
+            ErroAgendamento::Io(e) => write!(f, "Falha de acesso aos arquivos: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for ErroAgendamento {
+    fn from(e: io::Error) -> Self {
+        ErroAgendamento::Io(e)
+    }
+}
+
+/// Aceita `DD/MM/AAAA` ou `AAAAMMDD` e rejeita datas anteriores a hoje.
+pub fn validar_data(data: &str) -> Result<NaiveDate, ErroAgendamento> {
+    let hoje = Local::now().date_naive();
+    match datas::interpretar(data) {
+        Some(d) if d < hoje => Err(ErroAgendamento::DataPassada(data.to_string())),
+        Some(d) => Ok(d),
+        None => Err(ErroAgendamento::DataInvalida(data.to_string())),
+    }
+}
+
+pub fn validar_hora(hora: &str) -> Result<NaiveTime, ErroAgendamento> {
+    NaiveTime::parse_from_str(hora.trim(), "%H:%M")
+        .map_err(|_| ErroAgendamento::HoraInvalida(hora.to_string()))
+}
+
+/// Minutos desde a meia-noite; evita a volta do relógio de `NaiveTime`
+/// quando uma consulta termina depois das 23:59.
+fn minutos(hora: NaiveTime) -> u32 {
+    hora.num_seconds_from_midnight() / 60
+}
+
+/// Início e fim, em minutos desde a meia-noite.
+type Intervalo = (u32, u32);
+
+fn sobrepoe(a: Intervalo, b: Intervalo) -> bool {
+    a.0 < b.1 && b.0 < a.1
+}
+
+/// Duração das consultas do médico, conforme sua especialidade.
+pub fn duracao_do_medico(db: &Database, codigo_medico: u32) -> Result<u32, io::Error> {
+    Ok(match db.medicos.read_record(codigo_medico)? {
+        Some(medico) => db
+            .especialidades
+            .read_record(medico.codigo_especialidade)?
+            .map_or(DURACAO_PADRAO, |e| e.duracao_consulta),
+        None => DURACAO_PADRAO,
+    })
+}
+
+/// Rejeita a consulta se ela se sobrepõe, no mesmo dia, a outra consulta do
+/// mesmo médico ou do mesmo paciente. Cada consulta ocupa a duração definida
+/// na especialidade do seu médico; a própria consulta (mesmo código) é ignorada,
+/// assim como consultas canceladas e consultas antigas com hora em formato inválido.
+pub fn verificar_conflitos(
+    db: &Database,
+    consulta: &Consulta,
+    duracao: u32,
+) -> Result<(), ErroAgendamento> {
+    let inicio = minutos(validar_hora(&consulta.hora)?);
+    let intervalo = (inicio, inicio + duracao);
+
+    for outra in db.consultas.read_all_records()? {
+        if outra.codigo_consulta == consulta.codigo_consulta
+            || outra.data != consulta.data
+            || !outra.status.ocupa_horario()
+        {
+            continue;
+        }
+        let mesmo_medico = outra.codigo_medico == consulta.codigo_medico;
+        let mesmo_paciente = outra.codigo_paciente == consulta.codigo_paciente;
+        if !mesmo_medico && !mesmo_paciente {
+            continue;
+        }
+        // Sem hora legível não há como saber se sobrepõe; essas consultas
+        // antigas aparecem na reconciliação de diárias (`consultas_com_hora_invalida`).
+        let Ok(outra_inicio) = validar_hora(&outra.hora) else {
+            continue;
+        };
+        let outra_inicio = minutos(outra_inicio);
+        let outra_duracao = duracao_do_medico(db, outra.codigo_medico)?;
+
+        if sobrepoe(intervalo, (outra_inicio, outra_inicio + outra_duracao)) {
+            let hora = outra.hora.clone();
+            let codigo_consulta = outra.codigo_consulta;
+            return Err(if mesmo_medico {
+                ErroAgendamento::ConflitoMedico {
+                    codigo_consulta,
+                    hora,
+                }
+            } else {
+                ErroAgendamento::ConflitoPaciente {
+                    codigo_consulta,
+                    hora,
+                }
+            });
+        }
+    }
+    Ok(())
+}
+
+fn verificar_dia(agenda: &AgendaMedico, dia: NaiveDate) -> Result<(), ErroAgendamento> {
+    if !agenda.atende_no_dia(dia) {
+        return Err(ErroAgendamento::MedicoNaoAtende(dia.format("%d/%m/%Y").to_string()));
+    }
+    if agenda.data_bloqueada(datas::codigo(dia)) {
+        return Err(ErroAgendamento::DataBloqueada(dia.format("%d/%m/%Y").to_string()));
+    }
+    Ok(())
+}
+
+/// Expediente e intervalo da agenda, em minutos desde a meia-noite.
+fn horarios_da_agenda(
+    agenda: &AgendaMedico,
+) -> Result<(Intervalo, Option<Intervalo>), ErroAgendamento> {
+    let expediente = (
+        minutos(validar_hora(&agenda.hora_inicio)?),
+        minutos(validar_hora(&agenda.hora_fim)?),
+    );
+    let intervalo = if agenda.tem_intervalo() {
+        Some((
+            minutos(validar_hora(&agenda.intervalo_inicio)?),
+            minutos(validar_hora(&agenda.intervalo_fim)?),
+        ))
+    } else {
+        None
+    };
+    Ok((expediente, intervalo))
+}
+
+/// Confere a consulta contra a agenda do médico: dia da semana, datas
+/// bloqueadas, expediente e intervalo. Médicos sem agenda cadastrada atendem
+/// em qualquer horário, como antes de a agenda existir.
+pub fn verificar_expediente(
+    db: &Database,
+    consulta: &Consulta,
+    duracao: u32,
+) -> Result<(), ErroAgendamento> {
+    let Some(agenda) = db.agendas.read_record(consulta.codigo_medico)? else {
+        return Ok(());
+    };
+    verificar_dia(&agenda, consulta.data)?;
+
+    let inicio = minutos(validar_hora(&consulta.hora)?);
+    let ocupado = (inicio, inicio + duracao);
+    let (expediente, intervalo) = horarios_da_agenda(&agenda)?;
+
+    if ocupado.0 < expediente.0 || ocupado.1 > expediente.1 {
+        return Err(ErroAgendamento::ForaDoExpediente {
+            inicio: agenda.hora_inicio,
+            fim: agenda.hora_fim,
+        });
+    }
+    if let Some(intervalo) = intervalo
+        && sobrepoe(ocupado, intervalo)
+    {
+        return Err(ErroAgendamento::NoIntervalo {
+            inicio: agenda.intervalo_inicio,
+            fim: agenda.intervalo_fim,
+        });
+    }
+    Ok(())
+}
+
+fn hora_de_minutos(minutos: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(minutos / 60, minutos % 60, 0).unwrap()
+}
+
+/// Divide o expediente em blocos de `duracao` minutos e devolve o início dos
+/// blocos que não batem no intervalo, nas consultas já marcadas com o médico
+/// nem em `ocupados` (ex.: outras consultas do paciente).
+fn blocos_livres(
+    agenda: &AgendaMedico,
+    duracao: u32,
+    consultas: &[Consulta],
+    data: NaiveDate,
+    ocupados: &[Intervalo],
+) -> Result<Vec<u32>, ErroAgendamento> {
+    let (expediente, intervalo) = horarios_da_agenda(agenda)?;
+
+    let mut ocupados = ocupados.to_vec();
+    // Consultas com hora ilegível não bloqueiam nenhum bloco; ver `consultas_com_hora_invalida`.
+    for consulta in consultas {
+        if consulta.codigo_medico == agenda.codigo_medico
+            && consulta.data == data
+            && consulta.status.ocupa_horario()
+            && let Ok(hora) = validar_hora(&consulta.hora)
+        {
+            let inicio = minutos(hora);
+            ocupados.push((inicio, inicio + duracao));
+        }
+    }
+    ocupados.extend(intervalo);
+
+    let mut livres = Vec::new();
+    let mut inicio = expediente.0;
+    while inicio + duracao <= expediente.1 {
+        let bloco = (inicio, inicio + duracao);
+        if !ocupados.iter().any(|o| sobrepoe(bloco, *o)) {
+            livres.push(inicio);
+        }
+        inicio += duracao;
+    }
+    Ok(livres)
+}
+
+/// Horários em que o médico pode receber uma consulta na data: a agenda é
+/// dividida em blocos da duração da especialidade, descartando o intervalo
+/// e os blocos que se sobrepõem a consultas já marcadas com ele.
+pub fn horarios_livres(
+    db: &Database,
+    codigo_medico: u32,
+    data: NaiveDate,
+) -> Result<Vec<NaiveTime>, ErroAgendamento> {
+    let agenda = db
+        .agendas
+        .read_record(codigo_medico)?
+        .ok_or(ErroAgendamento::AgendaNaoEncontrada(codigo_medico))?;
+    verificar_dia(&agenda, data)?;
+
+    let duracao = duracao_do_medico(db, codigo_medico)?.max(1);
+    let consultas = db.consultas.read_all_records()?;
+    let livres = blocos_livres(&agenda, duracao, &consultas, data, &[])?;
+    Ok(livres.into_iter().map(hora_de_minutos).collect())
+}
+
+/// Quantos dias à frente a busca de horários livres percorre antes de desistir.
+pub const DIAS_BUSCA: usize = 60;
+
+pub enum BuscaHorario {
+    Especialidade(u32),
+    Medico(u32),
+}
+
+#[derive(Debug)]
+pub struct HorarioLivre {
+    pub data: NaiveDate,
+    pub hora: NaiveTime,
+    pub codigo_medico: u32,
+}
+
+/// Procura, a partir de `a_partir`, os primeiros `quantidade` horários em que
+/// há vaga na diária da especialidade e o médico está livre na agenda.
+/// Médicos sem agenda cadastrada não entram na busca. Com `codigo_paciente`,
+/// horários que batem com outras consultas do paciente também são descartados.
+pub fn proximos_horarios(
+    db: &Database,
+    busca: BuscaHorario,
+    a_partir: NaiveDate,
+    quantidade: usize,
+    codigo_paciente: Option<u32>,
+) -> Result<Vec<HorarioLivre>, ErroAgendamento> {
+    if quantidade == 0 {
+        return Ok(Vec::new());
+    }
+    let medicos = match busca {
+        BuscaHorario::Medico(codigo) => vec![
+            db.medicos
+                .read_record(codigo)?
+                .ok_or(ErroAgendamento::MedicoNaoEncontrado(codigo))?,
+        ],
+        BuscaHorario::Especialidade(codigo) => db
+            .medicos
+            .read_all_records()?
+            .into_iter()
+            .filter(|m| m.codigo_especialidade == codigo)
+            .collect(),
+    };
+
+    let mut candidatos = Vec::new();
+    for medico in medicos {
+        let Some(agenda) = db.agendas.read_record(medico.codigo_medico)? else {
+            continue;
+        };
+        let Some(especialidade) = db.especialidades.read_record(medico.codigo_especialidade)?
+        else {
+            continue;
+        };
+        candidatos.push((agenda, especialidade));
+    }
+
+    let consultas = db.consultas.read_all_records()?;
+    let agora = Local::now().naive_local();
+    let mut encontrados = Vec::new();
+
+    for dia in a_partir.iter_days().take(DIAS_BUSCA) {
+        let codigo_dia = datas::codigo(dia);
+
+        let mut ocupados_paciente = Vec::new();
+        for consulta in &consultas {
+            if Some(consulta.codigo_paciente) == codigo_paciente
+                && consulta.data == dia
+                && consulta.status.ocupa_horario()
+                && let Ok(hora) = validar_hora(&consulta.hora)
+            {
+                let inicio = minutos(hora);
+                let duracao = duracao_do_medico(db, consulta.codigo_medico)?;
+                ocupados_paciente.push((inicio, inicio + duracao));
+            }
+        }
+
+        let mut do_dia = Vec::new();
+        for (agenda, especialidade) in &candidatos {
+            if verificar_dia(agenda, dia).is_err()
+                || verificar_vaga(&db.diarias, codigo_dia, especialidade).is_err()
+            {
+                continue;
+            }
+            let duracao = especialidade.duracao_consulta.max(1);
+            for inicio in blocos_livres(agenda, duracao, &consultas, dia, &ocupados_paciente)? {
+                if dia == agora.date() && inicio <= minutos(agora.time()) {
+                    continue;
+                }
+                do_dia.push(HorarioLivre {
+                    data: dia,
+                    hora: hora_de_minutos(inicio),
+                    codigo_medico: agenda.codigo_medico,
+                });
+            }
+        }
+
+        do_dia.sort_by_key(|h| (h.hora, h.codigo_medico));
+        for horario in do_dia {
+            encontrados.push(horario);
+            if encontrados.len() == quantidade {
+                return Ok(encontrados);
+            }
+        }
+    }
+    Ok(encontrados)
+}
+
+/// Consultas do médico ainda agendadas ou confirmadas. Enquanto houver alguma, a
+/// especialidade dele não pode mudar: as diárias delas estão contadas na atual.
+pub fn consultas_em_aberto_do_medico(
+    consultas: &FileManager<Consulta>,
+    codigo_medico: u32,
+) -> Result<Vec<Consulta>, io::Error> {
+    Ok(consultas
+        .read_all_records()?
+        .into_iter()
+        .filter(|c| c.codigo_medico == codigo_medico && c.status.em_aberto())
+        .collect())
+}
+
+pub fn verificar_vaga(
+    diaria_manager: &FileManager<Diaria>,
+    codigo_dia: u32,
+    especialidade: &Especialidade,
+) -> Result<(), ErroAgendamento> {
+    let consultas_do_dia = diaria_manager
+        .read_record((codigo_dia, especialidade.codigo_especialidade))?
+        .map_or(0, |d| d.quantidade_consultas);
+
+    if consultas_do_dia >= especialidade.limite_diario {
+        return Err(ErroAgendamento::SemVagas {
+            limite: especialidade.limite_diario,
+        });
+    }
+    Ok(())
+}
+
+/// Vaga na diária, expediente do médico e conflitos de horário da consulta.
+pub fn verificar_horario(
+    db: &Database,
+    consulta: &Consulta,
+    especialidade: &Especialidade,
+) -> Result<(), ErroAgendamento> {
+    verificar_vaga(&db.diarias, datas::codigo(consulta.data), especialidade)?;
+    verificar_expediente(db, consulta, especialidade.duracao_consulta)?;
+    verificar_conflitos(db, consulta, especialidade.duracao_consulta)
+}
+
+/// Validação completa de uma consulta nova: regras de agendamento e depois
+/// [`verificar_horario`]. Devolve a especialidade do médico da consulta.
+pub fn validar_consulta(
+    db: &Database,
+    regras: &[Box<dyn RegraAgendamento>],
+    consulta: &Consulta,
+    exames: &[Exame],
+) -> Result<Especialidade, ErroAgendamento> {
+    let paciente = db
+        .pacientes
+        .read_record(consulta.codigo_paciente)?
+        .ok_or(ErroAgendamento::PacienteNaoEncontrado(consulta.codigo_paciente))?;
+    let medico = db
+        .medicos
+        .read_record(consulta.codigo_medico)?
+        .ok_or(ErroAgendamento::MedicoNaoEncontrado(consulta.codigo_medico))?;
+    let agendamento = Agendamento {
+        paciente: &paciente,
+        medico: &medico,
+        exames,
+        data: consulta.data,
+    };
+    regras::validar(regras, db, &agendamento)?;
+    let especialidade = db
+        .especialidades
+        .read_record(medico.codigo_especialidade)?
+        .ok_or(ErroAgendamento::EspecialidadeNaoEncontrada(medico.codigo_especialidade))?;
+    verificar_horario(db, consulta, &especialidade)?;
+    Ok(especialidade)
+}
+
+pub fn atualizar_diaria(
+    diaria_manager: &mut FileManager<Diaria>,
+    codigo_dia: u32,
+    codigo_especialidade: u32,
+    incremento: i32,
+) -> Result<(), io::Error> {
+    let chave = (codigo_dia, codigo_especialidade);
+    if let Some(mut diaria) = diaria_manager.read_record(chave)? {
+        diaria.quantidade_consultas = diaria
+            .quantidade_consultas
+            .saturating_add_signed(incremento);
+        diaria_manager.update_record(&diaria, chave)?;
+    } else if incremento > 0 {
+        let nova_diaria = Diaria {
+            codigo_dia,
+            codigo_especialidade,
+            quantidade_consultas: incremento as u32,
+        };
+        diaria_manager.create_record(&nova_diaria, chave)?;
+    }
+    Ok(())
+}
+
+/// Move a consulta para outra data/hora. A vaga no novo dia é reservada antes de
+/// gravar a consulta e a do dia antigo só é liberada depois; se algum passo falhar,
+/// os anteriores são desfeitos para que consulta e diárias continuem coerentes.
+/// As regras de agendamento valem para a nova data (ex.: a idade do paciente).
+pub fn remarcar_consulta(
+    db: &mut Database,
+    regras: &[Box<dyn RegraAgendamento>],
+    codigo_consulta: u32,
+    nova_data: &str,
+    nova_hora: &str,
+) -> Result<Consulta, ErroAgendamento> {
+    let consulta = db
+        .consultas
+        .read_record(codigo_consulta)?
+        .ok_or(ErroAgendamento::ConsultaNaoEncontrada(codigo_consulta))?;
+    if !consulta.status.em_aberto() {
+        return Err(ErroAgendamento::ConsultaEncerrada(consulta.status));
+    }
+    let nova_data = validar_data(nova_data)?;
+    let nova_hora = validar_hora(nova_hora)?;
+
+    let medico = db
+        .medicos
+        .read_record(consulta.codigo_medico)?
+        .ok_or(ErroAgendamento::MedicoNaoEncontrado(consulta.codigo_medico))?;
+    let especialidade = db
+        .especialidades
+        .read_record(medico.codigo_especialidade)?
+        .ok_or(ErroAgendamento::EspecialidadeNaoEncontrada(medico.codigo_especialidade))?;
+    let paciente = db
+        .pacientes
+        .read_record(consulta.codigo_paciente)?
+        .ok_or(ErroAgendamento::PacienteNaoEncontrado(consulta.codigo_paciente))?;
+    let exames = exames_consulta::exames_da_consulta(db, codigo_consulta)?;
+
+    let remarcada = Consulta {
+        data: nova_data,
+        hora: nova_hora.format("%H:%M").to_string(),
+        ..consulta.clone()
+    };
+    let agendamento = Agendamento {
+        paciente: &paciente,
+        medico: &medico,
+        exames: &exames,
+        data: remarcada.data,
+    };
+    regras::validar(regras, db, &agendamento)?;
+    verificar_expediente(db, &remarcada, especialidade.duracao_consulta)?;
+    verificar_conflitos(db, &remarcada, especialidade.duracao_consulta)?;
+
+    if remarcada.data == consulta.data {
+        db.consultas.update_record(&remarcada, codigo_consulta)?;
+        return Ok(remarcada);
+    }
+
+    let dia_novo = datas::codigo(remarcada.data);
+    let dia_antigo = datas::codigo(consulta.data);
+    let codigo_especialidade = especialidade.codigo_especialidade;
+
+    verificar_vaga(&db.diarias, dia_novo, &especialidade)?;
+    atualizar_diaria(&mut db.diarias, dia_novo, codigo_especialidade, 1)?;
+
+    if let Err(e) = db.consultas.update_record(&remarcada, codigo_consulta) {
+        let _ = atualizar_diaria(&mut db.diarias, dia_novo, codigo_especialidade, -1);
+        return Err(e.into());
+    }
+
+    if let Err(e) = atualizar_diaria(&mut db.diarias, dia_antigo, codigo_especialidade, -1) {
+        let _ = db.consultas.update_record(&consulta, codigo_consulta);
+        let _ = atualizar_diaria(&mut db.diarias, dia_novo, codigo_especialidade, -1);
+        return Err(e.into());
+    }
+
+    Ok(remarcada)
+}
+
+/// Consultas que ocupam horário mas têm a hora gravada num formato que não é
+/// `HH:MM` (dados antigos, ex.: "5555"). As verificações de conflito e a busca
+/// de horários livres não conseguem considerá-las, então precisam ser corrigidas
+/// pela opção Alterar Consulta.
+pub fn consultas_com_hora_invalida(db: &Database) -> Result<Vec<Consulta>, io::Error> {
+    Ok(db
+        .consultas
+        .read_all_records()?
+        .into_iter()
+        .filter(|c| c.status.ocupa_horario() && validar_hora(&c.hora).is_err())
+        .collect())
+}
+
+/// Conta as consultas gravadas por (dia, especialidade do médico). Consultas
+/// canceladas ou cujo médico não existe mais ficam de fora da contagem.
+pub fn contar_consultas_por_dia(db: &Database) -> Result<BTreeMap<(u32, u32), u32>, io::Error> {
+    let mut contagem = BTreeMap::new();
+    for consulta in db.consultas.read_all_records()? {
+        if !consulta.status.ocupa_horario() {
+            continue;
+        }
+        let codigo_dia = datas::codigo(consulta.data);
+        let Some(medico) = db.medicos.read_record(consulta.codigo_medico)? else {
+            continue;
+        };
+        *contagem
+            .entry((codigo_dia, medico.codigo_especialidade))
+            .or_insert(0) += 1;
+    }
+    Ok(contagem)
+}
+
+/// Divergência entre o contador gravado em `diarias.dat` e o número de consultas
+/// realmente marcadas. Dias sem diária gravada aparecem com `armazenado` zero.
+#[derive(Debug)]
+pub struct DiferencaDiaria {
+    pub codigo_dia: u32,
+    pub codigo_especialidade: u32,
+    pub armazenado: u32,
+    pub calculado: u32,
+}
+
+/// Compara as diárias gravadas com a contagem feita a partir das consultas e
+/// devolve só as que divergem, ordenadas por dia e especialidade.
+pub fn comparar_diarias(db: &Database) -> Result<Vec<DiferencaDiaria>, io::Error> {
+    let mut contagem: BTreeMap<(u32, u32), (u32, u32)> = contar_consultas_por_dia(db)?
+        .into_iter()
+        .map(|(chave, calculado)| (chave, (0, calculado)))
+        .collect();
+    for diaria in db.diarias.read_all_records()? {
+        contagem.entry(diaria.get_key()).or_insert((0, 0)).0 = diaria.quantidade_consultas;
+    }
+
+    Ok(contagem
+        .into_iter()
+        .filter(|(_, (armazenado, calculado))| armazenado != calculado)
+        .map(
+            |((codigo_dia, codigo_especialidade), (armazenado, calculado))| DiferencaDiaria {
+                codigo_dia,
+                codigo_especialidade,
+                armazenado,
+                calculado,
+            },
+        )
+        .collect())
+}
+
+/// Regrava `diarias.dat` a partir das consultas existentes. Retorna quantas
+/// diárias foram gravadas.
+pub fn reconstruir_diarias(db: &mut Database) -> Result<usize, io::Error> {
+    let diarias: Vec<Diaria> = contar_consultas_por_dia(db)?
+        .into_iter()
+        .map(|((codigo_dia, codigo_especialidade), quantidade_consultas)| Diaria {
+            codigo_dia,
+            codigo_especialidade,
+            quantidade_consultas,
+        })
+        .collect();
+    db.diarias.replace_all(&diarias)?;
+    Ok(diarias.len())
+}
+
+/// Muda o status da consulta, registrando o momento da mudança. Ao cancelar,
+/// a vaga da diária é devolvida; a consulta continua gravada para histórico.
+pub fn alterar_status(
+    db: &mut Database,
+    codigo_consulta: u32,
+    novo: StatusConsulta,
+) -> Result<Consulta, ErroAgendamento> {
+    let mut consulta = db
+        .consultas
+        .read_record(codigo_consulta)?
+        .ok_or(ErroAgendamento::ConsultaNaoEncontrada(codigo_consulta))?;
+    if !consulta.status.pode_mudar_para(novo) {
+        return Err(ErroAgendamento::TransicaoInvalida {
+            de: consulta.status,
+            para: novo,
+        });
+    }
+
+    consulta.status = novo;
+    consulta.historico_status.push(MudancaStatus::agora(novo));
+    db.consultas.update_record(&consulta, codigo_consulta)?;
+
+    if novo == StatusConsulta::Cancelada
+        && let Some(medico) = db.medicos.read_record(consulta.codigo_medico)?
+    {
+        let codigo_dia = datas::codigo(consulta.data);
+        atualizar_diaria(&mut db.diarias, codigo_dia, medico.codigo_especialidade, -1)?;
+    }
+    Ok(consulta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::{medico::Medico, paciente::Paciente};
+
+    // Segunda-feira.
+    fn dia() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2030, 6, 10).unwrap()
+    }
+
+    fn consulta(codigo_consulta: u32, codigo_paciente: u32, hora: &str) -> Consulta {
+        Consulta {
+            codigo_consulta,
+            codigo_paciente,
+            codigo_medico: 1,
+            data: dia(),
+            hora: hora.to_string(),
+            status: StatusConsulta::Agendada,
+            ..Default::default()
+        }
+    }
+
+    /// Pediatria com uma vaga por dia e um médico que atende de manhã nos dias úteis.
+    fn banco() -> Database {
+        let mut db = Database::em_memoria();
+        let especialidade = Especialidade {
+            codigo_especialidade: 1,
+            descricao: "Pediatria".to_string(),
+            limite_diario: 1,
+            duracao_consulta: 30,
+            idade_maxima: Some(17),
+            ..Default::default()
+        };
+        db.especialidades.create_record(&especialidade, 1).unwrap();
+        let medico = Medico {
+            codigo_medico: 1,
+            codigo_especialidade: 1,
+            ..Default::default()
+        };
+        db.medicos.create_record(&medico, 1).unwrap();
+        let agenda = AgendaMedico {
+            codigo_medico: 1,
+            dias_semana: 0b11111,
+            hora_inicio: "08:00".to_string(),
+            hora_fim: "12:00".to_string(),
+            ..Default::default()
+        };
+        db.agendas.create_record(&agenda, 1).unwrap();
+        for (codigo_paciente, ano) in [(1, 2020), (2, 1990)] {
+            let paciente = Paciente {
+                codigo_paciente,
+                data_nascimento: NaiveDate::from_ymd_opt(ano, 1, 1),
+                ..Default::default()
+            };
+            db.pacientes.create_record(&paciente, codigo_paciente).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn validacao_da_consulta_cobre_regras_expediente_e_vagas() {
+        let mut db = banco();
+        let regras = regras::carregar(None).unwrap();
+
+        let adulto = validar_consulta(&db, &regras, &consulta(1, 2, "09:00"), &[]);
+        assert!(matches!(adulto, Err(ErroAgendamento::RegraViolada { .. })));
+        let tarde = validar_consulta(&db, &regras, &consulta(1, 1, "13:00"), &[]);
+        assert!(matches!(tarde, Err(ErroAgendamento::ForaDoExpediente { .. })));
+        let sem_paciente = validar_consulta(&db, &regras, &consulta(1, 3, "09:00"), &[]);
+        assert!(matches!(sem_paciente, Err(ErroAgendamento::PacienteNaoEncontrado(3))));
+
+        let especialidade = validar_consulta(&db, &regras, &consulta(1, 1, "09:00"), &[]).unwrap();
+        assert_eq!(especialidade.codigo_especialidade, 1);
+        db.consultas.create_record(&consulta(1, 1, "09:00"), 1).unwrap();
+        atualizar_diaria(&mut db.diarias, datas::codigo(dia()), 1, 1).unwrap();
+
+        let lotado = validar_consulta(&db, &regras, &consulta(2, 1, "10:00"), &[]);
+        assert!(matches!(lotado, Err(ErroAgendamento::SemVagas { limite: 1 })));
+    }
+
+    #[test]
+    fn remarcar_aplica_as_regras_na_nova_data() {
+        let mut db = banco();
+        let regras = regras::carregar(None).unwrap();
+        db.consultas.create_record(&consulta(1, 1, "09:00"), 1).unwrap();
+        atualizar_diaria(&mut db.diarias, datas::codigo(dia()), 1, 1).unwrap();
+
+        // Em 2038 o paciente terá 18 anos e a pediatria atende só até 17.
+        let erro = remarcar_consulta(&mut db, &regras, 1, "07/06/2038", "09:00");
+        assert!(matches!(erro, Err(ErroAgendamento::RegraViolada { .. })));
+        assert_eq!(db.consultas.read_record(1).unwrap().unwrap().data, dia());
+
+        let remarcada = remarcar_consulta(&mut db, &regras, 1, "11/06/2030", "10:00").unwrap();
+        assert_eq!(remarcada.hora, "10:00");
+        let diaria = |data: NaiveDate| {
+            db.diarias
+                .read_record((datas::codigo(data), 1))
+                .unwrap()
+                .map_or(0, |d| d.quantidade_consultas)
+        };
+        assert_eq!(diaria(dia()), 0);
+        assert_eq!(diaria(dia().succ_opt().unwrap()), 1);
+    }
+}
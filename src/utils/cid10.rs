@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::db::database::Database;
+use crate::db::file_manager::FileManager;
+use crate::structs::{
+    cid::Cid,
+    consulta::StatusConsulta,
+    consulta_diagnostico::ConsultaDiagnostico,
+};
+
+/// Catálogo embutido no programa, carregado na migração: as categorias e
+/// subcategorias mais comuns no atendimento ambulatorial. O catálogo completo
+/// pode ser importado dos arquivos CSV do DATASUS (ver `interpretar_csv`).
+pub const CATALOGO_PADRAO: &str = include_str!("../../recursos/cid10.csv");
+
+#[derive(Debug)]
+pub enum ErroDiagnostico {
+    CodigoInvalido(String),
+    ForaDoCatalogo(String),
+    ConsultaNaoEncontrada(u32),
+    SemAtendimento { codigo_consulta: u32, status: StatusConsulta },
+    Io(io::Error),
+}
+
+impl fmt::Display for ErroDiagnostico {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErroDiagnostico::CodigoInvalido(codigo) => write!(
+                f,
+                "Código de diagnóstico inválido ({}). Use o formato CID-10, ex.: J11 ou J11.1.",
+                codigo
+            ),
+            ErroDiagnostico::ForaDoCatalogo(codigo) => write!(
+                f,
+                "O código {} não está no catálogo CID-10. Importe o catálogo completo pelo menu CID-10.",
+                codigo
+            ),
+            ErroDiagnostico::ConsultaNaoEncontrada(codigo) => {
+                write!(f, "Consulta com código {} não encontrada.", codigo)
+            }
+            ErroDiagnostico::SemAtendimento { codigo_consulta, status } => write!(
+                f,
+                "A consulta {} está com status {}: não houve atendimento para diagnosticar.",
+                codigo_consulta,
+                status.descricao()
+            ),
+            ErroDiagnostico::Io(e) => write!(f, "Falha de acesso aos arquivos: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for ErroDiagnostico {
+    fn from(e: io::Error) -> Self {
+        ErroDiagnostico::Io(e)
+    }
+}
+
+/// Aceita "j11.1", "J111" ou "J11.1" e devolve "J11.1": uma letra, dois
+/// dígitos e, opcionalmente, o dígito da subcategoria.
+pub fn normalizar(codigo: &str) -> Option<String> {
+    let codigo: String = codigo.trim().to_uppercase().chars().filter(|c| *c != '.').collect();
+    let mut chars = codigo.chars();
+    let letra = chars.next()?;
+    let digitos: String = chars.collect();
+    if !letra.is_ascii_uppercase()
+        || !(2..=3).contains(&digitos.len())
+        || !digitos.chars().all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let (categoria, subcategoria) = digitos.split_at(2);
+    if subcategoria.is_empty() {
+        Some(format!("{}{}", letra, categoria))
+    } else {
+        Some(format!("{}{}.{}", letra, categoria, subcategoria))
+    }
+}
+
+/// Código normalizado como número, na ordem do catálogo: a categoria vem logo
+/// antes das suas subcategorias ("J11" = 91100, "J11.1" = 91102).
+pub fn chave(codigo: &str) -> u32 {
+    let mut chars = codigo.chars();
+    let letra = chars.next().map_or(0, |c| (c as u32).saturating_sub('A' as u32));
+    let digitos: Vec<u32> = chars.filter_map(|c| c.to_digit(10)).collect();
+    let categoria = digitos.iter().take(2).fold(0, |acc, d| acc * 10 + d);
+    let subcategoria = digitos.get(2).map_or(0, |d| d + 1);
+    letra * 10000 + categoria * 100 + subcategoria
+}
+
+pub fn buscar_por_codigo(catalogo: &FileManager<Cid>, codigo: &str) -> Result<Option<Cid>, io::Error> {
+    match normalizar(codigo) {
+        Some(codigo) => catalogo.read_record(chave(&codigo)),
+        None => Ok(None),
+    }
+}
+
+/// Normaliza os códigos, confere se estão no catálogo e descarta repetições,
+/// mantendo a ordem informada.
+pub fn validar(catalogo: &FileManager<Cid>, codigos: &[String]) -> Result<Vec<String>, ErroDiagnostico> {
+    let mut validos: Vec<String> = Vec::new();
+    for codigo in codigos {
+        let normalizado =
+            normalizar(codigo).ok_or_else(|| ErroDiagnostico::CodigoInvalido(codigo.clone()))?;
+        if catalogo.read_record(chave(&normalizado))?.is_none() {
+            return Err(ErroDiagnostico::ForaDoCatalogo(normalizado));
+        }
+        if !validos.contains(&normalizado) {
+            validos.push(normalizado);
+        }
+    }
+    Ok(validos)
+}
+
+/// Lê um CSV separado por ";" com cabeçalho. Aceita o formato próprio
+/// (`codigo;descricao`) e os arquivos de categorias (`CAT;...;DESCRICAO`) e
+/// subcategorias (`SUBCAT;...;DESCRICAO`) do DATASUS.
+pub fn interpretar_csv(texto: &str) -> Result<Vec<Cid>, io::Error> {
+    let invalido = |mensagem: String| io::Error::new(io::ErrorKind::InvalidData, mensagem);
+    let campos = |linha: &str| -> Vec<String> {
+        linha.split(';').map(|c| c.trim().trim_matches('"').trim().to_string()).collect()
+    };
+
+    let mut linhas = texto.lines().enumerate().filter(|(_, l)| !l.trim().is_empty());
+    let (_, cabecalho) = linhas.next().ok_or_else(|| invalido("arquivo vazio".to_string()))?;
+    let cabecalho: Vec<String> = campos(cabecalho.trim_start_matches('\u{feff}'))
+        .iter()
+        .map(|c| c.to_lowercase())
+        .collect();
+    let coluna = |nomes: &[&str]| cabecalho.iter().position(|c| nomes.contains(&c.as_str()));
+    let coluna_codigo = coluna(&["codigo", "subcat", "cat"])
+        .ok_or_else(|| invalido("cabeçalho sem coluna de código (codigo, SUBCAT ou CAT)".to_string()))?;
+    let coluna_descricao = coluna(&["descricao"])
+        .ok_or_else(|| invalido("cabeçalho sem coluna DESCRICAO".to_string()))?;
+
+    let mut registros = Vec::new();
+    for (numero, linha) in linhas {
+        let valores = campos(linha);
+        let (Some(codigo), Some(descricao)) = (valores.get(coluna_codigo), valores.get(coluna_descricao)) else {
+            return Err(invalido(format!("linha {}: colunas faltando", numero + 1)));
+        };
+        let codigo = normalizar(codigo)
+            .ok_or_else(|| invalido(format!("linha {}: código inválido: {}", numero + 1, codigo)))?;
+        registros.push(Cid {
+            codigo,
+            descricao: descricao.clone(),
+        });
+    }
+    Ok(registros)
+}
+
+/// Os CSVs do DATASUS vêm em Latin-1; o formato próprio, em UTF-8.
+fn decodificar(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap_or_else(|e| e.into_bytes().iter().map(|b| *b as char).collect())
+}
+
+/// Inclui os códigos novos e atualiza a descrição dos que já existem.
+pub fn importar(catalogo: &mut FileManager<Cid>, registros: &[Cid]) -> Result<usize, io::Error> {
+    for registro in registros {
+        let chave = chave(&registro.codigo);
+        if !catalogo.update_record(registro, chave)? {
+            catalogo.create_record(registro, chave)?;
+        }
+    }
+    catalogo.sync()?;
+    Ok(registros.len())
+}
+
+pub fn importar_arquivo(catalogo: &mut FileManager<Cid>, caminho: &Path) -> Result<usize, io::Error> {
+    let registros = interpretar_csv(&decodificar(fs::read(caminho)?))?;
+    importar(catalogo, &registros)
+}
+
+/// Códigos dos diagnósticos da consulta, na ordem do catálogo.
+pub fn codigos_da_consulta(db: &Database, codigo_consulta: u32) -> Result<Vec<String>, io::Error> {
+    let mut itens: Vec<ConsultaDiagnostico> = db
+        .consulta_diagnosticos
+        .read_all_records()?
+        .into_iter()
+        .filter(|item| item.codigo_consulta == codigo_consulta)
+        .collect();
+    itens.sort_by_key(|item| chave(&item.codigo_cid));
+    Ok(itens.into_iter().map(|item| item.codigo_cid).collect())
+}
+
+/// Diagnósticos da consulta com a descrição do catálogo.
+pub fn diagnosticos_da_consulta(db: &Database, codigo_consulta: u32) -> Result<Vec<Cid>, io::Error> {
+    let mut diagnosticos = Vec::new();
+    for codigo in codigos_da_consulta(db, codigo_consulta)? {
+        let cid = db.cids.read_record(chave(&codigo))?.unwrap_or(Cid {
+            codigo,
+            descricao: "Não encontrado no catálogo".to_string(),
+        });
+        diagnosticos.push(cid);
+    }
+    Ok(diagnosticos)
+}
+
+/// Faz com que a consulta tenha exatamente os diagnósticos em `codigos`.
+pub fn definir_diagnosticos(
+    db: &mut Database,
+    codigo_consulta: u32,
+    codigos: &[String],
+) -> Result<Vec<String>, ErroDiagnostico> {
+    let consulta = db
+        .consultas
+        .read_record(codigo_consulta)?
+        .ok_or(ErroDiagnostico::ConsultaNaoEncontrada(codigo_consulta))?;
+    if matches!(consulta.status, StatusConsulta::Cancelada | StatusConsulta::Faltou) {
+        return Err(ErroDiagnostico::SemAtendimento {
+            codigo_consulta,
+            status: consulta.status,
+        });
+    }
+    let codigos = validar(&db.cids, codigos)?;
+
+    for atual in codigos_da_consulta(db, codigo_consulta)? {
+        if !codigos.contains(&atual) {
+            db.consulta_diagnosticos.delete_record((codigo_consulta, chave(&atual)))?;
+        }
+    }
+    anexar_diagnosticos(db, codigo_consulta, &codigos)?;
+    Ok(codigos)
+}
+
+/// Acrescenta diagnósticos já validados à consulta, sem tirar os que ela tem.
+pub fn anexar_diagnosticos(db: &mut Database, codigo_consulta: u32, codigos: &[String]) -> Result<(), io::Error> {
+    let atuais = codigos_da_consulta(db, codigo_consulta)?;
+    for codigo in codigos {
+        if !atuais.contains(codigo) {
+            let item = ConsultaDiagnostico {
+                codigo_consulta,
+                codigo_cid: codigo.clone(),
+            };
+            db.consulta_diagnosticos.create_record(&item, (codigo_consulta, chave(codigo)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Quantas consultas de cada especialidade tiveram cada diagnóstico no período,
+/// do mais frequente para o menos frequente. Consultas canceladas não contam.
+pub fn diagnosticos_frequentes(
+    db: &Database,
+    inicio: NaiveDate,
+    fim: NaiveDate,
+) -> Result<BTreeMap<String, Vec<(String, u32)>>, io::Error> {
+    let consultas: BTreeMap<u32, u32> = db
+        .consultas
+        .read_all_records()?
+        .into_iter()
+        .filter(|c| c.data >= inicio && c.data <= fim && c.status != StatusConsulta::Cancelada)
+        .map(|c| (c.codigo_consulta, c.codigo_medico))
+        .collect();
+
+    let mut contagem: BTreeMap<String, BTreeMap<String, u32>> = BTreeMap::new();
+    for item in db.consulta_diagnosticos.read_all_records()? {
+        let Some(codigo_medico) = consultas.get(&item.codigo_consulta) else {
+            continue;
+        };
+        let especialidade = db
+            .medicos
+            .read_record(*codigo_medico)?
+            .and_then(|m| db.especialidades.read_record(m.codigo_especialidade).unwrap_or(None))
+            .map_or("Especialidade não encontrada".to_string(), |e| e.descricao);
+        *contagem.entry(especialidade).or_default().entry(item.codigo_cid).or_insert(0) += 1;
+    }
+
+    Ok(contagem
+        .into_iter()
+        .map(|(especialidade, por_codigo)| {
+            let mut ranking: Vec<(String, u32)> = por_codigo.into_iter().collect();
+            ranking.sort_by(|a, b| b.1.cmp(&a.1).then(chave(&a.0).cmp(&chave(&b.0))));
+            (especialidade, ranking)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::{consulta::Consulta, especialidade::Especialidade, medico::Medico};
+
+    fn codigos(registros: &[Cid]) -> Vec<(&str, &str)> {
+        registros.iter().map(|c| (c.codigo.as_str(), c.descricao.as_str())).collect()
+    }
+
+    #[test]
+    fn normaliza_os_formatos_aceitos() {
+        for (entrada, esperado) in [
+            ("J11", Some("J11")),
+            ("j11.1", Some("J11.1")),
+            ("J111", Some("J11.1")),
+            (" a09 ", Some("A09")),
+            ("Z99.9", Some("Z99.9")),
+            ("J1", None),
+            ("J1111", None),
+            ("11.1", None),
+            ("JJ1", None),
+            ("", None),
+        ] {
+            assert_eq!(normalizar(entrada).as_deref(), esperado, "{}", entrada);
+        }
+    }
+
+    #[test]
+    fn chave_segue_a_ordem_do_catalogo_sem_repetir() {
+        let ordenados = ["A00", "A00.0", "A00.9", "A01", "J11", "J11.0", "J11.1", "J11.9", "J12", "Z99", "Z99.9"];
+        let chaves: Vec<u32> = ordenados.iter().map(|c| chave(c)).collect();
+        assert!(chaves.windows(2).all(|par| par[0] < par[1]), "{:?}", chaves);
+        assert_eq!(chave("J11"), 91100);
+        assert_eq!(chave("J11.0"), 91101);
+        assert_eq!(chave("J11.1"), 91102);
+    }
+
+    #[test]
+    fn interpreta_o_formato_proprio_e_os_do_datasus() {
+        let proprio = "\u{feff}codigo;descricao\nj11.1;Influenza com outras manifestações respiratórias\n\nI10;\"Hipertensão essencial\"\n";
+        assert_eq!(
+            codigos(&interpretar_csv(proprio).unwrap()),
+            [
+                ("J11.1", "Influenza com outras manifestações respiratórias"),
+                ("I10", "Hipertensão essencial")
+            ]
+        );
+
+        let categorias = "CAT;CLASSIF;DESCRICAO;DESCRABREV;REFER;EXCLUIDOS\nA00;;Cólera;A00   Cólera;;\n";
+        assert_eq!(codigos(&interpretar_csv(categorias).unwrap()), [("A00", "Cólera")]);
+
+        let subcategorias = "SUBCAT;CLASSIF;RESTRSEXO;CAUSAOBITO;DESCRICAO;DESCRABREV;REFER;EXCLUIDOS\nA000;;;;Cólera devida a Vibrio cholerae 01, biótipo cholerae;;;\n";
+        assert_eq!(
+            codigos(&interpretar_csv(subcategorias).unwrap()),
+            [("A00.0", "Cólera devida a Vibrio cholerae 01, biótipo cholerae")]
+        );
+
+        // Os arquivos do DATASUS vêm em Latin-1: "Cólera" com ó = 0xF3.
+        let latin1 = b"CAT;DESCRICAO\nA00;C\xf3lera\n".to_vec();
+        assert_eq!(codigos(&interpretar_csv(&decodificar(latin1)).unwrap()), [("A00", "Cólera")]);
+
+        for invalido in ["", "codigo;nome\nA00;Cólera", "codigo;descricao\nA00", "codigo;descricao\nXYZ;Nada"] {
+            let erro = interpretar_csv(invalido).unwrap_err();
+            assert_eq!(erro.kind(), io::ErrorKind::InvalidData, "{}", invalido);
+        }
+    }
+
+    #[test]
+    fn diagnosticos_frequentes_por_especialidade_no_periodo() {
+        let mut db = Database::em_memoria();
+        for (codigo, descricao) in [(1, "Clínica Geral"), (2, "Pediatria")] {
+            let especialidade = Especialidade {
+                codigo_especialidade: codigo,
+                descricao: descricao.to_string(),
+                ..Default::default()
+            };
+            db.especialidades.create_record(&especialidade, codigo).unwrap();
+            let medico = Medico {
+                codigo_medico: codigo,
+                codigo_especialidade: codigo,
+                ..Default::default()
+            };
+            db.medicos.create_record(&medico, codigo).unwrap();
+        }
+        let dia = |d: u32| NaiveDate::from_ymd_opt(2025, 5, d).unwrap();
+        // (consulta, médico, dia, status, diagnósticos)
+        let consultas = [
+            (1, 1, 5, StatusConsulta::Realizada, vec!["J11", "I10"]),
+            (2, 1, 6, StatusConsulta::Realizada, vec!["I10"]),
+            (3, 1, 7, StatusConsulta::Realizada, vec!["J11"]),
+            (4, 1, 8, StatusConsulta::Cancelada, vec!["J11"]),
+            (5, 1, 30, StatusConsulta::Realizada, vec!["J11"]),
+            (6, 2, 6, StatusConsulta::Realizada, vec!["J06.9"]),
+            (7, 9, 6, StatusConsulta::Realizada, vec!["A09"]),
+        ];
+        for (codigo_consulta, codigo_medico, d, status, diagnosticos) in consultas {
+            let consulta = Consulta {
+                codigo_consulta,
+                codigo_medico,
+                data: dia(d),
+                status,
+                ..Default::default()
+            };
+            db.consultas.create_record(&consulta, codigo_consulta).unwrap();
+            let diagnosticos: Vec<String> = diagnosticos.into_iter().map(String::from).collect();
+            anexar_diagnosticos(&mut db, codigo_consulta, &diagnosticos).unwrap();
+        }
+
+        let frequentes = diagnosticos_frequentes(&db, dia(1), dia(10)).unwrap();
+        let par = |codigo: &str, quantidade| (codigo.to_string(), quantidade);
+        assert_eq!(frequentes["Clínica Geral"], vec![par("I10", 2), par("J11", 2)]);
+        assert_eq!(frequentes["Pediatria"], vec![par("J06.9", 1)]);
+        assert_eq!(frequentes["Especialidade não encontrada"], vec![par("A09", 1)]);
+        assert_eq!(frequentes.len(), 3);
+
+        let maio_inteiro = diagnosticos_frequentes(&db, dia(1), dia(31)).unwrap();
+        assert_eq!(maio_inteiro["Clínica Geral"], vec![par("J11", 3), par("I10", 2)]);
+        assert!(diagnosticos_frequentes(&db, dia(11), dia(29)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,434 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::db::file_manager::{Entity, FileManager};
+use crate::structs::medicao::Medicao;
+use crate::structs::paciente::{Paciente, Sexo};
+
+/// Faixas aceitas na digitação. Valores fora delas quase sempre são erro de
+/// unidade (ex.: altura em centímetros ou peso em gramas).
+pub const PESO_MINIMO: f32 = 0.3;
+pub const PESO_MAXIMO: f32 = 400.0;
+pub const ALTURA_MINIMA: f32 = 0.2;
+pub const ALTURA_MAXIMA: f32 = 2.6;
+
+/// Diretório, relativo ao diretório atual, onde tabelas de IMC por idade da OMS
+/// podem substituir as embutidas no programa.
+pub const DIRETORIO_REFERENCIA: &str = "referencias_oms";
+const ARQUIVO_MENINOS: &str = "imc_idade_meninos.txt";
+const ARQUIVO_MENINAS: &str = "imc_idade_meninas.txt";
+const TABELA_MENINOS: &str = include_str!("../../recursos/imc_idade_meninos.txt");
+const TABELA_MENINAS: &str = include_str!("../../recursos/imc_idade_meninas.txt");
+
+/// A curva de IMC por idade da OMS vai até os 19 anos (228 meses); depois
+/// vale a classificação de adultos.
+const MESES_CURVA: f64 = 228.0;
+/// Até aqui valem os padrões de crescimento de 0 a 5 anos, com pontos de corte próprios.
+const MESES_PRIMEIRA_INFANCIA: f64 = 60.0;
+/// Conversão de dias para meses usada pela OMS nas tabelas.
+const DIAS_POR_MES: f64 = 30.4375;
+
+#[derive(Debug)]
+pub enum ErroMedida {
+    PesoForaDaFaixa(f32),
+    AlturaForaDaFaixa(f32),
+}
+
+impl fmt::Display for ErroMedida {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErroMedida::PesoForaDaFaixa(peso) => write!(
+                f,
+                "Peso fora da faixa aceita ({:.2} kg). Informe entre {} e {} kg.",
+                peso, PESO_MINIMO, PESO_MAXIMO
+            ),
+            ErroMedida::AlturaForaDaFaixa(altura) => write!(
+                f,
+                "Altura fora da faixa aceita ({:.2} m). Informe em metros, entre {} e {}.",
+                altura, ALTURA_MINIMA, ALTURA_MAXIMA
+            ),
+        }
+    }
+}
+
+pub fn validar_medidas(peso: f32, altura: f32) -> Result<(), ErroMedida> {
+    if !(PESO_MINIMO..=PESO_MAXIMO).contains(&peso) {
+        return Err(ErroMedida::PesoForaDaFaixa(peso));
+    }
+    if !(ALTURA_MINIMA..=ALTURA_MAXIMA).contains(&altura) {
+        return Err(ErroMedida::AlturaForaDaFaixa(altura));
+    }
+    Ok(())
+}
+
+/// `None` quando a altura não é positiva (ex.: pacientes antigos sem medida).
+pub fn imc(peso: f32, altura: f32) -> Option<f32> {
+    (altura > 0.0).then(|| peso / (altura * altura))
+}
+
+/// Classificação da OMS para adultos.
+pub fn classificar_adulto(imc: f32) -> &'static str {
+    match imc {
+        _ if imc < 18.5 => "Abaixo do peso",
+        _ if imc < 25.0 => "Peso normal",
+        _ if imc < 30.0 => "Sobrepeso",
+        _ if imc < 35.0 => "Obesidade grau I",
+        _ if imc < 40.0 => "Obesidade grau II",
+        _ => "Obesidade grau III",
+    }
+}
+
+/// Pontos de corte da OMS sobre o escore-z do IMC para a idade. Até os 5
+/// anos há a faixa de risco de sobrepeso e os cortes ficam um desvio acima.
+fn classificar_pediatrico(escore_z: f64, meses: f64) -> &'static str {
+    let primeira_infancia = meses <= MESES_PRIMEIRA_INFANCIA;
+    match escore_z {
+        z if z < -3.0 => "Magreza acentuada",
+        z if z < -2.0 => "Magreza",
+        z if primeira_infancia && z > 3.0 => "Obesidade",
+        z if primeira_infancia && z > 2.0 => "Sobrepeso",
+        z if primeira_infancia && z > 1.0 => "Risco de sobrepeso",
+        z if z > 2.0 => "Obesidade",
+        z if z > 1.0 => "Sobrepeso",
+        _ => "Eutrofia",
+    }
+}
+
+/// Parâmetros L (Box-Cox), M (mediana) e S (coeficiente de variação) de uma idade.
+#[derive(Debug, Clone, Copy)]
+struct Lms {
+    meses: f64,
+    l: f64,
+    m: f64,
+    s: f64,
+}
+
+impl Lms {
+    /// Valor da curva no escore-z `z`.
+    fn valor(&self, z: f64) -> f64 {
+        if self.l == 0.0 {
+            self.m * (self.s * z).exp()
+        } else {
+            self.m * (1.0 + self.l * self.s * z).powf(1.0 / self.l)
+        }
+    }
+
+    /// Escore-z pelo método LMS. Além de ±3, a OMS mede a distância em
+    /// unidades do intervalo entre 2 e 3 desvios, porque a cauda da curva
+    /// Box-Cox cresce rápido demais.
+    fn escore_z(&self, imc: f64) -> f64 {
+        let z = if self.l == 0.0 {
+            (imc / self.m).ln() / self.s
+        } else {
+            ((imc / self.m).powf(self.l) - 1.0) / (self.l * self.s)
+        };
+        if z > 3.0 {
+            let sd3 = self.valor(3.0);
+            3.0 + (imc - sd3) / (sd3 - self.valor(2.0))
+        } else if z < -3.0 {
+            let sd3 = self.valor(-3.0);
+            -3.0 + (imc - sd3) / (self.valor(-2.0) - sd3)
+        } else {
+            z
+        }
+    }
+}
+
+/// Tabelas LMS de IMC por idade da OMS, por sexo.
+pub struct ReferenciaImc {
+    meninos: Vec<Lms>,
+    meninas: Vec<Lms>,
+}
+
+impl ReferenciaImc {
+    /// Lê `imc_idade_meninos.txt` e `imc_idade_meninas.txt` de `diretorio` e,
+    /// para o arquivo que não existir, usa a tabela embutida em `recursos/`.
+    /// São as tabelas da OMS em texto separado por tabulação: uma linha de
+    /// cabeçalho com `Day` (padrões de 0 a 5 anos) ou `Month` (referência de 5
+    /// a 19 anos) na primeira coluna e as colunas `L`, `M` e `S`. As duas
+    /// tabelas podem ser concatenadas no mesmo arquivo, cada uma com seu
+    /// cabeçalho. Linhas iniciadas por `#` são comentários.
+    pub fn carregar(diretorio: &Path) -> Result<ReferenciaImc, io::Error> {
+        Ok(ReferenciaImc {
+            meninos: ler_tabela(&diretorio.join(ARQUIVO_MENINOS), TABELA_MENINOS)?,
+            meninas: ler_tabela(&diretorio.join(ARQUIVO_MENINAS), TABELA_MENINAS)?,
+        })
+    }
+
+    /// Interpola entre as duas linhas vizinhas. `None` fora da faixa da tabela.
+    fn lms(&self, sexo: Sexo, meses: f64) -> Option<Lms> {
+        let tabela = match sexo {
+            Sexo::Masculino => &self.meninos,
+            Sexo::Feminino => &self.meninas,
+        };
+        let depois = tabela.partition_point(|lms| lms.meses < meses);
+        let b = tabela.get(depois)?;
+        if b.meses == meses || depois == 0 {
+            return (b.meses == meses).then_some(*b);
+        }
+        let a = tabela[depois - 1];
+        let t = (meses - a.meses) / (b.meses - a.meses);
+        Some(Lms {
+            meses,
+            l: a.l + (b.l - a.l) * t,
+            m: a.m + (b.m - a.m) * t,
+            s: a.s + (b.s - a.s) * t,
+        })
+    }
+}
+
+/// Lê a tabela de `caminho` ou, se o arquivo não existir, interpreta `padrao`.
+fn ler_tabela(caminho: &Path, padrao: &str) -> Result<Vec<Lms>, io::Error> {
+    match fs::read_to_string(caminho) {
+        Ok(texto) => interpretar_tabela(&caminho.display().to_string(), &texto),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            interpretar_tabela("tabela embutida", padrao)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn interpretar_tabela(origem: &str, texto: &str) -> Result<Vec<Lms>, io::Error> {
+    let invalido = |linha: usize, mensagem: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}, linha {}: {}", origem, linha, mensagem),
+        )
+    };
+
+    // (divisor da idade para chegar a meses, colunas de L, M e S)
+    let mut colunas: Option<(f64, [usize; 3])> = None;
+    let mut tabela = Vec::new();
+    for (i, linha) in texto.lines().enumerate() {
+        let campos: Vec<&str> = linha.split_whitespace().collect();
+        let Some(primeiro) = campos.first() else {
+            continue;
+        };
+        if primeiro.starts_with('#') {
+            continue;
+        }
+
+        let Ok(idade) = primeiro.parse::<f64>() else {
+            let divisor = match primeiro.to_lowercase().as_str() {
+                "day" => DIAS_POR_MES,
+                "month" => 1.0,
+                _ => return Err(invalido(i + 1, "a primeira coluna deve ser Day ou Month")),
+            };
+            let posicao = |nome: &str| {
+                campos
+                    .iter()
+                    .position(|c| c.eq_ignore_ascii_case(nome))
+                    .ok_or_else(|| invalido(i + 1, &format!("coluna {} não encontrada", nome)))
+            };
+            colunas = Some((divisor, [posicao("L")?, posicao("M")?, posicao("S")?]));
+            continue;
+        };
+
+        let (divisor, [l, m, s]) =
+            colunas.ok_or_else(|| invalido(i + 1, "dados antes do cabeçalho"))?;
+        let valor = |coluna: usize| {
+            campos
+                .get(coluna)
+                .and_then(|c| c.parse::<f64>().ok())
+                .ok_or_else(|| invalido(i + 1, "valor ausente ou inválido"))
+        };
+        tabela.push(Lms {
+            meses: idade / divisor,
+            l: valor(l)?,
+            m: valor(m)?,
+            s: valor(s)?,
+        });
+    }
+
+    tabela.sort_by(|a, b| a.meses.total_cmp(&b.meses));
+    tabela.dedup_by(|b, a| a.meses == b.meses);
+    Ok(tabela)
+}
+
+/// Probabilidade acumulada da normal padrão (Abramowitz e Stegun 7.1.26,
+/// erro menor que 1,5e-7), em porcentagem.
+fn percentil(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poli = t
+        * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poli * (-x * x).exp();
+    let acumulada = if z >= 0.0 { 0.5 * (1.0 + erf) } else { 0.5 * (1.0 - erf) };
+    acumulada * 100.0
+}
+
+pub enum AvaliacaoImc {
+    /// Altura zerada: o IMC não pode ser calculado.
+    SemMedida,
+    Adulto {
+        imc: f32,
+        classificacao: &'static str,
+    },
+    Pediatrica {
+        imc: f32,
+        escore_z: f64,
+        percentil: f64,
+        classificacao: &'static str,
+    },
+    /// Criança ou adolescente que não pôde ser comparado com a curva da OMS.
+    PediatricaSemReferencia { imc: f32, motivo: &'static str },
+}
+
+impl fmt::Display for AvaliacaoImc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AvaliacaoImc::SemMedida => write!(f, "IMC: não calculado (altura não informada)"),
+            AvaliacaoImc::Adulto { imc, classificacao } => {
+                write!(f, "IMC: {:.2}\nDiagnóstico: {}", imc, classificacao)
+            }
+            AvaliacaoImc::Pediatrica {
+                imc,
+                escore_z,
+                percentil,
+                classificacao,
+            } => write!(
+                f,
+                "IMC: {:.2} (escore-z {:.2}, percentil {:.1})\nDiagnóstico: {} (IMC para a idade, OMS)",
+                imc, escore_z, percentil, classificacao
+            ),
+            AvaliacaoImc::PediatricaSemReferencia { imc, motivo } => write!(
+                f,
+                "IMC: {:.2}\nDiagnóstico: não classificado, {}",
+                imc, motivo
+            ),
+        }
+    }
+}
+
+/// Classifica a medição pela idade do paciente na data em que foi feita:
+/// até 19 anos pela curva de IMC por idade da OMS, depois pelos cortes de
+/// adulto. Pacientes sem data de nascimento são tratados como adultos, como
+/// antes de a idade ser considerada.
+pub fn avaliar(paciente: &Paciente, medicao: &Medicao, referencia: &ReferenciaImc) -> AvaliacaoImc {
+    let Some(imc) = imc(medicao.peso, medicao.altura) else {
+        return AvaliacaoImc::SemMedida;
+    };
+    let Some(meses) = idade_em_meses(paciente, medicao.data) else {
+        return AvaliacaoImc::Adulto {
+            imc,
+            classificacao: classificar_adulto(imc),
+        };
+    };
+    if meses > MESES_CURVA {
+        return AvaliacaoImc::Adulto {
+            imc,
+            classificacao: classificar_adulto(imc),
+        };
+    }
+
+    let Some(sexo) = paciente.sexo else {
+        return AvaliacaoImc::PediatricaSemReferencia {
+            imc,
+            motivo: "sexo não cadastrado",
+        };
+    };
+    let Some(lms) = referencia.lms(sexo, meses) else {
+        return AvaliacaoImc::PediatricaSemReferencia {
+            imc,
+            motivo: "idade fora das tabelas da OMS carregadas",
+        };
+    };
+
+    let escore_z = lms.escore_z(imc as f64);
+    AvaliacaoImc::Pediatrica {
+        imc,
+        escore_z,
+        percentil: percentil(escore_z),
+        classificacao: classificar_pediatrico(escore_z, meses),
+    }
+}
+
+/// Idade em meses (fracionários, como nas tabelas da OMS) na data `em`.
+fn idade_em_meses(paciente: &Paciente, em: NaiveDate) -> Option<f64> {
+    let nascimento = paciente.data_nascimento?;
+    let dias = (em - nascimento).num_days();
+    (dias >= 0).then(|| dias as f64 / DIAS_POR_MES)
+}
+
+/// Medições do paciente, da mais antiga para a mais recente.
+pub fn medicoes_do_paciente(
+    medicoes: &FileManager<Medicao>,
+    codigo_paciente: u32,
+) -> Result<Vec<Medicao>, io::Error> {
+    let mut medicoes: Vec<Medicao> = medicoes
+        .read_all_records()?
+        .into_iter()
+        .filter(|m| m.codigo_paciente == codigo_paciente)
+        .collect();
+    medicoes.sort_by_key(|m| m.data);
+    Ok(medicoes)
+}
+
+/// Grava a medição; outra do mesmo paciente no mesmo dia é substituída.
+pub fn registrar_medicao(
+    medicoes: &mut FileManager<Medicao>,
+    medicao: &Medicao,
+) -> Result<(), io::Error> {
+    let chave = medicao.get_key();
+    if !medicoes.update_record(medicao, chave)? {
+        medicoes.create_record(medicao, chave)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crianca(sexo: Sexo, nascimento: NaiveDate) -> Paciente {
+        Paciente {
+            codigo_paciente: 1,
+            data_nascimento: Some(nascimento),
+            sexo: Some(sexo),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tabelas_embutidas_classificam_sem_arquivos_externos() {
+        let referencia = ReferenciaImc::carregar(Path::new("diretorio-que-nao-existe")).unwrap();
+        let nascimento = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+
+        // Menino de 10 anos na mediana da curva: escore-z perto de zero.
+        let medicao = Medicao {
+            codigo_paciente: 1,
+            data: NaiveDate::from_ymd_opt(2030, 1, 1).unwrap(),
+            peso: 16.461 * 1.4 * 1.4,
+            altura: 1.4,
+        };
+        match avaliar(&crianca(Sexo::Masculino, nascimento), &medicao, &referencia) {
+            AvaliacaoImc::Pediatrica {
+                escore_z,
+                classificacao,
+                ..
+            } => {
+                assert!(escore_z.abs() < 0.05, "escore-z {}", escore_z);
+                assert_eq!(classificacao, "Eutrofia");
+            }
+            outra => panic!("esperava avaliação pediátrica, veio {}", outra),
+        }
+
+        // Menina de 1 ano com IMC 23: acima de +3 desvios na primeira infância.
+        let medicao = Medicao {
+            codigo_paciente: 1,
+            data: NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+            peso: 23.0 * 0.75 * 0.75,
+            altura: 0.75,
+        };
+        match avaliar(&crianca(Sexo::Feminino, nascimento), &medicao, &referencia) {
+            AvaliacaoImc::Pediatrica { classificacao, .. } => {
+                assert_eq!(classificacao, "Obesidade")
+            }
+            outra => panic!("esperava avaliação pediátrica, veio {}", outra),
+        }
+    }
+}
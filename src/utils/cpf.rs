@@ -0,0 +1,39 @@
+/// Remove pontuação e confere os dígitos verificadores. Retorna os 11 dígitos
+/// do CPF, ou `None` se ele for inválido. Sequências repetidas ("111.111.111-11")
+/// passam no cálculo, mas não são CPFs emitidos, então também são recusadas.
+pub fn normalizar(cpf: &str) -> Option<String> {
+    let digitos: Vec<u32> = cpf
+        .chars()
+        .filter(|c| !matches!(c, '.' | '-' | ' '))
+        .map(|c| c.to_digit(10))
+        .collect::<Option<_>>()?;
+
+    if digitos.len() != 11 || digitos.iter().all(|d| *d == digitos[0]) {
+        return None;
+    }
+    if digito_verificador(&digitos[..9]) != digitos[9]
+        || digito_verificador(&digitos[..10]) != digitos[10]
+    {
+        return None;
+    }
+    Some(digitos.iter().map(|d| char::from_digit(*d, 10).unwrap()).collect())
+}
+
+/// Pesos decrescentes a partir de `len + 1`; resto 10 vira 0.
+fn digito_verificador(digitos: &[u32]) -> u32 {
+    let peso_inicial = digitos.len() as u32 + 1;
+    let soma: u32 = digitos
+        .iter()
+        .enumerate()
+        .map(|(i, d)| d * (peso_inicial - i as u32))
+        .sum();
+    (soma * 10 % 11) % 10
+}
+
+/// "12345678909" -> "123.456.789-09". Valores fora do padrão voltam como estão.
+pub fn formatar(cpf: &str) -> String {
+    if cpf.len() != 11 || !cpf.chars().all(|c| c.is_ascii_digit()) {
+        return cpf.to_string();
+    }
+    format!("{}.{}.{}-{}", &cpf[0..3], &cpf[3..6], &cpf[6..9], &cpf[9..11])
+}
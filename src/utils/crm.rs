@@ -0,0 +1,39 @@
+/// Unidades da federação aceitas como UF do CRM.
+const UFS: [&str; 27] = [
+    "AC", "AL", "AM", "AP", "BA", "CE", "DF", "ES", "GO", "MA", "MG", "MS", "MT", "PA", "PB",
+    "PE", "PI", "PR", "RJ", "RN", "RO", "RR", "RS", "SC", "SE", "SP", "TO",
+];
+
+const MAX_DIGITOS: usize = 6;
+
+/// Confere o número (1 a 6 dígitos, sem zeros à esquerda no resultado) e a
+/// UF. Retorna `(numero, uf)` normalizados ou `None` se algum for inválido.
+pub fn normalizar(numero: &str, uf: &str) -> Option<(String, String)> {
+    let numero = numero.trim().trim_start_matches('0');
+    if numero.is_empty()
+        || numero.len() > MAX_DIGITOS
+        || !numero.chars().all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let uf = uf.trim().to_uppercase();
+    if !UFS.contains(&uf.as_str()) {
+        return None;
+    }
+    Some((numero.to_string(), uf))
+}
+
+/// Chave usada na unicidade e na busca: "123456/SP".
+pub fn chave(numero: &str, uf: &str) -> String {
+    format!("{}/{}", numero, uf)
+}
+
+/// "CRM/SP 123456", ou "Não cadastrado" para médicos antigos sem CRM.
+pub fn formatar(numero: &str, uf: &str) -> String {
+    if numero.is_empty() {
+        "Não cadastrado".to_string()
+    } else {
+        format!("CRM/{} {}", uf, numero)
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::{Datelike, NaiveDate};
+use std::io;
+use std::mem::size_of;
+
+/// Formatos aceitos na digitação. Os registros antigos guardavam a data como
+/// texto livre e trazem os dois.
+const FORMATOS: [&str; 2] = ["%d/%m/%Y", "%Y%m%d"];
+
+/// Gravada no lugar do tamanho do texto que os registros antigos traziam.
+/// Registros novos guardam `MARCA_DATA` seguida do código AAAAMMDD (0 = sem
+/// data); nos antigos vem o tamanho e o texto, interpretado na leitura.
+const MARCA_DATA: u32 = u32::MAX;
+
+pub fn interpretar(texto: &str) -> Option<NaiveDate> {
+    let texto = texto.trim();
+    FORMATOS
+        .iter()
+        .find_map(|formato| NaiveDate::parse_from_str(texto, formato).ok())
+}
+
+/// AAAAMMDD como número, a forma usada em `Diaria::codigo_dia` e
+/// `AgendaMedico::datas_bloqueadas`.
+pub fn codigo(data: NaiveDate) -> u32 {
+    data.year() as u32 * 10000 + data.month() * 100 + data.day()
+}
+
+pub fn de_codigo(codigo: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt((codigo / 10000) as i32, codigo / 100 % 100, codigo % 100)
+}
+
+/// "18/02/2005", ou "Não informada".
+pub fn formatar(data: Option<NaiveDate>) -> String {
+    data.map_or("Não informada".to_string(), |d| d.format("%d/%m/%Y").to_string())
+}
+
+pub fn gravar(bytes: &mut Vec<u8>, data: Option<NaiveDate>) {
+    bytes.extend_from_slice(&MARCA_DATA.to_le_bytes());
+    bytes.extend_from_slice(&data.map_or(0, codigo).to_le_bytes());
+}
+
+/// Lê uma data gravada por `gravar` ou, em registros antigos, o texto no
+/// lugar dela. Texto que não bate com nenhum formato vira `None`.
+pub fn ler(bytes: &[u8], cursor: &mut usize) -> Result<Option<NaiveDate>, io::Error> {
+    let tamanho = ler_u32(bytes, cursor)?;
+    if tamanho == MARCA_DATA {
+        return Ok(de_codigo(ler_u32(bytes, cursor)?));
+    }
+
+    let texto = ler_texto(bytes, *cursor, tamanho)?;
+    *cursor += tamanho as usize;
+    Ok(interpretar(&texto))
+}
+
+/// O texto que os registros antigos guardavam na posição `cursor`, sem
+/// interpretar; `None` quando ali já está uma data gravada por `gravar`.
+pub fn texto_antigo(bytes: &[u8], cursor: usize) -> Result<Option<String>, io::Error> {
+    let mut cursor = cursor;
+    let tamanho = ler_u32(bytes, &mut cursor)?;
+    if tamanho == MARCA_DATA {
+        return Ok(None);
+    }
+    ler_texto(bytes, cursor, tamanho).map(Some)
+}
+
+fn truncado() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "registro truncado no campo de data")
+}
+
+fn ler_u32(bytes: &[u8], cursor: &mut usize) -> Result<u32, io::Error> {
+    let fim = cursor.checked_add(size_of::<u32>()).ok_or_else(truncado)?;
+    let campo = bytes.get(*cursor..fim).ok_or_else(truncado)?;
+    *cursor = fim;
+    Ok(u32::from_le_bytes(campo.try_into().unwrap()))
+}
+
+fn ler_texto(bytes: &[u8], inicio: usize, tamanho: u32) -> Result<String, io::Error> {
+    let fim = inicio.checked_add(tamanho as usize).ok_or_else(truncado)?;
+    let texto = bytes.get(inicio..fim).ok_or_else(truncado)?;
+    String::from_utf8(texto.to_vec()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,88 @@
+use crate::structs::{
+    cid::Cid, cidade::Cidade, consulta::Consulta, consulta_diagnostico::ConsultaDiagnostico,
+    consulta_exame::ConsultaExame, diaria::Diaria, especialidade::Especialidade, exame::Exame,
+    medicao::Medicao, medico::Medico, paciente::Paciente, prontuario::Prontuario,
+};
+use crate::utils::{crm, datas};
+
+// Uma linha por registro, usada nas buscas dos menus e na pesquisa pela linha de comando.
+
+pub fn paciente(p: &Paciente) -> String {
+    format!("{} - {} (nascimento: {})", p.codigo_paciente, p.nome, datas::formatar(p.data_nascimento))
+}
+
+pub fn medico(m: &Medico) -> String {
+    let crm_medico = if m.crm.is_empty() {
+        "sem CRM".to_string()
+    } else {
+        crm::formatar(&m.crm, &m.uf_crm)
+    };
+    format!("{} - {} ({})", m.codigo_medico, m.nome, crm_medico)
+}
+
+pub fn especialidade(e: &Especialidade) -> String {
+    format!("{} - {} (R$ {:.2})", e.codigo_especialidade, e.descricao, e.valor_consulta)
+}
+
+pub fn cidade(c: &Cidade) -> String {
+    format!("{} - {}/{}", c.codigo_cidade, c.descricao, c.estado)
+}
+
+pub fn exame(e: &Exame) -> String {
+    format!("{} - {} (R$ {:.2})", e.codigo_exame, e.descricao, e.valor_exame)
+}
+
+pub fn cid(c: &Cid) -> String {
+    format!("{} - {}", c.codigo, c.descricao)
+}
+
+pub fn consulta(c: &Consulta) -> String {
+    format!(
+        "{} - {} às {}, paciente {}, médico {} ({})",
+        c.codigo_consulta,
+        datas::formatar(Some(c.data)),
+        c.hora,
+        c.codigo_paciente,
+        c.codigo_medico,
+        c.status.descricao()
+    )
+}
+
+pub fn medicao(m: &Medicao) -> String {
+    format!(
+        "Paciente {} em {}: {:.2} kg, {:.2} m",
+        m.codigo_paciente,
+        datas::formatar(Some(m.data)),
+        m.peso,
+        m.altura
+    )
+}
+
+pub fn diaria(d: &Diaria) -> String {
+    format!(
+        "{} - especialidade {}: {} consulta(s)",
+        datas::formatar(datas::de_codigo(d.codigo_dia)),
+        d.codigo_especialidade,
+        d.quantidade_consultas
+    )
+}
+
+pub fn prontuario(p: &Prontuario) -> String {
+    format!(
+        "Anotação {} da consulta {}, paciente {} (criada em {})",
+        p.codigo_prontuario, p.codigo_consulta, p.codigo_paciente, p.criado_em
+    )
+}
+
+pub fn diagnostico(d: &ConsultaDiagnostico) -> String {
+    format!("Consulta {} - {}", d.codigo_consulta, d.codigo_cid)
+}
+
+pub fn pedido_exame(p: &ConsultaExame) -> String {
+    format!(
+        "Consulta {} - Exame {}: {}",
+        p.codigo_consulta,
+        p.codigo_exame,
+        p.status.descricao()
+    )
+}
@@ -0,0 +1,305 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io;
+
+use crate::db::database::Database;
+use crate::db::file_manager::Entity;
+use crate::structs::{
+    consulta::StatusConsulta,
+    consulta_exame::{ConsultaExame, MudancaStatusExame, ResultadoExame, StatusExame},
+    exame::Exame,
+};
+
+#[derive(Debug)]
+pub enum ErroExame {
+    PedidoNaoEncontrado { codigo_consulta: u32, codigo_exame: u32 },
+    TransicaoInvalida { de: StatusExame, para: StatusExame },
+    SemResultados,
+    Io(io::Error),
+}
+
+impl fmt::Display for ErroExame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErroExame::PedidoNaoEncontrado { codigo_consulta, codigo_exame } => write!(
+                f,
+                "O exame {} não foi pedido na consulta {}.",
+                codigo_exame, codigo_consulta
+            ),
+            ErroExame::TransicaoInvalida { de, para } => write!(
+                f,
+                "Um exame com status {} não pode passar para {}.",
+                de.descricao(),
+                para.descricao()
+            ),
+            ErroExame::SemResultados => write!(f, "Informe ao menos um valor de resultado."),
+            ErroExame::Io(e) => write!(f, "Falha de acesso aos arquivos: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for ErroExame {
+    fn from(e: io::Error) -> Self {
+        ErroExame::Io(e)
+    }
+}
+
+/// Códigos dos exames pedidos na consulta, em ordem crescente.
+pub fn codigos_exames(db: &Database, codigo_consulta: u32) -> Result<Vec<u32>, io::Error> {
+    Ok(db
+        .consulta_exames
+        .read_all_records()?
+        .into_iter()
+        .filter(|item| item.codigo_consulta == codigo_consulta)
+        .map(|item| item.codigo_exame)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect())
+}
+
+/// Exames pedidos na consulta. Itens que apontam para exames excluídos são ignorados.
+pub fn exames_da_consulta(db: &Database, codigo_consulta: u32) -> Result<Vec<Exame>, io::Error> {
+    let mut exames = Vec::new();
+    for codigo_exame in codigos_exames(db, codigo_consulta)? {
+        if let Some(exame) = db.exames.read_record(codigo_exame)? {
+            exames.push(exame);
+        }
+    }
+    Ok(exames)
+}
+
+/// Pedidos da consulta com o andamento de cada um, em ordem de exame.
+pub fn pedidos_da_consulta(db: &Database, codigo_consulta: u32) -> Result<Vec<ConsultaExame>, io::Error> {
+    let mut pedidos: Vec<ConsultaExame> = db
+        .consulta_exames
+        .read_all_records()?
+        .into_iter()
+        .filter(|item| item.codigo_consulta == codigo_consulta)
+        .collect();
+    pedidos.sort_by_key(|item| item.codigo_exame);
+    Ok(pedidos)
+}
+
+/// Só pedidos ainda não coletados podem sair da consulta; depois disso o
+/// exame já tem amostra ou laudo e precisa ser acompanhado até a revisão.
+pub fn verificar_remocao(db: &Database, codigo_consulta: u32, codigos: &[u32]) -> Result<(), io::Error> {
+    for pedido in pedidos_da_consulta(db, codigo_consulta)? {
+        if !codigos.contains(&pedido.codigo_exame) && pedido.status != StatusExame::Solicitado {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "o exame {} já está com status {} e não pode ser retirado da consulta",
+                    pedido.codigo_exame,
+                    pedido.status.descricao()
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Faz com que a consulta tenha exatamente os exames em `codigos`, incluindo os
+/// novos e excluindo os que saíram da lista (ver `verificar_remocao`).
+pub fn definir_exames(
+    db: &mut Database,
+    codigo_consulta: u32,
+    codigos: &[u32],
+) -> Result<(), io::Error> {
+    verificar_remocao(db, codigo_consulta, codigos)?;
+    let atuais = codigos_exames(db, codigo_consulta)?;
+    for codigo_exame in &atuais {
+        if !codigos.contains(codigo_exame) {
+            db.consulta_exames.delete_record((codigo_consulta, *codigo_exame))?;
+        }
+    }
+    for codigo_exame in codigos {
+        if !atuais.contains(codigo_exame) {
+            let item = ConsultaExame::novo(codigo_consulta, *codigo_exame);
+            db.consulta_exames.create_record(&item, (codigo_consulta, *codigo_exame))?;
+        }
+    }
+    Ok(())
+}
+
+fn ler_pedido(db: &Database, codigo_consulta: u32, codigo_exame: u32) -> Result<ConsultaExame, ErroExame> {
+    db.consulta_exames
+        .read_record((codigo_consulta, codigo_exame))?
+        .ok_or(ErroExame::PedidoNaoEncontrado {
+            codigo_consulta,
+            codigo_exame,
+        })
+}
+
+fn gravar_status(db: &mut Database, mut pedido: ConsultaExame, status: StatusExame) -> Result<ConsultaExame, ErroExame> {
+    if pedido.status != status {
+        pedido.status = status;
+        pedido.historico_status.push(MudancaStatusExame::agora(status));
+    }
+    db.consulta_exames.update_record(&pedido, pedido.get_key())?;
+    Ok(pedido)
+}
+
+/// A amostra foi colhida: Solicitado → Coletado.
+pub fn registrar_coleta(db: &mut Database, codigo_consulta: u32, codigo_exame: u32) -> Result<ConsultaExame, ErroExame> {
+    let pedido = ler_pedido(db, codigo_consulta, codigo_exame)?;
+    if pedido.status != StatusExame::Solicitado {
+        return Err(ErroExame::TransicaoInvalida {
+            de: pedido.status,
+            para: StatusExame::Coletado,
+        });
+    }
+    gravar_status(db, pedido, StatusExame::Coletado)
+}
+
+/// Grava o laudo do exame coletado. Um laudo ainda não revisado pode ser
+/// corrigido: os valores informados substituem os anteriores.
+pub fn registrar_resultados(
+    db: &mut Database,
+    codigo_consulta: u32,
+    codigo_exame: u32,
+    resultados: Vec<ResultadoExame>,
+) -> Result<ConsultaExame, ErroExame> {
+    let mut pedido = ler_pedido(db, codigo_consulta, codigo_exame)?;
+    if !matches!(pedido.status, StatusExame::Coletado | StatusExame::ComResultado) {
+        return Err(ErroExame::TransicaoInvalida {
+            de: pedido.status,
+            para: StatusExame::ComResultado,
+        });
+    }
+    if resultados.is_empty() {
+        return Err(ErroExame::SemResultados);
+    }
+    pedido.resultados = resultados;
+    gravar_status(db, pedido, StatusExame::ComResultado)
+}
+
+/// O médico conferiu o laudo: ComResultado → Revisado.
+pub fn revisar(db: &mut Database, codigo_consulta: u32, codigo_exame: u32) -> Result<ConsultaExame, ErroExame> {
+    let pedido = ler_pedido(db, codigo_consulta, codigo_exame)?;
+    if pedido.status != StatusExame::ComResultado {
+        return Err(ErroExame::TransicaoInvalida {
+            de: pedido.status,
+            para: StatusExame::Revisado,
+        });
+    }
+    gravar_status(db, pedido, StatusExame::Revisado)
+}
+
+/// Pedidos do paciente ainda sem revisão, por consulta e exame. Consultas
+/// canceladas ou em que o paciente faltou não geram pendência.
+pub fn pendentes_do_paciente(db: &Database, codigo_paciente: u32) -> Result<Vec<ConsultaExame>, io::Error> {
+    let consultas: BTreeSet<u32> = db
+        .consultas
+        .read_all_records()?
+        .into_iter()
+        .filter(|c| {
+            c.codigo_paciente == codigo_paciente
+                && !matches!(c.status, StatusConsulta::Cancelada | StatusConsulta::Faltou)
+        })
+        .map(|c| c.codigo_consulta)
+        .collect();
+    let mut pendentes: Vec<ConsultaExame> = db
+        .consulta_exames
+        .read_all_records()?
+        .into_iter()
+        .filter(|item| consultas.contains(&item.codigo_consulta) && item.status.pendente())
+        .collect();
+    pendentes.sort_by_key(|item| item.get_key());
+    Ok(pendentes)
+}
+
+/// Lê uma lista de códigos separados por vírgula ou espaço ("1, 4 7").
+/// Repetições são descartadas; `None` se algum item não for um número.
+pub fn interpretar_codigos(texto: &str) -> Option<Vec<u32>> {
+    let mut codigos = Vec::new();
+    for parte in texto.split([',', ' ']).filter(|p| !p.trim().is_empty()) {
+        let codigo = parte.trim().parse().ok()?;
+        if !codigos.contains(&codigo) {
+            codigos.push(codigo);
+        }
+    }
+    Some(codigos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resultado(analito: &str, valor: &str) -> ResultadoExame {
+        ResultadoExame {
+            analito: analito.to_string(),
+            valor: valor.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn status(db: &Database, codigo_exame: u32) -> StatusExame {
+        db.consulta_exames.read_record((1, codigo_exame)).unwrap().unwrap().status
+    }
+
+    #[test]
+    fn pedido_passa_pelas_etapas_em_ordem() {
+        let mut db = Database::em_memoria();
+        definir_exames(&mut db, 1, &[10]).unwrap();
+        assert!(matches!(
+            registrar_resultados(&mut db, 1, 10, vec![resultado("Hemoglobina", "13")]),
+            Err(ErroExame::TransicaoInvalida { de: StatusExame::Solicitado, .. })
+        ));
+        assert!(matches!(
+            revisar(&mut db, 1, 10),
+            Err(ErroExame::TransicaoInvalida { de: StatusExame::Solicitado, .. })
+        ));
+        assert!(matches!(
+            registrar_coleta(&mut db, 1, 99),
+            Err(ErroExame::PedidoNaoEncontrado { codigo_consulta: 1, codigo_exame: 99 })
+        ));
+
+        registrar_coleta(&mut db, 1, 10).unwrap();
+        assert_eq!(status(&db, 10), StatusExame::Coletado);
+        assert!(matches!(
+            registrar_coleta(&mut db, 1, 10),
+            Err(ErroExame::TransicaoInvalida { de: StatusExame::Coletado, .. })
+        ));
+        assert!(matches!(
+            registrar_resultados(&mut db, 1, 10, Vec::new()),
+            Err(ErroExame::SemResultados)
+        ));
+
+        registrar_resultados(&mut db, 1, 10, vec![resultado("Hemoglobina", "31")]).unwrap();
+        // Laudo ainda não revisado pode ser corrigido sem repetir a etapa no histórico.
+        let corrigido =
+            registrar_resultados(&mut db, 1, 10, vec![resultado("Hemoglobina", "13")]).unwrap();
+        assert_eq!(corrigido.resultados[0].valor, "13");
+        let revisado = revisar(&mut db, 1, 10).unwrap();
+        let etapas: Vec<StatusExame> = revisado.historico_status.iter().map(|m| m.status).collect();
+        assert_eq!(etapas, StatusExame::TODOS);
+
+        assert!(matches!(
+            registrar_resultados(&mut db, 1, 10, vec![resultado("Hemoglobina", "14")]),
+            Err(ErroExame::TransicaoInvalida { de: StatusExame::Revisado, .. })
+        ));
+        let gravado = db.consulta_exames.read_record((1, 10)).unwrap().unwrap();
+        assert_eq!(gravado.status, StatusExame::Revisado);
+        assert_eq!(gravado.resultados[0].valor, "13");
+    }
+
+    #[test]
+    fn so_pedidos_nao_coletados_saem_da_consulta() {
+        let mut db = Database::em_memoria();
+        definir_exames(&mut db, 1, &[10, 20, 30]).unwrap();
+        registrar_coleta(&mut db, 1, 20).unwrap();
+
+        verificar_remocao(&db, 1, &[20]).unwrap();
+        let erro = verificar_remocao(&db, 1, &[10, 30]).unwrap_err();
+        assert_eq!(erro.kind(), io::ErrorKind::InvalidInput);
+        assert!(erro.to_string().contains("exame 20"));
+
+        assert!(definir_exames(&mut db, 1, &[10]).is_err());
+        assert_eq!(codigos_exames(&db, 1).unwrap(), vec![10, 20, 30]);
+
+        definir_exames(&mut db, 1, &[20, 40]).unwrap();
+        assert_eq!(codigos_exames(&db, 1).unwrap(), vec![20, 40]);
+        assert_eq!(status(&db, 20), StatusExame::Coletado);
+        assert_eq!(status(&db, 40), StatusExame::Solicitado);
+    }
+}
@@ -1,14 +1,10 @@
 use std::collections::HashMap;
+use std::io;
 
-use crate::{db::file_manager::FileManager, menus::{ler_opcao_menu, ler_string}, structs::{ consulta::Consulta, especialidade::Especialidade, exame::Exame, medico::Medico}};
+use crate::{db::database::Database, menus::{ler_data, ler_opcao_menu}, structs::{consulta::{Consulta, StatusConsulta}, especialidade::Especialidade}, utils::{crm, exames_consulta}};
 
 
-pub fn menu_faturamento(
-    consulta_manager: &FileManager<Consulta>,
-    medico_manager: &FileManager<Medico>,
-    especialidade_manager: &FileManager<Especialidade>,
-    exame_manager: &FileManager<Exame>,
-) {
+pub fn menu_faturamento(db: &Database, status: &[StatusConsulta]) {
     loop {
         println!("\n--- Relatórios de Faturamento ---");
         println!("1. Faturamento por Dia");
@@ -16,112 +12,102 @@ pub fn menu_faturamento(
         println!("3. Faturamento por Médico");
         println!("4. Faturamento por Especialidade");
         println!("5. Voltar");
+        let descricoes: Vec<&str> = status.iter().map(|s| s.descricao()).collect();
+        println!("(Considerando consultas com status: {})", descricoes.join(", "));
         let choice = ler_opcao_menu();
 
         match choice {
-            1 => faturamento_por_dia(consulta_manager, medico_manager, especialidade_manager, exame_manager),
-            2 => faturamento_por_periodo(consulta_manager, medico_manager, especialidade_manager, exame_manager),
-            3 => faturamento_por_medico(consulta_manager, medico_manager, especialidade_manager, exame_manager),
-            4 => faturamento_por_especialidade(consulta_manager, medico_manager, especialidade_manager, exame_manager),
+            1 => faturamento_por_dia(db, status),
+            2 => faturamento_por_periodo(db, status),
+            3 => faturamento_por_medico(db, status),
+            4 => faturamento_por_especialidade(db, status),
             5 => break,
             _ => println!("Opção inválida."),
         }
     }
 }
 
-pub fn faturamento_por_dia(
-    consulta_manager: &FileManager<Consulta>,
-    medico_manager: &FileManager<Medico>,
-    especialidade_manager: &FileManager<Especialidade>,
-    exame_manager: &FileManager<Exame>,
-) {
-    let dia = ler_string("Digite o dia (AAAAMMDD): ");
-    let consultas = consulta_manager.read_all_records().unwrap();
+pub fn faturamento_por_dia(db: &Database, status: &[StatusConsulta]) {
+    let dia = ler_data("Digite o dia (DD/MM/AAAA): ");
+    let Some(consultas) = ler_consultas_faturadas(db, status) else {
+        return;
+    };
     let consultas_do_dia = consultas.into_iter().filter(|c| c.data == dia).collect::<Vec<_>>();
     
-    let mut faturamento_total = 0.0;
-    println!("\nFaturamento do dia {}:", dia);
-    for consulta in consultas_do_dia {
-        let valor = calcular_valor_consulta_total(&consulta, medico_manager, especialidade_manager, exame_manager);
-        println!("- Consulta {}: R$ {:.2}", consulta.codigo_consulta, valor);
-        faturamento_total += valor;
-    }
+    println!("\nFaturamento do dia {}:", dia.format("%d/%m/%Y"));
+    let faturamento_total = listar_valores(db, &consultas_do_dia);
     println!("Faturamento total do dia: R$ {:.2}", faturamento_total);
 }
 
-pub fn faturamento_por_periodo(
-    consulta_manager: &FileManager<Consulta>,
-    medico_manager: &FileManager<Medico>,
-    especialidade_manager: &FileManager<Especialidade>,
-    exame_manager: &FileManager<Exame>,
-) {
-    let inicio_str = ler_string("Digite a data de início (AAAAMMDD): ");
-    let fim_str = ler_string("Digite a data de fim (AAAAMMDD): ");
-    
-    let inicio = inicio_str.parse::<u32>().unwrap_or(0);
-    let fim = fim_str.parse::<u32>().unwrap_or(0);
+pub fn faturamento_por_periodo(db: &Database, status: &[StatusConsulta]) {
+    let inicio = ler_data("Digite a data de início (DD/MM/AAAA): ");
+    let fim = ler_data("Digite a data de fim (DD/MM/AAAA): ");
 
-    let consultas = consulta_manager.read_all_records().unwrap();
+    let Some(consultas) = ler_consultas_faturadas(db, status) else {
+        return;
+    };
     let consultas_do_periodo = consultas.into_iter()
-        .filter(|c| {
-            let data_consulta = c.data.parse::<u32>().unwrap_or(0);
-            data_consulta >= inicio && data_consulta <= fim
-        })
+        .filter(|c| c.data >= inicio && c.data <= fim)
         .collect::<Vec<_>>();
 
-    let mut faturamento_total = 0.0;
-    println!("\nFaturamento do período de {} a {}:", inicio_str, fim_str);
-    for consulta in consultas_do_periodo {
-        let valor = calcular_valor_consulta_total(&consulta, medico_manager, especialidade_manager, exame_manager);
-        println!("- Consulta {}: R$ {:.2}", consulta.codigo_consulta, valor);
-        faturamento_total += valor;
-    }
+    println!(
+        "\nFaturamento do período de {} a {}:",
+        inicio.format("%d/%m/%Y"),
+        fim.format("%d/%m/%Y")
+    );
+    let faturamento_total = listar_valores(db, &consultas_do_periodo);
     println!("Faturamento total do período: R$ {:.2}", faturamento_total);
 }
 
-pub fn faturamento_por_medico(
-    consulta_manager: &FileManager<Consulta>,
-    medico_manager: &FileManager<Medico>,
-    especialidade_manager: &FileManager<Especialidade>,
-    exame_manager: &FileManager<Exame>,
-) {
+pub fn faturamento_por_medico(db: &Database, status: &[StatusConsulta]) {
     let mut faturamento_por_medico = HashMap::new();
-    let consultas = consulta_manager.read_all_records().unwrap();
-    let medicos = medico_manager.read_all_records().unwrap();
+    let Some(consultas) = ler_consultas_faturadas(db, status) else {
+        return;
+    };
+    let medicos = match db.medicos.read_all_records() {
+        Ok(m) => m,
+        Err(_) => {
+            println!("Erro ao ler registros de médicos.");
+            return;
+        }
+    };
     
+    let mut sem_valor = Vec::new();
     for consulta in consultas {
-        let valor = calcular_valor_consulta_total(&consulta, medico_manager, especialidade_manager, exame_manager);
-        let medico = medicos.iter().find(|m| m.codigo_medico == consulta.codigo_medico);
-        if let Some(medico) = medico {
-            *faturamento_por_medico.entry(&medico.nome).or_insert(0.0) += valor;
+        match calcular_valor_consulta_total(&consulta, db) {
+            Ok(valor) => *faturamento_por_medico.entry(consulta.codigo_medico).or_insert(0.0) += valor,
+            Err(e) => sem_valor.push((consulta.codigo_consulta, e)),
         }
     }
     
     println!("\n--- Faturamento por Médico ---");
-    for (nome_medico, faturamento) in faturamento_por_medico {
-        println!("{}: R$ {:.2}", nome_medico, faturamento);
+    for (codigo_medico, faturamento) in faturamento_por_medico {
+        match medicos.iter().find(|m| m.codigo_medico == codigo_medico) {
+            Some(medico) => println!(
+                "{} ({}): R$ {:.2}",
+                medico.nome,
+                crm::formatar(&medico.crm, &medico.uf_crm),
+                faturamento
+            ),
+            None => println!("Médico {} (Não encontrado): R$ {:.2}", codigo_medico, faturamento),
+        }
     }
+    avisar_sem_valor(&sem_valor);
 }
 
-pub fn faturamento_por_especialidade(
-    consulta_manager: &FileManager<Consulta>,
-    medico_manager: &FileManager<Medico>,
-    especialidade_manager: &FileManager<Especialidade>,
-    exame_manager: &FileManager<Exame>,
-) {
+pub fn faturamento_por_especialidade(db: &Database, status: &[StatusConsulta]) {
     let mut faturamento_por_especialidade = HashMap::new();
-    let consultas = consulta_manager.read_all_records().unwrap();
-    let medicos = medico_manager.read_all_records().unwrap();
-    let especialidades = especialidade_manager.read_all_records().unwrap();
+    let Some(consultas) = ler_consultas_faturadas(db, status) else {
+        return;
+    };
 
+    let mut sem_valor = Vec::new();
     for consulta in consultas {
-        let valor = calcular_valor_consulta_total(&consulta, medico_manager, especialidade_manager, exame_manager);
-        let medico = medicos.iter().find(|m| m.codigo_medico == consulta.codigo_medico);
-        if let Some(medico) = medico {
-            let especialidade = especialidades.iter().find(|e| e.codigo_especialidade == medico.codigo_especialidade);
-            if let Some(especialidade) = especialidade {
-                *faturamento_por_especialidade.entry(&especialidade.descricao).or_insert(0.0) += valor;
+        match especialidade_e_valor(&consulta, db) {
+            Ok((especialidade, valor)) => {
+                *faturamento_por_especialidade.entry(especialidade.descricao).or_insert(0.0) += valor;
             }
+            Err(e) => sem_valor.push((consulta.codigo_consulta, e)),
         }
     }
 
@@ -129,17 +115,157 @@ pub fn faturamento_por_especialidade(
     for (descricao, faturamento) in faturamento_por_especialidade {
         println!("{}: R$ {:.2}", descricao, faturamento);
     }
+    avisar_sem_valor(&sem_valor);
 }
 
-pub fn calcular_valor_consulta_total(
-    consulta: &Consulta,
-    medico_manager: &FileManager<Medico>,
-    especialidade_manager: &FileManager<Especialidade>,
-    exame_manager: &FileManager<Exame>,
-) -> f32 {
-    let medico = medico_manager.read_record(consulta.codigo_medico).unwrap().unwrap();
-    let especialidade = especialidade_manager.read_record(medico.codigo_especialidade).unwrap().unwrap();
-    let exame = exame_manager.read_record(consulta.codigo_exame).unwrap().unwrap();
-    
-    especialidade.valor_consulta + exame.valor_exame
+/// Imprime o valor de cada consulta e devolve a soma. As que não têm valor
+/// ficam fora do total e são listadas ao final.
+fn listar_valores(db: &Database, consultas: &[Consulta]) -> f32 {
+    let mut total = 0.0;
+    let mut sem_valor = Vec::new();
+    for consulta in consultas {
+        match calcular_valor_consulta_total(consulta, db) {
+            Ok(valor) => {
+                println!("- Consulta {}: R$ {:.2}", consulta.codigo_consulta, valor);
+                total += valor;
+            }
+            Err(e) => sem_valor.push((consulta.codigo_consulta, e)),
+        }
+    }
+    avisar_sem_valor(&sem_valor);
+    total
+}
+
+fn avisar_sem_valor(sem_valor: &[(u32, io::Error)]) {
+    if sem_valor.is_empty() {
+        return;
+    }
+    println!("ATENÇÃO: {} consulta(s) ficaram fora do total:", sem_valor.len());
+    for (codigo_consulta, e) in sem_valor {
+        println!("- Consulta {}: {}", codigo_consulta, e);
+    }
+}
+
+fn ler_consultas_faturadas(db: &Database, status: &[StatusConsulta]) -> Option<Vec<Consulta>> {
+    match consultas_faturadas(db, status) {
+        Ok(consultas) => Some(consultas),
+        Err(_) => {
+            println!("Erro ao ler registros de consultas.");
+            None
+        }
+    }
+}
+
+/// Consultas cujo status entra no faturamento (por padrão, só as realizadas).
+fn consultas_faturadas(db: &Database, status: &[StatusConsulta]) -> Result<Vec<Consulta>, io::Error> {
+    let consultas = db.consultas.read_all_records()?;
+    Ok(consultas.into_iter().filter(|c| status.contains(&c.status)).collect())
+}
+
+/// Especialidade do médico da consulta e o valor total (consulta mais exames).
+/// `NotFound` quando o médico ou a especialidade dele não existe mais.
+fn especialidade_e_valor(consulta: &Consulta, db: &Database) -> Result<(Especialidade, f32), io::Error> {
+    let medico = db.medicos.read_record(consulta.codigo_medico)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("médico {} não encontrado", consulta.codigo_medico),
+        )
+    })?;
+    let especialidade = db.especialidades.read_record(medico.codigo_especialidade)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("especialidade {} do médico {} não encontrada", medico.codigo_especialidade, medico.codigo_medico),
+        )
+    })?;
+    let exames = exames_consulta::exames_da_consulta(db, consulta.codigo_consulta)?;
+
+    let valor = especialidade.valor_consulta + exames.iter().map(|e| e.valor_exame).sum::<f32>();
+    Ok((especialidade, valor))
+}
+
+pub fn calcular_valor_consulta_total(consulta: &Consulta, db: &Database) -> Result<f32, io::Error> {
+    especialidade_e_valor(consulta, db).map(|(_, valor)| valor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::{exame::Exame, medico::Medico};
+
+    fn consulta(codigo_consulta: u32) -> Consulta {
+        Consulta {
+            codigo_consulta,
+            codigo_paciente: 1,
+            codigo_medico: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn valor_total_soma_consulta_e_exames() {
+        let mut db = Database::em_memoria();
+        let especialidade = Especialidade {
+            codigo_especialidade: 1,
+            descricao: "Cardiologia".to_string(),
+            valor_consulta: 200.0,
+            ..Default::default()
+        };
+        db.especialidades.create_record(&especialidade, 1).unwrap();
+        let medico = Medico {
+            codigo_medico: 1,
+            nome: "Dra. Ana".to_string(),
+            codigo_especialidade: 1,
+            ..Default::default()
+        };
+        db.medicos.create_record(&medico, 1).unwrap();
+        for (codigo_exame, valor_exame) in [(1, 50.0), (2, 75.5)] {
+            let exame = Exame {
+                codigo_exame,
+                codigo_especialidade: 1,
+                valor_exame,
+                ..Default::default()
+            };
+            db.exames.create_record(&exame, codigo_exame).unwrap();
+        }
+        db.consultas.create_record(&consulta(1), 1).unwrap();
+        db.consultas.create_record(&consulta(2), 2).unwrap();
+        exames_consulta::definir_exames(&mut db, 1, &[1, 2]).unwrap();
+
+        assert_eq!(calcular_valor_consulta_total(&consulta(1), &db).unwrap(), 325.5);
+        assert_eq!(calcular_valor_consulta_total(&consulta(2), &db).unwrap(), 200.0);
+
+        exames_consulta::definir_exames(&mut db, 1, &[2]).unwrap();
+        assert_eq!(calcular_valor_consulta_total(&consulta(1), &db).unwrap(), 275.5);
+
+        let orfa = Consulta {
+            codigo_medico: 9,
+            ..consulta(3)
+        };
+        let erro = calcular_valor_consulta_total(&orfa, &db).unwrap_err();
+        assert_eq!(erro.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn fatura_apenas_os_status_configurados() {
+        let mut db = Database::em_memoria();
+        let status = [
+            StatusConsulta::Agendada,
+            StatusConsulta::Realizada,
+            StatusConsulta::Cancelada,
+        ];
+        for (i, status) in status.into_iter().enumerate() {
+            let consulta = Consulta {
+                status,
+                ..consulta(i as u32 + 1)
+            };
+            db.consultas.create_record(&consulta, consulta.codigo_consulta).unwrap();
+        }
+
+        let codigos = |status: &[StatusConsulta]| -> Vec<u32> {
+            consultas_faturadas(&db, status).unwrap().iter().map(|c| c.codigo_consulta).collect()
+        };
+        assert_eq!(codigos(&[StatusConsulta::Realizada]), vec![2]);
+        assert_eq!(codigos(&[StatusConsulta::Agendada, StatusConsulta::Realizada]), vec![1, 2]);
+        assert!(codigos(&[]).is_empty());
+    }
 }
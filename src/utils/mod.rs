@@ -1,3 +1,14 @@
+pub mod agendamento;
+pub mod cid10;
+pub mod clinical;
+pub mod cpf;
+pub mod crm;
+pub mod datas;
+pub mod descricoes;
+pub mod exames_consulta;
+pub mod pesquisa;
 pub mod faturamentos;
+pub mod regras;
 pub mod relatorios;
-pub mod print_data;
\ No newline at end of file
+pub mod print_data;
+pub mod prontuarios;
\ No newline at end of file
@@ -0,0 +1,65 @@
+use std::io;
+
+use crate::db::database::Database;
+use crate::db::file_manager::FileManager;
+use crate::db::query::{Query, Queryable};
+use crate::db::search;
+use crate::utils::descricoes;
+use crate::structs::{
+    cid::Cid, cidade::Cidade, consulta::Consulta, consulta_diagnostico::ConsultaDiagnostico,
+    consulta_exame::ConsultaExame, diaria::Diaria, especialidade::Especialidade, exame::Exame,
+    medicao::Medicao, medico::Medico, paciente::Paciente, prontuario::Prontuario,
+};
+
+/// Coleções aceitas no início da consulta, com os campos de cada uma.
+pub const COLECOES: [(&str, &[&str]); 12] = [
+    ("pacientes", Paciente::FIELDS),
+    ("medicos", Medico::FIELDS),
+    ("especialidades", Especialidade::FIELDS),
+    ("cidades", Cidade::FIELDS),
+    ("exames", Exame::FIELDS),
+    ("consultas", Consulta::FIELDS),
+    ("medicoes", Medicao::FIELDS),
+    ("diarias", Diaria::FIELDS),
+    ("prontuarios", Prontuario::FIELDS),
+    ("cid10", Cid::FIELDS),
+    ("diagnosticos", ConsultaDiagnostico::FIELDS),
+    ("pedidos_exames", ConsultaExame::FIELDS),
+];
+
+pub const EXEMPLO: &str =
+    "consultas where data between 20250101 and 20250131 and codigo_medico = 3 order by hora";
+
+/// Interpreta e executa `texto` (ver `db::query`), devolvendo uma linha por registro.
+pub fn executar(db: &Database, texto: &str) -> Result<Vec<String>, io::Error> {
+    let consulta = Query::parse(texto)?;
+    match search::normalize(&consulta.entity).as_str() {
+        "pacientes" => listar(&consulta, &db.pacientes, descricoes::paciente),
+        "medicos" => listar(&consulta, &db.medicos, descricoes::medico),
+        "especialidades" => listar(&consulta, &db.especialidades, descricoes::especialidade),
+        "cidades" => listar(&consulta, &db.cidades, descricoes::cidade),
+        "exames" => listar(&consulta, &db.exames, descricoes::exame),
+        "consultas" => listar(&consulta, &db.consultas, descricoes::consulta),
+        "medicoes" => listar(&consulta, &db.medicoes, descricoes::medicao),
+        "diarias" => listar(&consulta, &db.diarias, descricoes::diaria),
+        "prontuarios" => listar(&consulta, &db.prontuarios, descricoes::prontuario),
+        "cid10" => listar(&consulta, &db.cids, descricoes::cid),
+        "diagnosticos" => listar(&consulta, &db.consulta_diagnosticos, descricoes::diagnostico),
+        "pedidos_exames" => listar(&consulta, &db.consulta_exames, descricoes::pedido_exame),
+        outra => {
+            let nomes: Vec<&str> = COLECOES.iter().map(|(nome, _)| *nome).collect();
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("coleção desconhecida: {} (coleções: {})", outra, nomes.join(", ")),
+            ))
+        }
+    }
+}
+
+fn listar<T: Queryable>(
+    consulta: &Query,
+    manager: &FileManager<T>,
+    descrever: fn(&T) -> String,
+) -> Result<Vec<String>, io::Error> {
+    Ok(consulta.execute(manager)?.iter().map(descrever).collect())
+}
@@ -0,0 +1,315 @@
+use std::fmt;
+use std::io;
+
+use chrono::{NaiveDateTime, NaiveTime};
+
+use crate::db::database::Database;
+use crate::structs::{
+    consulta::{Consulta, StatusConsulta},
+    prontuario::{self, Prontuario},
+};
+use crate::utils::cid10::{self, ErroDiagnostico};
+
+#[derive(Debug)]
+pub enum ErroProntuario {
+    CodigoJaExiste(u32),
+    ProntuarioNaoEncontrado(u32),
+    ConsultaNaoEncontrada(u32),
+    SemAtendimento { codigo_consulta: u32, status: StatusConsulta },
+    Diagnostico(ErroDiagnostico),
+    Io(io::Error),
+}
+
+impl fmt::Display for ErroProntuario {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErroProntuario::CodigoJaExiste(codigo) => {
+                write!(f, "Prontuário com código {} já existe.", codigo)
+            }
+            ErroProntuario::ProntuarioNaoEncontrado(codigo) => {
+                write!(f, "Prontuário com código {} não encontrado.", codigo)
+            }
+            ErroProntuario::ConsultaNaoEncontrada(codigo) => {
+                write!(f, "Consulta com código {} não encontrada.", codigo)
+            }
+            ErroProntuario::SemAtendimento { codigo_consulta, status } => write!(
+                f,
+                "A consulta {} está com status {}: não houve atendimento para registrar.",
+                codigo_consulta,
+                status.descricao()
+            ),
+            ErroProntuario::Diagnostico(e) => write!(f, "{}", e),
+            ErroProntuario::Io(e) => write!(f, "Falha de acesso aos arquivos: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for ErroProntuario {
+    fn from(e: io::Error) -> Self {
+        ErroProntuario::Io(e)
+    }
+}
+
+impl From<ErroDiagnostico> for ErroProntuario {
+    fn from(e: ErroDiagnostico) -> Self {
+        ErroProntuario::Diagnostico(e)
+    }
+}
+
+/// Item da linha do tempo do paciente.
+pub enum Evento {
+    Consulta(Consulta),
+    Anotacao(Prontuario),
+}
+
+pub struct EventoHistorico {
+    pub momento: NaiveDateTime,
+    pub evento: Evento,
+}
+
+/// Grava uma nova anotação. O paciente vem da consulta, que precisa ter
+/// acontecido ou ainda estar em aberto (anotações de pré-consulta). Os
+/// diagnósticos precisam estar no catálogo e passam a valer também para a consulta.
+pub fn registrar(db: &mut Database, mut prontuario: Prontuario) -> Result<Prontuario, ErroProntuario> {
+    if db.prontuarios.read_record(prontuario.codigo_prontuario)?.is_some() {
+        return Err(ErroProntuario::CodigoJaExiste(prontuario.codigo_prontuario));
+    }
+    let consulta = db
+        .consultas
+        .read_record(prontuario.codigo_consulta)?
+        .ok_or(ErroProntuario::ConsultaNaoEncontrada(prontuario.codigo_consulta))?;
+    if matches!(consulta.status, StatusConsulta::Cancelada | StatusConsulta::Faltou) {
+        return Err(ErroProntuario::SemAtendimento {
+            codigo_consulta: consulta.codigo_consulta,
+            status: consulta.status,
+        });
+    }
+
+    prontuario.codigo_paciente = consulta.codigo_paciente;
+    prontuario.diagnosticos = cid10::validar(&db.cids, &prontuario.diagnosticos)?;
+    prontuario.criado_em = prontuario::agora();
+    prontuario.alterado_em = prontuario.criado_em.clone();
+    db.prontuarios.create_record(&prontuario, prontuario.codigo_prontuario)?;
+    cid10::anexar_diagnosticos(db, prontuario.codigo_consulta, &prontuario.diagnosticos)?;
+    Ok(prontuario)
+}
+
+/// Regrava o conteúdo clínico da anotação. Consulta, paciente e data de
+/// criação continuam os da versão gravada. Diagnósticos novos são acrescentados
+/// à consulta; os retirados da anotação continuam nela até serem revistos pelo menu CID-10.
+pub fn alterar(db: &mut Database, prontuario: Prontuario) -> Result<Prontuario, ErroProntuario> {
+    let atual = db
+        .prontuarios
+        .read_record(prontuario.codigo_prontuario)?
+        .ok_or(ErroProntuario::ProntuarioNaoEncontrado(prontuario.codigo_prontuario))?;
+    let prontuario = Prontuario {
+        diagnosticos: cid10::validar(&db.cids, &prontuario.diagnosticos)?,
+        alterado_em: prontuario::agora(),
+        codigo_consulta: atual.codigo_consulta,
+        codigo_paciente: atual.codigo_paciente,
+        criado_em: atual.criado_em,
+        ..prontuario
+    };
+    db.prontuarios.update_record(&prontuario, prontuario.codigo_prontuario)?;
+    cid10::anexar_diagnosticos(db, prontuario.codigo_consulta, &prontuario.diagnosticos)?;
+    Ok(prontuario)
+}
+
+/// Anotações da consulta, da mais antiga à mais recente.
+pub fn prontuarios_da_consulta(db: &Database, codigo_consulta: u32) -> Result<Vec<Prontuario>, io::Error> {
+    let mut prontuarios: Vec<Prontuario> = db
+        .prontuarios
+        .read_all_records()?
+        .into_iter()
+        .filter(|p| p.codigo_consulta == codigo_consulta)
+        .collect();
+    prontuarios.sort_by(|a, b| a.criado_em.cmp(&b.criado_em));
+    Ok(prontuarios)
+}
+
+/// Consultas e anotações do paciente em ordem cronológica. A consulta conta
+/// pela data e hora marcadas; a anotação, pelo momento em que foi criada.
+pub fn linha_do_tempo(db: &Database, codigo_paciente: u32) -> Result<Vec<EventoHistorico>, io::Error> {
+    let mut eventos = Vec::new();
+    for consulta in db.consultas.read_all_records()? {
+        if consulta.codigo_paciente != codigo_paciente {
+            continue;
+        }
+        // Horas gravadas antes da validação podem não ser legíveis; contam como início do dia.
+        let hora = NaiveTime::parse_from_str(&consulta.hora, "%H:%M").unwrap_or(NaiveTime::MIN);
+        eventos.push(EventoHistorico {
+            momento: consulta.data.and_time(hora),
+            evento: Evento::Consulta(consulta),
+        });
+    }
+    for prontuario in db.prontuarios.read_all_records()? {
+        if prontuario.codigo_paciente != codigo_paciente {
+            continue;
+        }
+        let momento = NaiveDateTime::parse_from_str(&prontuario.criado_em, "%Y-%m-%d %H:%M:%S")
+            .unwrap_or(NaiveDateTime::MIN);
+        eventos.push(EventoHistorico {
+            momento,
+            evento: Evento::Anotacao(prontuario),
+        });
+    }
+    eventos.sort_by_key(|e| e.momento);
+    Ok(eventos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn dia(dia: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, dia).unwrap()
+    }
+
+    fn consulta(codigo_consulta: u32, data: NaiveDate, hora: &str, status: StatusConsulta) -> Consulta {
+        Consulta {
+            codigo_consulta,
+            codigo_paciente: 1,
+            codigo_medico: 1,
+            data,
+            hora: hora.to_string(),
+            status,
+            ..Default::default()
+        }
+    }
+
+    fn banco(consultas: &[Consulta]) -> Database {
+        let mut db = Database::em_memoria();
+        let catalogo = cid10::interpretar_csv(cid10::CATALOGO_PADRAO).unwrap();
+        cid10::importar(&mut db.cids, &catalogo).unwrap();
+        for c in consultas {
+            db.consultas.create_record(c, c.codigo_consulta).unwrap();
+        }
+        db
+    }
+
+    fn anotacao(codigo_prontuario: u32, codigo_consulta: u32) -> Prontuario {
+        Prontuario {
+            codigo_prontuario,
+            codigo_consulta,
+            anamnese: "Febre há dois dias".to_string(),
+            diagnosticos: vec!["j111".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn registrar_exige_atendimento_e_copia_o_paciente() {
+        let mut db = banco(&[
+            consulta(1, dia(3), "09:00", StatusConsulta::Cancelada),
+            consulta(2, dia(3), "10:00", StatusConsulta::Faltou),
+            consulta(3, dia(3), "11:00", StatusConsulta::Realizada),
+        ]);
+        for codigo_consulta in [1, 2] {
+            let erro = registrar(&mut db, anotacao(codigo_consulta, codigo_consulta)).unwrap_err();
+            assert!(matches!(erro, ErroProntuario::SemAtendimento { .. }));
+        }
+        assert!(matches!(
+            registrar(&mut db, anotacao(9, 99)),
+            Err(ErroProntuario::ConsultaNaoEncontrada(99))
+        ));
+        assert!(db.prontuarios.read_all_records().unwrap().is_empty());
+
+        let gravado = registrar(&mut db, Prontuario { codigo_paciente: 42, ..anotacao(1, 3) }).unwrap();
+        assert_eq!(gravado.codigo_paciente, 1);
+        assert_eq!(gravado.diagnosticos, vec!["J11.1".to_string()]);
+        assert_eq!(cid10::codigos_da_consulta(&db, 3).unwrap(), vec!["J11.1".to_string()]);
+        assert!(matches!(
+            registrar(&mut db, anotacao(1, 3)),
+            Err(ErroProntuario::CodigoJaExiste(1))
+        ));
+    }
+
+    #[test]
+    fn alterar_mantem_consulta_paciente_e_criacao() {
+        let mut db = banco(&[
+            consulta(1, dia(3), "09:00", StatusConsulta::Realizada),
+            consulta(2, dia(4), "09:00", StatusConsulta::Realizada),
+        ]);
+        let mut original = anotacao(1, 1);
+        original.criado_em = "2025-03-03 09:30:00".to_string();
+        db.prontuarios.create_record(&Prontuario { codigo_paciente: 1, ..original }, 1).unwrap();
+
+        let alterado = alterar(
+            &mut db,
+            Prontuario {
+                codigo_consulta: 2,
+                codigo_paciente: 7,
+                anamnese: "Sem febre".to_string(),
+                diagnosticos: vec!["I10".to_string()],
+                criado_em: "2030-01-01 00:00:00".to_string(),
+                ..anotacao(1, 2)
+            },
+        )
+        .unwrap();
+
+        let gravado = db.prontuarios.read_record(1).unwrap().unwrap();
+        for p in [&alterado, &gravado] {
+            assert_eq!(p.codigo_consulta, 1);
+            assert_eq!(p.codigo_paciente, 1);
+            assert_eq!(p.criado_em, "2025-03-03 09:30:00");
+            assert_eq!(p.anamnese, "Sem febre");
+            assert_ne!(p.alterado_em, p.criado_em);
+        }
+        assert_eq!(cid10::codigos_da_consulta(&db, 1).unwrap(), vec!["I10".to_string()]);
+        assert!(cid10::codigos_da_consulta(&db, 2).unwrap().is_empty());
+        assert!(matches!(
+            alterar(&mut db, anotacao(5, 1)),
+            Err(ErroProntuario::ProntuarioNaoEncontrado(5))
+        ));
+    }
+
+    #[test]
+    fn linha_do_tempo_em_ordem_cronologica() {
+        let mut outra = consulta(4, dia(1), "08:00", StatusConsulta::Realizada);
+        outra.codigo_paciente = 2;
+        let mut db = banco(&[
+            consulta(1, dia(10), "14:00", StatusConsulta::Realizada),
+            consulta(2, dia(5), "09:00", StatusConsulta::Realizada),
+            // Hora ilegível: conta como meia-noite, antes da anotação das 00:10.
+            consulta(3, dia(10), "5555", StatusConsulta::Realizada),
+            outra,
+        ]);
+        for (codigo_prontuario, codigo_consulta, criado_em) in [
+            (1, 1, "2025-03-10 14:40:00"),
+            (2, 2, "2025-03-05 09:20:00"),
+            (3, 3, "2025-03-10 00:10:00"),
+        ] {
+            let prontuario = Prontuario {
+                codigo_prontuario,
+                codigo_consulta,
+                codigo_paciente: 1,
+                criado_em: criado_em.to_string(),
+                ..Default::default()
+            };
+            db.prontuarios.create_record(&prontuario, codigo_prontuario).unwrap();
+        }
+
+        let eventos: Vec<String> = linha_do_tempo(&db, 1)
+            .unwrap()
+            .iter()
+            .map(|e| match &e.evento {
+                Evento::Consulta(c) => format!("consulta {}", c.codigo_consulta),
+                Evento::Anotacao(p) => format!("anotação {}", p.codigo_prontuario),
+            })
+            .collect();
+        assert_eq!(
+            eventos,
+            [
+                "consulta 2",
+                "anotação 2",
+                "consulta 3",
+                "anotação 3",
+                "consulta 1",
+                "anotação 1"
+            ]
+        );
+        let meia_noite = linha_do_tempo(&db, 1).unwrap()[2].momento;
+        assert_eq!(meia_noite, dia(10).and_time(NaiveTime::MIN));
+    }
+}
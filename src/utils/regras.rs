@@ -0,0 +1,152 @@
+use std::io;
+
+use chrono::NaiveDate;
+
+use crate::db::database::Database;
+use crate::structs::{exame::Exame, medico::Medico, paciente::Paciente};
+use crate::utils::agendamento::ErroAgendamento;
+
+/// Tudo o que já foi escolhido para a consulta no momento da validação.
+pub struct Agendamento<'a> {
+    pub paciente: &'a Paciente,
+    pub medico: &'a Medico,
+    pub exames: &'a [Exame],
+    pub data: NaiveDate,
+}
+
+/// Regra que cruza as entidades de um agendamento. As regras ativas são
+/// escolhidas pelo nome em `regras_agendamento` no `config.json`.
+pub trait RegraAgendamento {
+    fn nome(&self) -> &'static str;
+    fn verificar(&self, db: &Database, agendamento: &Agendamento) -> Result<(), ErroAgendamento>;
+}
+
+/// Todo exame pedido precisa ser da especialidade do médico.
+pub struct ExameDaEspecialidade;
+
+impl RegraAgendamento for ExameDaEspecialidade {
+    fn nome(&self) -> &'static str {
+        "exame_da_especialidade"
+    }
+
+    fn verificar(&self, db: &Database, agendamento: &Agendamento) -> Result<(), ErroAgendamento> {
+        let codigo_especialidade = agendamento.medico.codigo_especialidade;
+        let Some(exame) = agendamento
+            .exames
+            .iter()
+            .find(|e| e.codigo_especialidade != codigo_especialidade)
+        else {
+            return Ok(());
+        };
+
+        let especialidade = db
+            .especialidades
+            .read_record(codigo_especialidade)?
+            .map_or(codigo_especialidade.to_string(), |e| e.descricao);
+        let compativeis: Vec<String> = db
+            .exames
+            .read_all_records()?
+            .into_iter()
+            .filter(|e| e.codigo_especialidade == codigo_especialidade)
+            .map(|e| format!("{} - {}", e.codigo_exame, e.descricao))
+            .collect();
+        let compativeis = if compativeis.is_empty() {
+            "nenhum exame cadastrado".to_string()
+        } else {
+            compativeis.join("; ")
+        };
+
+        Err(ErroAgendamento::RegraViolada {
+            regra: self.nome(),
+            mensagem: format!(
+                "O exame {} ({}) não é da especialidade do médico ({}). Exames compatíveis: {}.",
+                exame.codigo_exame, exame.descricao, especialidade, compativeis
+            ),
+        })
+    }
+}
+
+/// A idade do paciente na data da consulta precisa estar na faixa atendida
+/// pela especialidade (ex.: pediatria só até 17 anos).
+pub struct IdadeDaEspecialidade;
+
+impl RegraAgendamento for IdadeDaEspecialidade {
+    fn nome(&self) -> &'static str {
+        "idade_da_especialidade"
+    }
+
+    fn verificar(&self, db: &Database, agendamento: &Agendamento) -> Result<(), ErroAgendamento> {
+        let codigo_especialidade = agendamento.medico.codigo_especialidade;
+        let Some(especialidade) = db.especialidades.read_record(codigo_especialidade)? else {
+            return Ok(());
+        };
+        if !especialidade.restringe_idade() {
+            return Ok(());
+        }
+
+        let paciente = agendamento.paciente;
+        let mensagem = match paciente.idade(agendamento.data) {
+            Some(idade) if especialidade.aceita_idade(idade) => return Ok(()),
+            Some(idade) => format!(
+                "O paciente {} terá {} anos na data da consulta e a especialidade {} atende {}.",
+                paciente.nome,
+                idade,
+                especialidade.descricao,
+                especialidade.descricao_idades()
+            ),
+            None => format!(
+                "O paciente {} não tem data de nascimento cadastrada e a especialidade {} atende {}.",
+                paciente.nome,
+                especialidade.descricao,
+                especialidade.descricao_idades()
+            ),
+        };
+        Err(ErroAgendamento::RegraViolada {
+            regra: self.nome(),
+            mensagem,
+        })
+    }
+}
+
+fn todas() -> Vec<Box<dyn RegraAgendamento>> {
+    vec![Box::new(ExameDaEspecialidade), Box::new(IdadeDaEspecialidade)]
+}
+
+/// Regras ativas: todas quando `nomes` é `None`, senão só as listadas.
+/// Nomes desconhecidos são erro, para que um erro de digitação não desligue
+/// uma validação sem ninguém perceber.
+pub fn carregar(nomes: Option<&[String]>) -> Result<Vec<Box<dyn RegraAgendamento>>, io::Error> {
+    let Some(nomes) = nomes else {
+        return Ok(todas());
+    };
+
+    let mut disponiveis = todas();
+    let mut ativas = Vec::new();
+    for nome in nomes {
+        let Some(posicao) = disponiveis.iter().position(|r| r.nome() == nome) else {
+            let conhecidas: Vec<&str> = todas().iter().map(|r| r.nome()).collect();
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "regra de agendamento desconhecida: {} (disponíveis: {})",
+                    nome,
+                    conhecidas.join(", ")
+                ),
+            ));
+        };
+        ativas.push(disponiveis.remove(posicao));
+    }
+    Ok(ativas)
+}
+
+/// Aplica as regras em ordem e para na primeira violada.
+pub fn validar(
+    regras: &[Box<dyn RegraAgendamento>],
+    db: &Database,
+    agendamento: &Agendamento,
+) -> Result<(), ErroAgendamento> {
+    for regra in regras {
+        regra.verificar(db, agendamento)?;
+    }
+    Ok(())
+}
@@ -1,69 +1,258 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::io;
 
-use crate::{db::file_manager::FileManager, structs::{cidade::Cidade, consulta::Consulta, especialidade::Especialidade, exame::Exame, medico::Medico, paciente::Paciente}};
+use crate::db::database::Database;
+use crate::menus::{descrever_idade, ler_data, ler_u32};
+use crate::structs::consulta::{Consulta, StatusConsulta};
+use crate::structs::exame::Exame;
+use crate::structs::medico::Medico;
+use crate::structs::paciente::{FaixaEtaria, Paciente};
+use crate::utils::{cid10, crm, exames_consulta};
 
 
-pub fn relatorio_consultas_ordenadas(
-    consulta_manager: &FileManager<Consulta>,
-    paciente_manager: &FileManager<Paciente>,
-    cidade_manager: &FileManager<Cidade>,
-    medico_manager: &FileManager<Medico>,
-    especialidade_manager: &FileManager<Especialidade>,
-    exame_manager: &FileManager<Exame>,
-) {
-    println!("\n--- Relatório de Consultas Ordenadas ---");
-
-    let mut consultas = match consulta_manager.read_all_records() {
-        Ok(c) => c,
-        Err(_) => {
-            println!("Erro ao ler registros de consultas.");
-            return;
-        }
-    };
+/// Uma consulta do relatório com paciente, médico e exames já carregados.
+/// Registros que não existem mais ficam `None` e aparecem como "Não encontrado".
+struct LinhaConsulta {
+    consulta: Consulta,
+    paciente: Option<Paciente>,
+    medico: Option<Medico>,
+    exames: Vec<Exame>,
+    nome_cidade: String,
+    valor_total: f32,
+}
 
-    consultas.sort_by(|a, b| a.codigo_consulta.cmp(&b.codigo_consulta));
+/// Totais do relatório. Toda consulta conta na sua faixa etária, mas só as de
+/// status faturado somam valor e pacientes.
+#[derive(Default)]
+struct ResumoConsultas {
+    pacientes: HashSet<u32>,
+    valor_total: f32,
+    // Faixa etária na data da consulta (None = sem data de nascimento) -> (consultas, valor faturado).
+    por_faixa: BTreeMap<Option<FaixaEtaria>, (u32, f32)>,
+}
 
-    let mut pacientes_unicos = HashSet::new();
-    let mut valor_total_a_pagar = 0.0;
+fn linhas_consultas(db: &Database) -> Result<Vec<LinhaConsulta>, io::Error> {
+    let mut consultas = db.consultas.read_all_records()?;
+    consultas.sort_by_key(|c| c.codigo_consulta);
 
+    let mut linhas = Vec::new();
     for consulta in consultas {
-        let paciente = paciente_manager.read_record(consulta.codigo_paciente).unwrap_or(None);
-        let medico = medico_manager.read_record(consulta.codigo_medico).unwrap_or(None);
-        let exame = exame_manager.read_record(consulta.codigo_exame).unwrap_or(None);
-
-        let nome_paciente = paciente.as_ref().map_or("Não encontrado".to_string(), |p| p.nome.clone());
-        let nome_medico = medico.as_ref().map_or("Não encontrado".to_string(), |m| m.nome.clone());
-        let desc_exame = exame.as_ref().map_or("Não encontrado".to_string(), |e| e.descricao.clone());
+        let paciente = db.pacientes.read_record(consulta.codigo_paciente).unwrap_or(None);
+        let medico = db.medicos.read_record(consulta.codigo_medico).unwrap_or(None);
+        let exames = exames_consulta::exames_da_consulta(db, consulta.codigo_consulta).unwrap_or_default();
 
         let nome_cidade = if let Some(p) = &paciente {
-            cidade_manager.read_record(p.codigo_cidade).unwrap_or(None).map_or("Não encontrada".to_string(), |c| c.descricao)
+            db.cidades.read_record(p.codigo_cidade).unwrap_or(None).map_or("Não encontrada".to_string(), |c| c.descricao)
         } else {
             "Não encontrada".to_string()
         };
 
         let valor_consulta = medico.as_ref()
-            .and_then(|m| especialidade_manager.read_record(m.codigo_especialidade).unwrap_or(None))
+            .and_then(|m| db.especialidades.read_record(m.codigo_especialidade).unwrap_or(None))
             .map_or(0.0, |e| e.valor_consulta);
-        let valor_exame = exame.as_ref().map_or(0.0, |e| e.valor_exame);
-        let valor_total = valor_consulta + valor_exame;
+        let valor_exames: f32 = exames.iter().map(|e| e.valor_exame).sum();
 
-        valor_total_a_pagar += valor_total;
-        pacientes_unicos.insert(consulta.codigo_paciente);
+        linhas.push(LinhaConsulta {
+            consulta,
+            paciente,
+            medico,
+            exames,
+            nome_cidade,
+            valor_total: valor_consulta + valor_exames,
+        });
+    }
+    Ok(linhas)
+}
+
+fn resumir(linhas: &[LinhaConsulta], status_faturados: &[StatusConsulta]) -> ResumoConsultas {
+    let mut resumo = ResumoConsultas::default();
+    for linha in linhas {
+        let consulta = &linha.consulta;
+        let faixa = linha.paciente.as_ref().and_then(|p| p.idade(consulta.data)).map(FaixaEtaria::de_idade);
+        let totais_faixa = resumo.por_faixa.entry(faixa).or_insert((0, 0.0));
+        totais_faixa.0 += 1;
+
+        if status_faturados.contains(&consulta.status) {
+            resumo.valor_total += linha.valor_total;
+            resumo.pacientes.insert(consulta.codigo_paciente);
+            totais_faixa.1 += linha.valor_total;
+        }
+    }
+    resumo
+}
+
+/// Lista todas as consultas com seu status; os totais só somam as consultas
+/// cujo status está em `status_faturados`.
+pub fn relatorio_consultas_ordenadas(db: &Database, status_faturados: &[StatusConsulta]) {
+    println!("\n--- Relatório de Consultas Ordenadas ---");
+
+    let linhas = match linhas_consultas(db) {
+        Ok(l) => l,
+        Err(_) => {
+            println!("Erro ao ler registros de consultas.");
+            return;
+        }
+    };
+
+    for linha in &linhas {
+        let nome_paciente = linha.paciente.as_ref().map_or("Não encontrado".to_string(), |p| p.nome.clone());
+        let nome_medico = linha.medico.as_ref().map_or("Não encontrado".to_string(), |m| m.nome.clone());
+        let crm_medico = linha.medico.as_ref().map_or("Não encontrado".to_string(), |m| crm::formatar(&m.crm, &m.uf_crm));
+        let desc_exames = if linha.exames.is_empty() {
+            "Nenhum".to_string()
+        } else {
+            linha.exames.iter().map(|e| e.descricao.as_str()).collect::<Vec<_>>().join(", ")
+        };
 
         println!("--------------------------------------------------");
-        println!("Código da Consulta: {}", consulta.codigo_consulta);
+        println!("Código da Consulta: {}", linha.consulta.codigo_consulta);
         println!("Nome do Paciente: {}", nome_paciente);
-        println!("Nome da Cidade do Paciente: {}", nome_cidade);
+        if let Some(p) = &linha.paciente {
+            println!("Idade do Paciente: {}", descrever_idade(p, linha.consulta.data));
+        }
+        println!("Nome da Cidade do Paciente: {}", linha.nome_cidade);
         println!("Nome do Médico: {}", nome_medico);
-        println!("Descrição do Exame: {}", desc_exame);
-        println!("Valor a ser Pago: R$ {:.2}", valor_total);
+        println!("CRM do Médico: {}", crm_medico);
+        println!("Exames: {}", desc_exames);
+        println!("Status: {}", linha.consulta.status.descricao());
+        println!("Valor a ser Pago: R$ {:.2}", linha.valor_total);
     }
-    
+
+    let resumo = resumir(&linhas, status_faturados);
     println!("--------------------------------------------------");
     println!("--- Resumo do Relatório ---");
-    println!("Quantidade Total de Pacientes: {}", pacientes_unicos.len());
-    println!("Valor Total a ser Pago: R$ {:.2}", valor_total_a_pagar);
+    let descricoes: Vec<&str> = status_faturados.iter().map(|s| s.descricao()).collect();
+    println!("Status considerados: {}", descricoes.join(", "));
+    println!("Quantidade Total de Pacientes: {}", resumo.pacientes.len());
+    println!("Valor Total a ser Pago: R$ {:.2}", resumo.valor_total);
+    println!("--- Por Faixa Etária (idade na data da consulta) ---");
+    for faixa in FaixaEtaria::TODAS.map(Some).into_iter().chain([None]) {
+        let (quantidade, valor) = resumo.por_faixa.get(&faixa).copied().unwrap_or((0, 0.0));
+        if faixa.is_none() && quantidade == 0 {
+            continue;
+        }
+        let descricao = faixa.map_or("Sem data de nascimento", FaixaEtaria::descricao);
+        println!("{}: {} consulta(s), R$ {:.2}", descricao, quantidade, valor);
+    }
+    println!("--------------------------------------------------");
+}
+
+/// Diagnósticos mais frequentes de cada especialidade em um período.
+pub fn relatorio_diagnosticos_frequentes(db: &Database) {
+    let inicio = ler_data("Data de início (DD/MM/AAAA): ");
+    let fim = ler_data("Data de fim (DD/MM/AAAA): ");
+    let limite = match ler_u32("Quantos diagnósticos por especialidade (0 = todos): ") {
+        0 => usize::MAX,
+        n => n as usize,
+    };
+
+    let frequentes = match cid10::diagnosticos_frequentes(db, inicio, fim) {
+        Ok(f) => f,
+        Err(_) => {
+            println!("Erro ao ler registros de diagnósticos.");
+            return;
+        }
+    };
+
+    println!(
+        "\n--- Diagnósticos Mais Frequentes de {} a {} ---",
+        inicio.format("%d/%m/%Y"),
+        fim.format("%d/%m/%Y")
+    );
+    if frequentes.is_empty() {
+        println!("Nenhum diagnóstico registrado no período.");
+    }
+    for (especialidade, ranking) in frequentes {
+        println!("{}:", especialidade);
+        for (codigo, quantidade) in ranking.into_iter().take(limite) {
+            let descricao = db
+                .cids
+                .read_record(cid10::chave(&codigo))
+                .unwrap_or(None)
+                .map_or("Não encontrado no catálogo".to_string(), |c| c.descricao);
+            println!("  {} - {}: {} consulta(s)", codigo, descricao, quantidade);
+        }
+    }
     println!("--------------------------------------------------");
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::structs::especialidade::Especialidade;
+
+    fn data(ano: i32, mes: u32, dia: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(ano, mes, dia).unwrap()
+    }
+
+    #[test]
+    fn resumo_separa_faixas_e_so_fatura_os_status_configurados() {
+        let mut db = Database::em_memoria();
+        let especialidade = Especialidade {
+            codigo_especialidade: 1,
+            valor_consulta: 100.0,
+            ..Default::default()
+        };
+        db.especialidades.create_record(&especialidade, 1).unwrap();
+        let medico = Medico {
+            codigo_medico: 1,
+            codigo_especialidade: 1,
+            ..Default::default()
+        };
+        db.medicos.create_record(&medico, 1).unwrap();
+        let exame = Exame {
+            codigo_exame: 1,
+            codigo_especialidade: 1,
+            valor_exame: 20.0,
+            ..Default::default()
+        };
+        db.exames.create_record(&exame, 1).unwrap();
+        for (codigo_paciente, nascimento) in [(1, data(2015, 1, 1)), (2, data(1950, 1, 1))] {
+            let paciente = Paciente {
+                codigo_paciente,
+                data_nascimento: Some(nascimento),
+                ..Default::default()
+            };
+            db.pacientes.create_record(&paciente, codigo_paciente).unwrap();
+        }
+        // A consulta 4 é de paciente e médico que não existem mais.
+        let consultas = [
+            (1, 1, 1, StatusConsulta::Realizada),
+            (2, 2, 1, StatusConsulta::Realizada),
+            (3, 1, 1, StatusConsulta::Cancelada),
+            (4, 99, 99, StatusConsulta::Realizada),
+        ];
+        for (codigo_consulta, codigo_paciente, codigo_medico, status) in consultas.into_iter().rev() {
+            let consulta = Consulta {
+                codigo_consulta,
+                codigo_paciente,
+                codigo_medico,
+                data: data(2025, 6, 10),
+                status,
+                ..Default::default()
+            };
+            db.consultas.create_record(&consulta, codigo_consulta).unwrap();
+        }
+        exames_consulta::definir_exames(&mut db, 1, &[1]).unwrap();
+
+        let linhas = linhas_consultas(&db).unwrap();
+        let codigos: Vec<u32> = linhas.iter().map(|l| l.consulta.codigo_consulta).collect();
+        assert_eq!(codigos, vec![1, 2, 3, 4]);
+        assert_eq!(linhas[0].valor_total, 120.0);
+        assert!(linhas[3].paciente.is_none() && linhas[3].medico.is_none());
+        assert_eq!(linhas[3].valor_total, 0.0);
 
+        let resumo = resumir(&linhas, &[StatusConsulta::Realizada]);
+        assert_eq!(resumo.pacientes, HashSet::from([1, 2, 99]));
+        assert_eq!(resumo.valor_total, 220.0);
+        assert_eq!(resumo.por_faixa.get(&Some(FaixaEtaria::Pediatrica)), Some(&(2, 120.0)));
+        assert_eq!(resumo.por_faixa.get(&Some(FaixaEtaria::Geriatrica)), Some(&(1, 100.0)));
+        assert_eq!(resumo.por_faixa.get(&Some(FaixaEtaria::Adulta)), None);
+        assert_eq!(resumo.por_faixa.get(&None), Some(&(1, 0.0)));
+
+        let resumo = resumir(&linhas, &[StatusConsulta::Cancelada]);
+        assert_eq!(resumo.pacientes, HashSet::from([1]));
+        assert_eq!(resumo.valor_total, 100.0);
+    }
+}